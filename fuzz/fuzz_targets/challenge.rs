@@ -0,0 +1,32 @@
+//! Challenge codes are pasted from chats and passed on the command line. Any code that parses
+//! has to give a game that can be played.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use snake_video::challenge::Challenge;
+use snake_video::game::Game;
+use snake_video::{bot, ruleset::Ruleset};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Some(ruleset) = Ruleset::from_fingerprint(text) {
+        let fingerprint = ruleset.fingerprint();
+        assert_eq!(Ruleset::from_fingerprint(&fingerprint).map(|r| r.fingerprint()), Some(fingerprint));
+    }
+
+    let Some(challenge) = Challenge::parse(text) else {
+        return;
+    };
+
+    assert!(Challenge::parse(&challenge.code()).is_some());
+
+    let mut game = Game::new(&challenge);
+    for _ in 0..100 {
+        game.tick(bot::greedy(&game));
+    }
+});
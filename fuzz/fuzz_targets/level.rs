@@ -0,0 +1,33 @@
+//! Levels are drawn by hand and shared as files. Any level that parses has to give a game
+//! that can be played.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use snake_video::game::Game;
+use snake_video::level::Level;
+use snake_video::{bot, ruleset::Mode};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let Ok(level) = Level::parse(text) else {
+        return;
+    };
+
+    // What gets exported can be read back the same
+    let text = level.to_text();
+    assert_eq!(Level::parse(&text).map(|level| level.to_text()).ok(), Some(text));
+
+    assert_eq!(level.snake.len(), level.snake.iter().collect::<std::collections::BTreeSet<_>>().len());
+
+    for mode in [Mode::Puzzle, Mode::Classic] {
+        let mut game = Game::from_level(&level, mode, 0);
+        for _ in 0..100 {
+            game.tick(bot::greedy(&game));
+        }
+    }
+});
@@ -0,0 +1,29 @@
+//! Replay files come from other players. Whatever is in them, loading and watching one must
+//! not panic, only fail with an error.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use snake_video::player::Timeline;
+use snake_video::replay::Replay;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    let Ok(replay) = Replay::parse(text) else {
+        return;
+    };
+
+    let timeline = Timeline::new(replay);
+    timeline.markers(80);
+
+    // Seeking all the way to the end of a long replay is just slow, not interesting
+    let end = timeline.total.min(5_000);
+    for tick in [0, end / 2, end] {
+        let game = timeline.seek(tick);
+        timeline.column(game.ticks, 80);
+    }
+});
@@ -0,0 +1,223 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct Cell {
+    pub row: usize,
+    pub col: usize,
+}
+
+/// A snake tile points to the next segment of the snake towards the head. The head points to
+/// itself
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tile {
+    Empty,
+    Snake(Cell),
+    /// Only found in levels
+    Wall,
+}
+
+/// Things lying on the field. There are only a few of them on even the biggest fields, so they
+/// are not stored in the tiles
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Entity {
+    Food,
+    /// Food that has to be eaten in order, lowest number first
+    Pellet(u8),
+}
+
+/// The playing field.
+///
+/// The tiles are stored in one flat `Vec`, row after row, so even a 500x500 field is a single
+/// allocation and walking it row by row is cache friendly. The entities are kept apart in a
+/// sparse map, sorted by their index so that iterating over them is deterministic.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawBoard")]
+pub struct Board {
+    rows: usize,
+    cols: usize,
+    tiles: Vec<Tile>,
+    entities: BTreeMap<usize, Entity>,
+    /// Kept up to date with every change, see `hash`
+    #[serde(skip)]
+    hash: u64,
+}
+
+/// A board as it comes out of a file, before it's checked to make sense
+#[derive(Deserialize)]
+struct RawBoard {
+    rows: usize,
+    cols: usize,
+    tiles: Vec<Tile>,
+    entities: BTreeMap<usize, Entity>,
+}
+
+/// Spread the bits of a number all over a 64 bit key. This is the end of splitmix64
+pub(crate) fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// What a tile adds to the hash of the board. Empty tiles add nothing, so a new board has a
+/// hash of 0 whatever its size
+fn tile_key(index: usize, tile: Tile) -> u64 {
+    let index = (index as u64) << 2;
+
+    match tile {
+        Tile::Empty => 0,
+        Tile::Snake(next) => mix((index | 1) ^ mix(((next.row as u64) << 32) ^ next.col as u64)),
+        Tile::Wall => mix(index | 2),
+    }
+}
+
+fn entity_key(index: usize, entity: Entity) -> u64 {
+    let kind = match entity {
+        Entity::Food => 0,
+        Entity::Pellet(n) => 1 + n as u64,
+    };
+
+    mix((((index as u64) << 2) | 3) ^ mix(kind.wrapping_add(0x9e3779b97f4a7c15)))
+}
+
+impl TryFrom<RawBoard> for Board {
+    type Error = String;
+
+    fn try_from(raw: RawBoard) -> Result<Board, String> {
+        let mut board = Board {
+            rows: raw.rows,
+            cols: raw.cols,
+            tiles: raw.tiles,
+            entities: raw.entities,
+            hash: 0,
+        };
+
+        if board.rows.checked_mul(board.cols) != Some(board.tiles.len()) {
+            return Err(format!("a {}x{} board can't have {} tiles", board.cols, board.rows, board.tiles.len()));
+        }
+
+        if let Some(index) = board.entities.keys().find(|i| **i >= board.tiles.len()) {
+            return Err(format!("entity outside of the board at index {}", index));
+        }
+
+        // Snake tiles point to other cells, they have to stay on the board
+        for tile in board.tiles.iter() {
+            if let Tile::Snake(next) = tile {
+                if !board.contains(*next) {
+                    return Err(format!("snake pointing outside of the board at {},{}", next.row, next.col));
+                }
+            }
+        }
+
+        board.hash = board.rehash();
+        Ok(board)
+    }
+}
+
+impl Board {
+    pub fn new(rows: usize, cols: usize) -> Board {
+        Board {
+            rows,
+            cols,
+            tiles: vec![Tile::Empty; rows * cols],
+            entities: BTreeMap::new(),
+            hash: 0,
+        }
+    }
+
+    /// A hash of everything on the board. It's updated with every change, so getting it
+    /// doesn't cost anything whatever the size of the board: every tile and entity has its own
+    /// random looking key, and the hash is all of them xored together (Zobrist hashing)
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// The hash computed from scratch, which is what `hash` always has to be equal to
+    pub fn rehash(&self) -> u64 {
+        let tiles = self.tiles.iter().enumerate().map(|(i, tile)| tile_key(i, *tile));
+        let entities = self.entities.iter().map(|(i, entity)| entity_key(*i, *entity));
+
+        tiles.chain(entities).fold(0, |hash, key| hash ^ key)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn contains(&self, cell: Cell) -> bool {
+        cell.row < self.rows && cell.col < self.cols
+    }
+
+    fn index(&self, cell: Cell) -> usize {
+        cell.row * self.cols + cell.col
+    }
+
+    fn cell(&self, index: usize) -> Cell {
+        Cell {
+            row: index / self.cols,
+            col: index % self.cols,
+        }
+    }
+
+    pub fn tile(&self, cell: Cell) -> Tile {
+        self.tiles[self.index(cell)]
+    }
+
+    pub fn set_tile(&mut self, cell: Cell, tile: Tile) {
+        let index = self.index(cell);
+
+        self.hash ^= tile_key(index, self.tiles[index]) ^ tile_key(index, tile);
+        self.tiles[index] = tile;
+    }
+
+    pub fn entity(&self, cell: Cell) -> Option<Entity> {
+        self.entities.get(&self.index(cell)).copied()
+    }
+
+    pub fn put_entity(&mut self, cell: Cell, entity: Entity) {
+        let index = self.index(cell);
+
+        if let Some(old) = self.entities.insert(index, entity) {
+            self.hash ^= entity_key(index, old);
+        }
+        self.hash ^= entity_key(index, entity);
+    }
+
+    pub fn take_entity(&mut self, cell: Cell) -> Option<Entity> {
+        let index = self.index(cell);
+        let entity = self.entities.remove(&index)?;
+
+        self.hash ^= entity_key(index, entity);
+        Some(entity)
+    }
+
+    /// All the entities, row after row
+    pub fn entities(&self) -> impl Iterator<Item = (Cell, Entity)> + '_ {
+        self.entities.iter().map(|(index, entity)| (self.cell(*index), *entity))
+    }
+
+    /// Whether there is nothing at all in the cell
+    pub fn is_free(&self, cell: Cell) -> bool {
+        self.tile(cell) == Tile::Empty && self.entity(cell).is_none()
+    }
+
+    /// All the free cells, row after row
+    pub fn free_cells(&self) -> Vec<Cell> {
+        (0..self.tiles.len())
+            .filter(|i| self.tiles[*i] == Tile::Empty && !self.entities.contains_key(i))
+            .map(|i| self.cell(i))
+            .collect()
+    }
+
+    /// The tiles of one row
+    pub fn row(&self, row: usize) -> &[Tile] {
+        &self.tiles[row * self.cols..(row + 1) * self.cols]
+    }
+}
@@ -0,0 +1,197 @@
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Action;
+use crate::game::{Direction, Direction::*, Polarity::*};
+use crate::render::Frame;
+
+/// The time between two ticks, normally and while boosting
+pub const TICK: Duration = Duration::from_millis(100);
+pub const BOOSTED_TICK: Duration = Duration::from_millis(50);
+
+/// How much slower a heavy snake gets as it grows, by default: the time added to the tick for
+/// every cell of length over the two it starts with, and the slowest it gets
+pub const SLOWDOWN: Duration = Duration::from_millis(4);
+pub const SLOWEST: Duration = Duration::from_millis(300);
+
+/// The most a heavy snake can be slowed down by a cell of length, and the slowest it can ever
+/// get, in milliseconds. Any slower and it's not a game anymore
+pub const MAX_SLOWDOWN: u64 = 100;
+pub const MAX_SLOWEST: u64 = 2000;
+
+/// The length the time of a tick is counted from, with the heavy mutator
+const LIGHTEST: usize = 2;
+
+/// Terminals don't tell when a key is released, only when it's pressed, and a key that is held
+/// down is pressed again and again by the auto-repeat of the keyboard, usually every 30-50 ms.
+/// So a key counts as held if it comes again within this time, and as released once it
+/// stops coming for that long
+const REPEAT_GAP: Duration = Duration::from_millis(100);
+
+/// How many boosted ticks a full stamina bar lasts, and how many normal ticks it takes to
+/// win one back
+const STAMINA: u32 = 30;
+const RECOVERY: u32 = 3;
+
+/// The width of the stamina bar
+const BAR_WIDTH: usize = 10;
+
+/// How the time between two ticks grows with the length of the snake, with the heavy mutator.
+/// It's part of the ruleset, a slower curve pays for the same points
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Weight {
+    /// The time added for every cell of length
+    pub slowdown: Duration,
+    /// The slowest the snake gets, however long it is
+    pub slowest: Duration,
+}
+
+impl Default for Weight {
+    fn default() -> Weight {
+        Weight {
+            slowdown: SLOWDOWN,
+            slowest: SLOWEST,
+        }
+    }
+}
+
+impl Weight {
+    /// Whether the curve is one the game can be played with, see `MAX_SLOWDOWN` and `MAX_SLOWEST`
+    pub fn valid(&self) -> bool {
+        self.slowdown <= Duration::from_millis(MAX_SLOWDOWN) && (TICK..=Duration::from_millis(MAX_SLOWEST)).contains(&self.slowest)
+    }
+
+    /// The time between two ticks for a snake this long, from what it would be without any
+    /// weight. A boost makes a heavy snake just as much faster as a light one
+    pub fn tick(&self, tick: Duration, length: usize) -> Duration {
+        let slower = (TICK + self.slowdown * length.saturating_sub(LIGHTEST) as u32).min(self.slowest.max(TICK));
+        slower.mul_f64(tick.as_secs_f64() / TICK.as_secs_f64())
+    }
+}
+
+/// Whether holding the key for the action makes a snake going that way go faster: the boost
+/// key, or the key for the way it's already going. Holding any other direction only turns it
+pub fn speeds_up(action: Action, dir: Direction) -> bool {
+    match action {
+        Action::Boost => true,
+        Action::Up => dir == Ver(Neg),
+        Action::Down => dir == Ver(Pos),
+        Action::Left => dir == Hor(Neg),
+        Action::Right => dir == Hor(Pos),
+        _ => false,
+    }
+}
+
+/// Tells from the key presses whether a key is being held down
+#[derive(Default)]
+pub struct Hold {
+    last: Option<(Action, Instant)>,
+    until: Option<Instant>,
+}
+
+impl Hold {
+    /// Call every time a key for the action is pressed
+    pub fn press(&mut self, action: Action) {
+        let now = Instant::now();
+
+        match self.last {
+            Some((last, at)) if last == action && now - at < REPEAT_GAP => self.until = Some(now + REPEAT_GAP),
+            _ => self.until = None,
+        }
+
+        self.last = Some((action, now));
+    }
+
+    pub fn held(&self) -> bool {
+        self.until.is_some_and(|until| Instant::now() < until)
+    }
+}
+
+/// How much longer the snake can go fast
+pub struct Stamina {
+    left: u32,
+    recovering: u32,
+}
+
+impl Default for Stamina {
+    fn default() -> Stamina {
+        Stamina {
+            left: STAMINA,
+            recovering: 0,
+        }
+    }
+}
+
+impl Stamina {
+    /// Call once per tick, with whether the player wants to go fast. Returns how long to wait
+    /// before the next tick
+    pub fn tick(&mut self, boost: bool) -> Duration {
+        if boost && self.left > 0 {
+            self.left -= 1;
+            self.recovering = 0;
+
+            return BOOSTED_TICK;
+        }
+
+        if self.left < STAMINA {
+            self.recovering += 1;
+
+            if self.recovering == RECOVERY {
+                self.left += 1;
+                self.recovering = 0;
+            }
+        }
+
+        TICK
+    }
+
+    /// Draw the stamina bar on its own line
+    pub fn draw(&self, frame: &mut Frame) {
+        let full = (self.left as usize * BAR_WIDTH).div_ceil(STAMINA as usize);
+
+        writeln!(frame, "boost [{:#<full$}{:.<empty$}]", "", "", full = full, empty = BAR_WIDTH - full).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_heavy_snake_gets_slower_up_to_a_point() {
+        let weight = Weight::default();
+
+        assert_eq!(weight.tick(TICK, 2), TICK);
+        assert_eq!(weight.tick(TICK, 10), TICK + SLOWDOWN * 8);
+        assert_eq!(weight.tick(BOOSTED_TICK, 10), weight.tick(TICK, 10) / 2);
+        assert_eq!(weight.tick(TICK, 10_000), SLOWEST);
+    }
+
+    #[test]
+    fn only_the_way_the_snake_goes_speeds_it_up() {
+        assert!(speeds_up(Action::Boost, Hor(Neg)));
+        assert!(speeds_up(Action::Right, Hor(Pos)));
+        assert!(speeds_up(Action::Up, Ver(Neg)));
+
+        assert!(!speeds_up(Action::Left, Hor(Pos)));
+        assert!(!speeds_up(Action::Down, Hor(Pos)));
+        assert!(!speeds_up(Action::Pause, Hor(Pos)));
+    }
+
+    #[test]
+    fn a_key_counts_as_held_when_it_repeats() {
+        let mut hold = Hold::default();
+
+        hold.press(Action::Boost);
+        assert!(!hold.held());
+
+        hold.press(Action::Boost);
+        assert!(hold.held());
+
+        // Another key lets go of the first one
+        hold.press(Action::Right);
+        assert!(!hold.held());
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::board::{Cell, Tile::*};
+use crate::game::{Direction, Direction::*, Game, Polarity::*};
+use crate::ruleset::Mutator;
+
+const DIRECTIONS: [Direction; 4] = [Ver(Neg), Ver(Pos), Hor(Neg), Hor(Pos)];
+
+/// A very simple bot for headless games: go towards the closest food without dying on the next
+/// tick. It doesn't look any further, so it will eventually trap itself.
+pub fn greedy(game: &Game) -> Direction {
+    let food = game.next_food().map(|cell| (cell.row, cell.col));
+
+    let wrap = game.ruleset.has(Mutator::Wrap);
+
+    let distance = |dir: Direction| -> usize {
+        match (game.next_cell(dir), food) {
+            (Some(cell), Some((row, col))) => {
+                let rows = cell.row.abs_diff(row);
+                let cols = cell.col.abs_diff(col);
+
+                // If the field wraps around, the other way can be shorter
+                if wrap {
+                    rows.min(game.rows() - rows) + cols.min(game.cols() - cols)
+                } else {
+                    rows + cols
+                }
+            },
+            _ => usize::MAX,
+        }
+    };
+
+    DIRECTIONS
+        .iter()
+        .copied()
+        .filter(|dir| game.dir.can_turn_to(*dir) || *dir == game.dir)
+        .filter(|dir| !game.is_deadly(*dir))
+        .min_by_key(|dir| distance(*dir))
+        .unwrap_or(game.dir)
+}
+
+/// The bots there are to choose from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bot {
+    Greedy,
+    Astar,
+}
+
+impl Bot {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Bot::Greedy => "greedy",
+            Bot::Astar => "astar",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Bot> {
+        [Bot::Greedy, Bot::Astar].into_iter().find(|bot| bot.name() == name)
+    }
+
+    /// Where the bot goes next
+    pub fn play(&self, game: &Game) -> Direction {
+        match self {
+            Bot::Greedy => greedy(game),
+            Bot::Astar => astar(game),
+        }
+    }
+}
+
+/// How many ticks from now the cell stops being in the way. The segments of the snake leave
+/// one by one from the tail, as long as nothing is eaten on the way
+fn free_after(game: &Game, order: &[usize], cell: Cell) -> usize {
+    match game.field.tile(cell) {
+        Snake(_) => order[cell.row * game.cols() + cell.col] + 1,
+        Wall => usize::MAX,
+        Empty => 0,
+    }
+}
+
+/// The number of cells the snake could get to from a cell, as the field is now
+fn room(game: &Game, from: Cell) -> usize {
+    let mut seen = vec![false; game.rows() * game.cols()];
+    let mut todo = vec![from];
+    let mut count = 0;
+
+    seen[from.row * game.cols() + from.col] = true;
+
+    while let Some(cell) = todo.pop() {
+        count += 1;
+
+        for next in DIRECTIONS.iter().filter_map(|dir| game.neighbour(cell, *dir)) {
+            let i = next.row * game.cols() + next.col;
+
+            if !seen[i] && game.field.tile(next) == Empty {
+                seen[i] = true;
+                todo.push(next);
+            }
+        }
+    }
+
+    count
+}
+
+/// A bot that finds the shortest way to the food with A*, knowing that the tail gets out of
+/// the way as the snake goes. It only takes the way if there's room enough for the whole snake
+/// where it ends up, and without a way it goes where there is the most room, waiting for one.
+/// It makes for a much longer game than `greedy`, good for footage
+pub fn astar(game: &Game) -> Direction {
+    let cols = game.cols();
+    let index = |cell: Cell| cell.row * cols + cell.col;
+
+    // The number of the segment in each cell of the snake, from the tail
+    let mut order = vec![0; game.rows() * cols];
+    for (n, cell) in game.body().iter().enumerate() {
+        order[index(*cell)] = n;
+    }
+
+    let wrap = game.ruleset.has(Mutator::Wrap);
+    let estimate = |cell: Cell, food: Cell| -> usize {
+        let rows = cell.row.abs_diff(food.row);
+        let cols = cell.col.abs_diff(food.col);
+
+        if wrap {
+            rows.min(game.rows() - rows) + cols.min(game.cols() - cols)
+        } else {
+            rows + cols
+        }
+    };
+
+    let moves = || {
+        DIRECTIONS
+            .iter()
+            .copied()
+            .filter(|dir| game.dir.can_turn_to(*dir) || *dir == game.dir)
+            .filter(|dir| !game.is_deadly(*dir))
+    };
+
+    // The first move of the way to the food, if there's one
+    let way = game.next_food().and_then(|food| {
+        // (estimated length, ticks so far, cell, the first move)
+        let mut open = BinaryHeap::new();
+        let mut seen = vec![false; game.rows() * cols];
+
+        for dir in moves() {
+            let cell = game.next_cell(dir)?;
+            open.push(Reverse((1 + estimate(cell, food), 1, index(cell), dir_index(dir))));
+        }
+
+        while let Some(Reverse((_, ticks, i, first))) = open.pop() {
+            let cell = Cell { row: i / cols, col: i % cols };
+
+            if cell == food {
+                return Some(DIRECTIONS[first]);
+            }
+
+            if seen[i] {
+                continue;
+            }
+            seen[i] = true;
+
+            for next in DIRECTIONS.iter().filter_map(|dir| game.neighbour(cell, *dir)) {
+                if !seen[index(next)] && free_after(game, &order, next) <= ticks {
+                    open.push(Reverse((ticks + 1 + estimate(next, food), ticks + 1, index(next), first)));
+                }
+            }
+        }
+
+        None
+    });
+
+    let room_after = |dir: Direction| game.next_cell(dir).map_or(0, |cell| room(game, cell));
+
+    match way {
+        Some(dir) if room_after(dir) >= game.length => dir,
+        _ => moves().max_by_key(|dir| room_after(*dir)).unwrap_or(game.dir),
+    }
+}
+
+fn dir_index(dir: Direction) -> usize {
+    DIRECTIONS.iter().position(|d| *d == dir).unwrap()
+}
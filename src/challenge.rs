@@ -0,0 +1,138 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::ruleset::Ruleset;
+use crate::scores::data_dir;
+
+const CHALLENGE_FILE: &str = "challenge.txt";
+
+/// Everything needed to play the exact same run again: the same rules and the same food
+/// placement
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Challenge {
+    pub ruleset: Ruleset,
+    pub seed: u64,
+}
+
+impl Challenge {
+    /// The shareable form of the challenge, e.g. `snek1:classic/30x15/wrap:1234:1a2b3c4d`.
+    ///
+    /// The last part is the ruleset hash. It is not needed to play the challenge, but it lets
+    /// `parse` notice a string that got mangled while being copied around
+    pub fn code(&self) -> String {
+        format!("snek1:{}:{}:{:08x}", self.ruleset.fingerprint(), self.seed, self.ruleset.hash())
+    }
+
+    pub fn parse(code: &str) -> Option<Challenge> {
+        let mut parts = code.trim().split(':');
+
+        if parts.next()? != "snek1" {
+            return None;
+        }
+
+        let ruleset = Ruleset::from_fingerprint(parts.next()?)?;
+        let seed = parts.next()?.parse().ok()?;
+        let hash = u32::from_str_radix(parts.next()?, 16).ok()?;
+
+        if hash != ruleset.hash() || parts.next().is_some() {
+            return None;
+        }
+
+        Some(Challenge { ruleset, seed })
+    }
+
+    /// What the run was, on one line of the game over screen
+    pub fn stamp(&self) -> String {
+        format!("Seed: {}  Ruleset: {} (#{:08x})", self.seed, self.ruleset, self.ruleset.hash())
+    }
+
+    /// The result of the run, and how to play it again
+    fn message(&self, score: u32, length: usize, duration: Duration) -> String {
+        format!(
+            "I scored {} pts (length {}) in {}. Beat this exact run:\n\nsnake_video --challenge {}\n",
+            score,
+            length,
+            format_duration(duration),
+            self.code(),
+        )
+    }
+
+    /// Write the challenge with the result of the run to a file, so it can be pasted to a
+    /// friend. Returns the path of the file
+    pub fn save(&self, score: u32, length: usize, duration: Duration) -> io::Result<PathBuf> {
+        let dir = data_dir();
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(CHALLENGE_FILE);
+        fs::write(&path, self.message(score, length, duration))?;
+        Ok(path)
+    }
+}
+
+/// Format a duration as `m:ss`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ruleset::Mutator;
+
+    fn challenge() -> Challenge {
+        Challenge {
+            ruleset: Ruleset {
+                mutators: vec![Mutator::Wrap, Mutator::Decay],
+                ..Ruleset::default()
+            },
+            seed: 1234,
+        }
+    }
+
+    #[test]
+    fn a_challenge_comes_back_from_its_code() {
+        let code = challenge().code();
+        let back = Challenge::parse(&format!("  {}\n", code)).unwrap();
+
+        assert!(code.starts_with("snek1:classic/30x15/decay+wrap:1234:"), "{}", code);
+        assert_eq!(back.ruleset.fingerprint(), challenge().ruleset.fingerprint());
+        assert_eq!(back.seed, 1234);
+    }
+
+    #[test]
+    fn a_mangled_code_is_refused() {
+        let code = challenge().code();
+        let (start, hash) = code.rsplit_once(':').unwrap();
+
+        assert!(Challenge::parse(&format!("{}:{:08x}", start, u32::from_str_radix(hash, 16).unwrap() ^ 1)).is_none());
+        assert!(Challenge::parse(&code.replace("snek1", "snek2")).is_none());
+        assert!(Challenge::parse(&format!("{}:more", code)).is_none());
+        assert!(Challenge::parse(&code.replace("1234", "12a4")).is_none());
+    }
+
+    #[test]
+    fn the_game_over_shows_the_run() {
+        let challenge = challenge();
+
+        let ruleset = &challenge.ruleset;
+
+        assert_eq!(challenge.stamp(), format!("Seed: 1234  Ruleset: {} (#{:08x})", ruleset, ruleset.hash()));
+        assert_eq!(format_duration(Duration::from_secs(125)), "2:05");
+    }
+
+    #[test]
+    fn the_challenge_file_plays_the_same_run() {
+        let challenge = challenge();
+        let message = challenge.message(42, 9, Duration::from_secs(61));
+
+        assert!(message.starts_with("I scored 42 pts (length 9) in 1:01."), "{}", message);
+
+        let code = message.trim_end().rsplit(' ').next().unwrap();
+        assert_eq!(Challenge::parse(code).unwrap().code(), challenge.code());
+    }
+}
@@ -0,0 +1,69 @@
+use std::io;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// The version of the serialized format. Bump it whenever one of the serialized types changes
+/// in a way older versions of the game can't read anymore
+pub const VERSION: u32 = 1;
+
+/// The one way game data is written to files (and whatever else needs it): JSON, wrapped in an
+/// envelope that says what it is and which version of the format it was written with.
+///
+/// ```text
+/// {"kind":"replay","version":1,"data":{...}}
+/// ```
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    kind: &'a str,
+    version: u32,
+    data: &'a T,
+}
+
+/// The envelope without the data, to check it before looking at the data
+#[derive(Deserialize)]
+struct Header {
+    kind: String,
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Contents<T> {
+    data: T,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub fn encode<T: Serialize>(kind: &str, value: &T) -> String {
+    let envelope = Envelope {
+        kind,
+        version: VERSION,
+        data: value,
+    };
+
+    // Our types only have string keys in their maps, so this can't fail
+    serde_json::to_string(&envelope).unwrap()
+}
+
+pub fn decode<T: DeserializeOwned>(kind: &str, text: &str) -> io::Result<T> {
+    let header: Header = serde_json::from_str(text)
+        .map_err(|e| invalid(format!("not a snek {} file ({})", kind, e)))?;
+
+    if header.kind != kind {
+        return Err(invalid(format!("expected a {} file, got a {} file", kind, header.kind)));
+    }
+
+    if header.version > VERSION {
+        return Err(invalid(format!(
+            "the {} was made by a newer version of the game (format version {}, this one reads {})",
+            kind, header.version, VERSION,
+        )));
+    }
+
+    let contents: Contents<T> = serde_json::from_str(text)
+        .map_err(|e| invalid(format!("broken {} file: {}", kind, e)))?;
+
+    Ok(contents.data)
+}
@@ -0,0 +1,315 @@
+use std::fs;
+use std::io;
+
+use std::time::Duration;
+
+use console::Key;
+
+use crate::boost::{Weight, MAX_SLOWDOWN, MAX_SLOWEST, TICK};
+use crate::scores::data_dir;
+
+const CONFIG_FILE: &str = "config.txt";
+
+/// What a key does in the game
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// Go twice as fast while the key is held, holding a direction does it too
+    Boost,
+    Perf,
+    Pause,
+    /// Show the rules of the game being played, or go back to it
+    Rules,
+    /// Open the tuning console, or close it
+    Console,
+    Quit,
+}
+
+const ACTIONS: [Action; 10] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::Boost,
+    Action::Perf,
+    Action::Pause,
+    Action::Rules,
+    Action::Console,
+    Action::Quit,
+];
+
+/// The settings of a profile, from the `config.txt` file in its directory:
+///
+/// ```text
+/// # Keys, several of them can be given for the same action
+/// up = Up w
+/// down = Down s
+/// left = Left a
+/// right = Right d
+/// boost = Space
+/// perf = f
+/// pause = p
+/// rules = ?
+/// console = ~
+/// quit = Escape q
+///
+/// # With the heavy mutator, the milliseconds a tick takes longer for every cell of length,
+/// # and the longest it can take. Games played with another curve than the default one have
+/// # scores of their own
+/// heavy-slowdown = 4
+/// heavy-slowest = 300
+/// ```
+///
+/// Anything that's not in the file keeps its default. A key can only do one thing, and the
+/// directions and quit need at least one key each, a config that breaks either rule is
+/// refused with everything wrong with it.
+pub struct Config {
+    /// The keys bound to every action. A key is never bound to more than one action, `parse`
+    /// refuses a config that does that
+    pub keys: Vec<(Key, Action)>,
+    /// How much slower a heavy snake gets as it grows, in the games started with this profile
+    pub weight: Weight,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            keys: vec![
+                (Key::ArrowUp, Action::Up),
+                (Key::ArrowDown, Action::Down),
+                (Key::ArrowLeft, Action::Left),
+                (Key::ArrowRight, Action::Right),
+                (Key::Char(' '), Action::Boost),
+                (Key::Char('f'), Action::Perf),
+                (Key::Char('p'), Action::Pause),
+                (Key::Char('?'), Action::Rules),
+                (Key::Char('~'), Action::Console),
+                (Key::Escape, Action::Quit),
+            ],
+            weight: Weight::default(),
+        }
+    }
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Boost => "boost",
+            Action::Perf => "perf",
+            Action::Pause => "pause",
+            Action::Rules => "rules",
+            Action::Console => "console",
+            Action::Quit => "quit",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// The name of a key in the config file: a single character stands for itself, the other
+/// keys have names
+pub fn key_from_name(name: &str) -> Option<Key> {
+    let mut chars = name.chars();
+
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(Key::Char(ch));
+    }
+
+    match name {
+        "Up" => Some(Key::ArrowUp),
+        "Down" => Some(Key::ArrowDown),
+        "Left" => Some(Key::ArrowLeft),
+        "Right" => Some(Key::ArrowRight),
+        "Escape" => Some(Key::Escape),
+        "Enter" => Some(Key::Enter),
+        "Space" => Some(Key::Char(' ')),
+        "Tab" => Some(Key::Tab),
+        "Backspace" => Some(Key::Backspace),
+        "Home" => Some(Key::Home),
+        "End" => Some(Key::End),
+        _ => None,
+    }
+}
+
+/// The name of a key, the way `key_from_name` reads it. Keys that can't be bound get their
+/// name from `console`
+pub fn key_name(key: &Key) -> String {
+    match key {
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(ch) => ch.to_string(),
+        Key::ArrowUp => "Up".to_string(),
+        Key::ArrowDown => "Down".to_string(),
+        Key::ArrowLeft => "Left".to_string(),
+        Key::ArrowRight => "Right".to_string(),
+        Key::Escape => "Escape".to_string(),
+        Key::Enter => "Enter".to_string(),
+        Key::Tab => "Tab".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{} line {}: {}", CONFIG_FILE, line, msg))
+}
+
+impl Config {
+    /// Load the config of the profile in use. Without a config file, everything is default
+    pub fn load() -> io::Result<Config> {
+        match fs::read_to_string(data_dir().join(CONFIG_FILE)) {
+            Ok(text) => Config::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn parse(text: &str) -> io::Result<Config> {
+        let mut config = Config::default();
+
+        // The line each action got its keys on, the default ones are on line 0
+        let mut lines: Vec<(Action, usize)> = ACTIONS.iter().map(|action| (*action, 0)).collect();
+
+        for (n, line) in text.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = line.split_once('=').ok_or_else(|| invalid(n, "expected `action = keys`"))?;
+
+            let millis = |min: u64, max: u64| {
+                value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|ms| (min..=max).contains(ms))
+                    .map(Duration::from_millis)
+                    .ok_or_else(|| invalid(n, &format!("expected {} to {} milliseconds", min, max)))
+            };
+
+            match name.trim() {
+                "heavy-slowdown" => {
+                    config.weight.slowdown = millis(0, MAX_SLOWDOWN)?;
+                    continue;
+                },
+                "heavy-slowest" => {
+                    config.weight.slowest = millis(TICK.as_millis() as u64, MAX_SLOWEST)?;
+                    continue;
+                },
+                _ => {},
+            }
+
+            let action = Action::from_name(name.trim()).ok_or_else(|| invalid(n, "unknown action"))?;
+
+            let keys = value
+                .split_whitespace()
+                .map(key_from_name)
+                .collect::<Option<Vec<Key>>>()
+                .ok_or_else(|| invalid(n, "unknown key"))?;
+
+            // The keys given replace the default ones of the action
+            config.keys.retain(|(_, a)| *a != action);
+            config.keys.extend(keys.into_iter().map(|key| (key, action)));
+
+            for (a, line) in lines.iter_mut() {
+                if *a == action {
+                    *line = n;
+                }
+            }
+        }
+
+        let line_of = |action: Action| lines.iter().find(|(a, _)| *a == action).map_or(0, |(_, line)| *line);
+        let mut problems = Vec::new();
+
+        for (i, (key, action)) in config.keys.iter().enumerate() {
+            let Some((_, other)) = config.keys[..i].iter().find(|(k, a)| k == key && a != action) else {
+                continue;
+            };
+
+            // At least one of the two is in the file, that's where it has to be fixed
+            let (line, default) = match (line_of(*other), line_of(*action)) {
+                (0, line) => (line, format!(" ({} has it by default)", other.name())),
+                (line, 0) => (line, format!(" ({} has it by default)", action.name())),
+                (a, b) => (a.max(b), String::new()),
+            };
+
+            problems.push(format!(
+                "{} line {}: {} is bound to both {} and {}{}",
+                CONFIG_FILE,
+                line,
+                key_name(key),
+                other.name(),
+                action.name(),
+                default,
+            ));
+        }
+
+        for action in [Action::Up, Action::Down, Action::Left, Action::Right, Action::Quit] {
+            if !config.keys.iter().any(|(_, a)| *a == action) {
+                problems.push(format!("{} line {}: nothing is bound to {}", CONFIG_FILE, line_of(action), action.name()));
+            }
+        }
+
+        if !problems.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, problems.join("\n")));
+        }
+
+        Ok(config)
+    }
+
+    /// What the key does, if anything
+    pub fn action(&self, key: &Key) -> Option<Action> {
+        self.keys.iter().find(|(k, _)| k == key).map(|(_, action)| *action)
+    }
+
+    /// The name of the first key bound to the action, to tell the player which one to press
+    pub fn key_of(&self, action: Action) -> Option<String> {
+        self.keys.iter().find(|(_, a)| *a == action).map(|(key, _)| key_name(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_can_be_remapped() {
+        let config = Config::parse("up = k\ndown = j\nleft = h\nright = l\n").unwrap();
+
+        assert_eq!(config.action(&Key::Char('k')), Some(Action::Up));
+        assert_eq!(config.action(&Key::Char('l')), Some(Action::Right));
+        assert_eq!(config.action(&Key::ArrowUp), None);
+        assert_eq!(config.action(&Key::Escape), Some(Action::Quit));
+    }
+
+    #[test]
+    fn a_key_for_two_actions_is_refused() {
+        let e = Config::parse("# p pauses\nup = p\n").err().unwrap().to_string();
+
+        assert_eq!(e, "config.txt line 2: p is bound to both pause and up (pause has it by default)");
+    }
+
+    #[test]
+    fn an_action_without_a_key_is_refused() {
+        let e = Config::parse("left = a\nup =\n").err().unwrap().to_string();
+
+        assert_eq!(e, "config.txt line 2: nothing is bound to up");
+    }
+
+    #[test]
+    fn everything_wrong_is_listed() {
+        let e = Config::parse("up = f\nquit =\n").err().unwrap().to_string();
+
+        assert_eq!(e.lines().count(), 2, "{}", e);
+    }
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::codec;
+use crate::game::{Direction, Game};
+use crate::replay::Replay;
+use crate::scores::data_dir;
+
+const CRASHES_DIR: &str = "crashes";
+
+/// What gets written when the engine panics: the replay up to the crash, which is enough to
+/// make it happen again, plus the game as it was left by the panic and the direction of the
+/// tick that crashed
+#[derive(Serialize)]
+struct CrashDump<'a> {
+    replay: &'a Replay,
+    game: &'a Game,
+    dir: Direction,
+}
+
+/// Write a crash dump to the crashes directory and return its path
+pub fn dump(replay: &Replay, game: &Game, dir: Direction) -> io::Result<PathBuf> {
+    let dir_path = data_dir().join(CRASHES_DIR);
+    fs::create_dir_all(&dir_path)?;
+
+    let path = dir_path.join(format!("crash-{}-{}.json", replay.challenge.seed, game.ticks));
+    let dump = CrashDump { replay, game, dir };
+    fs::write(&path, codec::encode("crash", &dump))?;
+
+    Ok(path)
+}
@@ -0,0 +1,170 @@
+use crate::board::{Cell, Tile};
+use crate::game::Game;
+
+/// How close the body has to be straight ahead for the warning, in cells
+const BODY_AHEAD: usize = 2;
+
+/// Something worth hearing about. Each one rings the terminal bell its own way, one step of its
+/// pattern a tick, so they can be told apart without looking
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Cue {
+    /// There's food straight ahead of the snake: one ring
+    Food,
+    /// The snake went off one side of the field and came back on the other: two rings with a
+    /// tick between them
+    Wrap,
+    /// The snake is about to run into itself: three rings in a row
+    Body,
+}
+
+impl Cue {
+    fn pattern(&self) -> &'static [bool] {
+        match self {
+            Cue::Food => &[true],
+            Cue::Wrap => &[true, false, true],
+            Cue::Body => &[true, true, true],
+        }
+    }
+}
+
+/// Sound cues for players who can't see the field well. A cue only comes when something
+/// changes, food that stays ahead of the snake rings once and not on every tick
+#[derive(Default)]
+pub struct Cues {
+    /// What's left to ring of the last cue
+    playing: &'static [bool],
+    playing_cue: Option<Cue>,
+    head: Option<Cell>,
+    food_ahead: bool,
+    body_ahead: bool,
+}
+
+/// Whether there's food on the cells the snake goes through going straight on, before it hits
+/// anything
+fn food_ahead(game: &Game) -> bool {
+    let mut cell = game.head;
+
+    // On a field that wraps, the line goes all the way around back to the head
+    for _ in 0..game.rows().max(game.cols()) {
+        cell = match game.neighbour(cell, game.dir) {
+            Some(cell) if game.field.tile(cell) == Tile::Empty => cell,
+            _ => return false,
+        };
+
+        if game.field.entity(cell).is_some() {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Whether the snake's body is in the next few cells straight ahead
+fn body_ahead(game: &Game) -> bool {
+    let mut cell = game.head;
+
+    for _ in 0..BODY_AHEAD {
+        cell = match game.neighbour(cell, game.dir) {
+            Some(cell) => cell,
+            None => return false,
+        };
+
+        match game.field.tile(cell) {
+            Tile::Snake(_) => return true,
+            Tile::Wall => return false,
+            Tile::Empty => {},
+        }
+    }
+
+    false
+}
+
+impl Cues {
+    /// Look at the game after a tick. Returns the cue that starts with this tick, if any
+    pub fn update(&mut self, game: &Game) -> Option<Cue> {
+        // Anything else than the next cell over is the other side of the field
+        let wrapped = self.head.is_some_and(|head| head.row.abs_diff(game.head.row) + head.col.abs_diff(game.head.col) > 1);
+        self.head = Some(game.head);
+
+        let food = food_ahead(game);
+        let body = body_ahead(game);
+
+        let cue = [
+            (body && !self.body_ahead, Cue::Body),
+            (wrapped, Cue::Wrap),
+            (food && !self.food_ahead, Cue::Food),
+        ]
+        .into_iter()
+        .find_map(|(new, cue)| new.then_some(cue));
+
+        self.food_ahead = food;
+        self.body_ahead = body;
+
+        // A cue doesn't cut off a more important one that's still ringing
+        let cue = cue.filter(|cue| self.playing.is_empty() || self.playing_cue.is_none_or(|playing| *cue >= playing));
+        if let Some(cue) = cue {
+            self.playing = cue.pattern();
+            self.playing_cue = Some(cue);
+        }
+
+        cue
+    }
+
+    /// Whether the bell rings on this tick
+    pub fn ring(&mut self) -> bool {
+        match self.playing.split_first() {
+            Some((ring, rest)) => {
+                self.playing = rest;
+                *ring
+            },
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::{small, CORNERED};
+    use crate::game::{Direction::*, Polarity::*};
+    use crate::ruleset::Mutator;
+
+    fn rings(cues: &mut Cues, ticks: usize) -> Vec<bool> {
+        (0..ticks).map(|_| cues.ring()).collect()
+    }
+
+    #[test]
+    fn food_ahead_rings_once() {
+        // The food is in the corner, across the edge the snake is going to
+        let mut game = small(Mutator::Wrap, &[(1, 0), (2, 0)]);
+        let mut cues = Cues::default();
+
+        assert_eq!(cues.update(&game), Some(Cue::Food));
+        assert_eq!(rings(&mut cues, 2), [true, false]);
+
+        game.tick(Ver(Pos));
+        assert_eq!(cues.update(&game), None);
+    }
+
+    #[test]
+    fn going_around_the_field_rings_twice() {
+        let mut game = small(Mutator::Wrap, &[(3, 1), (4, 1)]);
+        let mut cues = Cues::default();
+
+        assert_eq!(cues.update(&game), None);
+
+        game.tick(Ver(Pos));
+        assert_eq!(game.head, Cell { row: 0, col: 1 });
+        assert_eq!(cues.update(&game), Some(Cue::Wrap));
+        assert_eq!(rings(&mut cues, 4), [true, false, true, false]);
+    }
+
+    #[test]
+    fn the_body_ahead_rings_three_times() {
+        let game = small(Mutator::Hint, &CORNERED);
+        let mut cues = Cues::default();
+
+        assert_eq!(cues.update(&game), Some(Cue::Body));
+        assert_eq!(rings(&mut cues, 4), [true, true, true, false]);
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+
+use crate::boost;
+use crate::bot::Bot;
+use crate::challenge::Challenge;
+use crate::game::Game;
+use crate::gif;
+use crate::pixels::{Canvas, PALETTE};
+
+/// The last frame stays up this long before the GIF starts over, in hundredths of a second
+const LAST_FRAME: u16 = 300;
+
+/// What came out of a demo
+pub struct Demo {
+    pub frames: usize,
+    pub score: u32,
+    pub length: usize,
+}
+
+/// Let the bot play the challenge for up to `seconds`, at the pace of the game, and keep every
+/// tick as a frame of a GIF. The GIF stops at the game over if the bot dies before that
+pub fn record(challenge: &Challenge, bot: Bot, seconds: u64, path: &Path) -> io::Result<Demo> {
+    let mut game = Game::new(challenge);
+
+    let mut canvas = Canvas::new(&game, true);
+
+    let out = BufWriter::new(File::create(path)?);
+    let mut gif = gif::Encoder::new(out, canvas.width as u16, canvas.height as u16, &PALETTE)?;
+
+    let delay = (boost::TICK.as_millis() / 10) as u16;
+    let ticks = seconds * 1000 / boost::TICK.as_millis() as u64;
+    let mut frames = 0;
+
+    loop {
+        canvas.draw(&game);
+
+        let last = game.over || game.ticks >= ticks;
+        gif.frame(&canvas.pixels, if last { LAST_FRAME } else { delay })?;
+        frames += 1;
+
+        if last {
+            break;
+        }
+
+        game.tick(bot.play(&game));
+    }
+
+    gif.finish()?;
+
+    Ok(Demo {
+        frames,
+        score: game.score,
+        length: game.length,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::ruleset::Ruleset;
+
+    #[test]
+    fn a_second_of_the_bot_is_a_frame_a_tick() {
+        let path = std::env::temp_dir().join(format!("snake-video-demo-{}.gif", std::process::id()));
+        let challenge = Challenge {
+            ruleset: Ruleset::default(),
+            seed: 7,
+        };
+
+        let demo = record(&challenge, Bot::Astar, 1, &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        let _ = fs::remove_file(&path);
+
+        // The first state, then one for every tick
+        assert_eq!(demo.frames, 11);
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::{self, Write as _};
+use std::io;
+use std::path::PathBuf;
+use std::process::exit;
+use std::sync::Mutex;
+use std::thread;
+
+use console::{Key, Term};
+
+use crate::render::Frame;
+use crate::title;
+
+/// Everything that can go wrong while the game runs, worded so the player can do something
+/// about it
+#[derive(Debug)]
+pub enum Error {
+    /// Drawing on the terminal or reading the keys failed
+    Terminal(io::Error),
+    /// The config file of the profile is missing something or couldn't be read
+    Config(io::Error),
+    /// The high score file couldn't be read or written
+    Scores(io::Error),
+    /// The hot-seat rounds couldn't be read or written
+    Rivalry(io::Error),
+    /// Something kept after a game over couldn't be written, e.g. the replay
+    Save(&'static str, io::Error),
+    /// The replay to watch couldn't be read
+    Replay(PathBuf, io::Error),
+    /// The level to play couldn't be read
+    Level(PathBuf, io::Error),
+    /// The level pack couldn't be read
+    Pack(PathBuf, io::Error),
+    /// The scenario to play couldn't be read
+    Scenario(PathBuf, io::Error),
+    /// A level pack couldn't be downloaded and installed
+    Download(String, io::Error),
+    /// The input trace to play couldn't be read
+    Trace(PathBuf, io::Error),
+    /// The photo finishes couldn't be read
+    Photos(io::Error),
+    /// The captions of a replay couldn't be read or written
+    Subtitles(PathBuf, io::Error),
+    /// The game being spectated couldn't be followed
+    Spectate(io::Error),
+    /// The engine panicked, with where the crash dump went
+    Crash(io::Result<PathBuf>),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Terminal(e) => write!(f, "couldn't use the terminal: {}", e),
+            Error::Config(e) => write!(f, "bad config: {}", e),
+            Error::Scores(e) => write!(f, "couldn't read or write the high score file: {}", e),
+            Error::Rivalry(e) => write!(f, "couldn't read or write the hot-seat rounds: {}", e),
+            Error::Save(what, e) => write!(f, "couldn't save the {}: {}", what, e),
+            Error::Replay(path, e) => write!(f, "couldn't load the replay {}: {}", path.display(), e),
+            Error::Level(path, e) => write!(f, "couldn't load the level {}: {}", path.display(), e),
+            Error::Pack(path, e) => write!(f, "couldn't load the level pack {}: {}", path.display(), e),
+            Error::Scenario(path, e) => write!(f, "couldn't load the scenario {}: {}", path.display(), e),
+            Error::Download(url, e) => write!(f, "couldn't install the pack from {}: {}", url, e),
+            Error::Trace(path, e) => write!(f, "couldn't load the input trace {}: {}", path.display(), e),
+            Error::Photos(e) => write!(f, "couldn't read the photo finishes: {}", e),
+            Error::Subtitles(path, e) => write!(f, "couldn't use the captions file {}: {}", path.display(), e),
+            Error::Spectate(e) => write!(f, "couldn't watch the game: {}", e),
+            Error::Crash(Ok(path)) => write!(f, "the game crashed, a crash dump was saved to {}", path.display()),
+            Error::Crash(Err(e)) => write!(f, "the game crashed, and the crash dump couldn't be saved: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Terminal(e)
+            | Error::Config(e)
+            | Error::Scores(e)
+            | Error::Rivalry(e)
+            | Error::Save(_, e)
+            | Error::Replay(_, e)
+            | Error::Level(_, e)
+            | Error::Pack(_, e)
+            | Error::Scenario(_, e)
+            | Error::Download(_, e)
+            | Error::Trace(_, e)
+            | Error::Photos(e)
+            | Error::Subtitles(_, e)
+            | Error::Spectate(e)
+            | Error::Crash(Err(e)) => Some(e),
+            Error::Crash(Ok(_)) => None,
+        }
+    }
+}
+
+impl Error {
+    /// The exit code of the process, following the `sysexits.h` convention
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Replay(_, e) | Error::Level(_, e) | Error::Pack(_, e) | Error::Scenario(_, e) | Error::Trace(_, e)
+                if e.kind() == io::ErrorKind::NotFound => 66,
+            Error::Replay(..) | Error::Level(..) | Error::Pack(..) | Error::Scenario(..) | Error::Trace(..) => 65,
+            Error::Subtitles(_, e) if e.kind() == io::ErrorKind::InvalidData => 65,
+            Error::Subtitles(..) | Error::Photos(_) => 74,
+            Error::Download(_, e) if e.kind() == io::ErrorKind::InvalidData => 65,
+            Error::Scores(_) | Error::Rivalry(_) | Error::Save(..) => 73,
+            Error::Download(_, e) if e.kind() == io::ErrorKind::AlreadyExists => 73,
+            Error::Download(..) => 69,
+            Error::Spectate(_) => 69,
+            Error::Config(_) => 78,
+            Error::Terminal(_) => 74,
+            Error::Crash(_) => 101,
+        }
+    }
+}
+
+/// Print the error and quit
+pub fn report(err: Error) -> ! {
+    eprintln!("snake_video: {}", err);
+    exit(err.exit_code())
+}
+
+/// Show the error on a screen of its own and quit once a key is pressed. For what's wrong with
+/// the way the game is set up, which would scroll away unseen once the game starts otherwise.
+/// Without a terminal to show it on, it's just printed
+pub fn screen(term: &Term, err: Error) -> ! {
+    if !term.is_term() {
+        report(err);
+    }
+
+    show(&err);
+    let _ = term.read_key();
+
+    report(err)
+}
+
+/// Draw the error on the whole screen, waiting for a key
+fn show(err: &Error) {
+    let mut frame = Frame::default();
+    frame.begin();
+
+    let text = err.to_string();
+    let mut lines = text.lines();
+
+    let _ = writeln!(frame, "{}", lines.next().unwrap_or_default());
+    for line in lines {
+        let _ = writeln!(frame, "  {}", line);
+    }
+
+    let _ = writeln!(frame);
+    let _ = writeln!(frame, "Press any key to quit");
+
+    // Whatever goes wrong here, the error itself still gets printed
+    let _ = frame.present(&mut io::stdout());
+}
+
+/// Leave the screen of the game, then print the error and quit. Used once the game has taken
+/// over the terminal, only by the thread reading the keys and not while it waits for one:
+/// console keeps the terminal raw for as long as a read goes on, and only puts it back when
+/// the key comes. Other threads use `hand_over`
+pub fn fail(term: &Term, err: Error) -> ! {
+    // The terminal itself may be what failed, so there is no point in checking
+    let _ = title::pop(term);
+
+    eprintln!();
+    report(err)
+}
+
+/// The error a thread that doesn't read the keys ran into, for the one that does
+static HANDED_OVER: Mutex<Option<Error>> = Mutex::new(None);
+
+/// Fail from a thread that draws while another one reads the keys. Quitting right away would
+/// leave the shell without echo, the other thread being in the middle of a read. So the error
+/// is shown instead, and the thread reading the keys fails with it once the next key comes
+/// (see `read_key`). This thread has nothing left to do until then
+pub fn hand_over(err: Error) -> ! {
+    show(&err);
+    *HANDED_OVER.lock().unwrap() = Some(err);
+
+    loop {
+        thread::park();
+    }
+}
+
+/// Wait for a key, in the thread reading them while others draw. Quits if it can't, or if
+/// another thread handed an error over in the meantime
+pub fn read_key(term: &Term) -> Key {
+    let key = term.read_key().unwrap_or_else(|e| fail(term, Error::Terminal(e)));
+
+    if let Some(err) = HANDED_OVER.lock().unwrap().take() {
+        fail(term, err);
+    }
+
+    key
+}
@@ -0,0 +1,149 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, ErrorKind, Read};
+use std::path::PathBuf;
+
+use sha2::{Digest, Sha256};
+
+use crate::pack::{self, Pack};
+use crate::profile;
+
+/// No level pack is anywhere near this big. It's there so a wrong URL can't fill the disk
+const MAX_DOWNLOAD: u64 = 32 * 1024 * 1024;
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Everything at the URL, as long as it's not too big
+fn download(url: &str) -> io::Result<Vec<u8>> {
+    let response = match ureq::get(url).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Err(io::Error::new(ErrorKind::NotFound, format!("{} not found", url))),
+        Err(ureq::Error::Status(code, _)) => return Err(io::Error::other(format!("{} gave HTTP {}", url, code))),
+        Err(e) => return Err(io::Error::other(e.to_string())),
+    };
+
+    let mut data = Vec::new();
+    response.into_reader().take(MAX_DOWNLOAD + 1).read_to_end(&mut data)?;
+
+    if data.len() as u64 > MAX_DOWNLOAD {
+        return Err(invalid(format!("{} is too big for a level pack", url)));
+    }
+
+    Ok(data)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02x}", byte).unwrap();
+        hex
+    })
+}
+
+/// Make sure the data is what the checksum says, in hex as `sha256sum` writes it: a checksum
+/// that isn't one is refused before anything is compared
+fn verify(data: &[u8], checksum: &str) -> io::Result<()> {
+    let expected = checksum.trim();
+
+    if expected.len() != 64 || !expected.chars().all(|ch| ch.is_ascii_hexdigit()) {
+        return Err(invalid(format!("{} isn't a SHA-256 checksum", expected)));
+    }
+
+    let actual = hex(&Sha256::digest(data));
+
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(invalid(format!("the checksum doesn't match: expected {}, got {}", expected, actual)));
+    }
+
+    Ok(())
+}
+
+/// The name the pack gets installed as, from the end of the URL: `.../winter.zip` is `winter`
+fn name_from_url(url: &str) -> Option<&str> {
+    let file = url.split(['?', '#']).next()?.rsplit('/').next()?;
+    Some(file.strip_suffix(".zip").unwrap_or(file))
+}
+
+/// Download a level pack (a zip file) and install it with the other packs. Returns where it
+/// was installed.
+///
+/// The download has to have the SHA-256 checksum given, or if there's none the one published
+/// next to it as `URL.sha256` (the output of `sha256sum` will do). Packs go around in chats
+/// and forums, and that's what makes sure the pack is the one its author put up.
+pub fn pack(url: &str, checksum: Option<&str>, name: Option<&str>) -> io::Result<PathBuf> {
+    let name = match name.or_else(|| name_from_url(url)) {
+        Some(name) if profile::valid_name(name) => name,
+        _ => return Err(invalid("the pack needs a name of letters, digits, - and _, give one with --name".to_string())),
+    };
+
+    let dir = pack::dir();
+    let path = dir.join(format!("{}.zip", name));
+
+    if path.exists() || dir.join(name).exists() {
+        return Err(io::Error::new(ErrorKind::AlreadyExists, format!("there is already a pack called {}", name)));
+    }
+
+    let expected = match checksum {
+        Some(checksum) => checksum.to_string(),
+        None => {
+            let sidecar = download(&format!("{}.sha256", url)).map_err(|e| match e.kind() {
+                ErrorKind::NotFound => invalid(format!("{}.sha256 not found, give the checksum with --sha256", url)),
+                _ => e,
+            })?;
+
+            String::from_utf8_lossy(&sidecar).split_whitespace().next().unwrap_or_default().to_string()
+        },
+    };
+
+    let data = download(url)?;
+    verify(&data, &expected)?;
+
+    // Only install packs that can be played
+    fs::create_dir_all(&dir)?;
+    let part = dir.join(format!(".{}.zip.part", name));
+    fs::write(&part, &data)?;
+
+    if let Err(e) = Pack::load(&part) {
+        let _ = fs::remove_file(&part);
+        return Err(e);
+    }
+
+    fs::rename(&part, &path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn the_right_checksum_passes() {
+        assert!(verify(b"abc", ABC).is_ok());
+        assert!(verify(b"abc", &format!("{}\n", ABC.to_uppercase())).is_ok());
+    }
+
+    #[test]
+    fn another_checksum_is_refused() {
+        let e = verify(b"abd", ABC).unwrap_err();
+
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        assert!(e.to_string().starts_with(&format!("the checksum doesn't match: expected {}, got ", ABC)), "{}", e);
+    }
+
+    #[test]
+    fn a_checksum_has_to_be_one() {
+        for checksum in ["", "abc", &ABC[1..], &ABC.replace('b', "g")] {
+            let e = verify(b"abc", checksum).unwrap_err();
+            assert_eq!(e.to_string(), format!("{} isn't a SHA-256 checksum", checksum));
+        }
+    }
+
+    #[test]
+    fn the_pack_is_named_after_the_url() {
+        assert_eq!(name_from_url("https://example.com/packs/winter.zip?download=1"), Some("winter"));
+        assert_eq!(name_from_url("https://example.com/packs/"), Some(""));
+    }
+}
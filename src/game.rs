@@ -0,0 +1,867 @@
+use rand::prelude::*;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::board::{mix, Board, Cell, Entity, Tile::*};
+use crate::boost::Weight;
+use crate::challenge::Challenge;
+use crate::level::Level;
+use crate::rng::{self, Stream};
+use crate::ruleset::{is_zero, Mode, Mutator, Ruleset};
+
+use Direction::*;
+use Event::*;
+use Polarity::*;
+
+/// How many numbered pellets a round of the sequence mode has
+const SEQUENCE_LENGTH: u8 = 5;
+
+/// How long the snake is before it eats anything, outside of levels. It only ever grows by
+/// eating, a cell per piece of food
+pub const START_LENGTH: usize = 2;
+
+/// What food is worth right when it comes, with the decay mutator
+pub const FOOD_VALUE: u32 = 5;
+/// How many ticks it takes food to lose a point, with the decay mutator
+pub const DECAY_TICKS: u64 = 30;
+/// How many cells of length make food worth a point more, with the heavy mutator
+pub const HEAVY_CELLS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Polarity {
+    Pos,
+    Neg,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Hor(Polarity),
+    Ver(Polarity),
+}
+
+/// Something noteworthy that happened during a tick
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Event {
+    Ate,
+    /// A pellet was eaten before one with a lower number
+    WrongOrder,
+    /// Going on straight would have killed the snake, but it turned just in time
+    NearMiss,
+    Died,
+}
+
+/// The whole state of a game. Everything that happens in a game comes from the ruleset, the
+/// seed and the directions passed to `tick`, so two games created from the same challenge and
+/// fed with the same directions always end up the same way
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawGame")]
+pub struct Game {
+    pub ruleset: Ruleset,
+    pub field: Board,
+    pub head: Cell,
+    pub tail: Cell,
+    pub dir: Direction,
+    /// Every piece of food eaten is worth one point, or more while it's fresh with the decay
+    /// mutator
+    pub score: u32,
+    pub length: usize,
+    /// How many ticks were played so far
+    pub ticks: u64,
+    pub over: bool,
+    /// How many ticks in a row the head has been held up against the body, out of the grace
+    /// ticks of the ruleset
+    #[serde(skip_serializing_if = "is_zero")]
+    pub stalled: u32,
+    /// With the decay mutator, the tick the food on the field was put there. There's only ever
+    /// one piece of food, or a round of pellets put there all at once, or the food of a level
+    /// that was there from the start, so that's the age of all of it
+    #[serde(skip_serializing_if = "is_zero")]
+    pub food_since: u64,
+    // This is the same generator as `StdRng`, but this one can tell where it is in its stream,
+    // which is what makes snapshots possible. Only the food is placed with it, see `rng`
+    rng: ChaCha12Rng,
+}
+
+/// Everything needed to restore a game at some tick without playing it from the start
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub ticks: u64,
+    /// The position of the random generator in its stream
+    pub rng_pos: u128,
+    pub dir: Direction,
+    pub score: u32,
+    pub food: Vec<Cell>,
+    /// The numbered pellets of the sequence mode. Older replays don't have them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pellets: Vec<(u8, Cell)>,
+    /// From the tail to the head
+    pub body: Vec<Cell>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub stalled: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub food_since: u64,
+}
+
+/// A game as it comes out of a file, before it's checked to make sense
+#[derive(Deserialize)]
+struct RawGame {
+    ruleset: Ruleset,
+    field: Board,
+    head: Cell,
+    tail: Cell,
+    dir: Direction,
+    score: u32,
+    length: usize,
+    ticks: u64,
+    over: bool,
+    #[serde(default)]
+    stalled: u32,
+    #[serde(default)]
+    food_since: u64,
+    rng: ChaCha12Rng,
+}
+
+impl TryFrom<RawGame> for Game {
+    type Error = String;
+
+    fn try_from(raw: RawGame) -> Result<Game, String> {
+        if raw.field.rows() != raw.ruleset.rows || raw.field.cols() != raw.ruleset.cols {
+            return Err("the board doesn't have the size of the ruleset".to_string());
+        }
+
+        if !raw.field.contains(raw.head) || !raw.field.contains(raw.tail) {
+            return Err("the snake is outside of the board".to_string());
+        }
+
+        Ok(Game {
+            ruleset: raw.ruleset,
+            field: raw.field,
+            head: raw.head,
+            tail: raw.tail,
+            dir: raw.dir,
+            score: raw.score,
+            length: raw.length,
+            ticks: raw.ticks,
+            over: raw.over,
+            stalled: raw.stalled,
+            food_since: raw.food_since,
+            rng: raw.rng,
+        })
+    }
+}
+
+impl Direction {
+    /// The snake can't turn around on the spot. If it moves horizontally, we only can change its
+    /// direction to vertical and the other way around.
+    pub fn can_turn_to(&self, dir: Direction) -> bool {
+        !matches!((self, dir), (Hor(_), Hor(_)) | (Ver(_), Ver(_)))
+    }
+
+    /// A quarter turn to the right, as seen from above: up becomes right
+    pub fn clockwise(&self) -> Direction {
+        match self {
+            Ver(Neg) => Hor(Pos),
+            Hor(Pos) => Ver(Pos),
+            Ver(Pos) => Hor(Neg),
+            Hor(Neg) => Ver(Neg),
+        }
+    }
+
+    pub fn counterclockwise(&self) -> Direction {
+        self.clockwise().clockwise().clockwise()
+    }
+}
+
+// This function is used to increase or decrease the horizontal or vertical position of the
+// snake's head on the field.
+//
+// p - current position
+// pol - polarity (negative or positive)
+// lim - maximum value. We will use the number of columns or rows here
+// wrap - whether the snake comes back on the other side of the field. If it doesn't, `None` is
+//        returned when the snake leaves the field
+fn step(p: usize, pol: Polarity, lim: usize, wrap: bool) -> Option<usize> {
+    match pol {
+        Pos => if p == lim - 1 {
+            wrap.then_some(0)
+        } else {
+            Some(p + 1)
+        },
+        Neg => if p == 0 {
+            wrap.then_some(lim - 1)
+        } else {
+            Some(p - 1)
+        },
+    }
+}
+
+impl Game {
+    pub fn new(challenge: &Challenge) -> Game {
+        let ruleset = challenge.ruleset.clone();
+        let rows = ruleset.rows;
+        let cols = ruleset.cols;
+
+        let mut field = Board::new(rows, cols);
+
+        let head = Cell {
+            row: rows / 2,
+            col: cols / 2
+        };
+
+        field.set_tile(head, Snake(head));
+
+        let tail = Cell {
+            row: head.row,
+            col: head.col - 1
+        };
+
+        field.set_tile(tail, Snake(head));
+
+        let mut game = Game {
+            ruleset,
+            field,
+            head,
+            tail,
+            dir: Hor(Pos),
+            score: 0,
+            length: START_LENGTH,
+            ticks: 0,
+            over: false,
+            stalled: 0,
+            food_since: 0,
+            // All the randomness of a run comes from the seed, so the same seed with the same
+            // ruleset gives the same food placement
+            rng: rng::stream(challenge.seed, Stream::Food),
+        };
+
+        // Place the first food on the field
+        game.spawn_food();
+
+        game
+    }
+
+    /// Restore a game from a snapshot taken during a game with the same challenge.
+    ///
+    /// Snapshots may come from files, so this returns `None` instead of panicking if the
+    /// snapshot doesn't describe a valid game
+    pub fn from_snapshot(challenge: &Challenge, snapshot: &Snapshot) -> Option<Game> {
+        let ruleset = challenge.ruleset.clone();
+        let rows = ruleset.rows;
+        let cols = ruleset.cols;
+
+        let mut field = Board::new(rows, cols);
+
+        if !snapshot.body.iter().chain(snapshot.food.iter()).all(|cell| field.contains(*cell)) {
+            return None;
+        }
+
+        let head = *snapshot.body.last()?;
+        let tail = snapshot.body[0];
+
+        // Every segment points to the next one, and the head points to itself
+        for (i, cell) in snapshot.body.iter().enumerate() {
+            if !field.is_free(*cell) {
+                return None;
+            }
+
+            let next = snapshot.body.get(i + 1).copied().unwrap_or(head);
+            field.set_tile(*cell, Snake(next));
+        }
+
+        for cell in snapshot.food.iter() {
+            if !field.is_free(*cell) {
+                return None;
+            }
+
+            field.put_entity(*cell, Entity::Food);
+        }
+
+        for (n, cell) in snapshot.pellets.iter() {
+            if !field.contains(*cell) || !field.is_free(*cell) {
+                return None;
+            }
+
+            field.put_entity(*cell, Entity::Pellet(*n));
+        }
+
+        let mut rng = rng::stream(challenge.seed, Stream::Food);
+        rng.set_word_pos(snapshot.rng_pos);
+
+        Some(Game {
+            ruleset,
+            field,
+            head,
+            tail,
+            dir: snapshot.dir,
+            score: snapshot.score,
+            length: snapshot.body.len(),
+            ticks: snapshot.ticks,
+            over: false,
+            stalled: snapshot.stalled,
+            food_since: snapshot.food_since,
+            rng,
+        })
+    }
+
+    /// Start a game on a level. Unless the level comes with food, some is placed at random as
+    /// in any other game
+    pub fn from_level(level: &Level, mode: Mode, seed: u64) -> Game {
+        let ruleset = Ruleset {
+            mode,
+            rows: level.rows,
+            cols: level.cols,
+            mutators: level.mutators.clone(),
+            grace: 0,
+            weight: Weight::default(),
+        };
+
+        let mut field = Board::new(level.rows, level.cols);
+
+        for cell in level.walls.iter() {
+            field.set_tile(*cell, Wall);
+        }
+
+        // The level checked that the snake is in one piece, from the tail to the head
+        let head = *level.snake.last().unwrap();
+        for pair in level.snake.windows(2) {
+            field.set_tile(pair[0], Snake(pair[1]));
+        }
+        field.set_tile(head, Snake(head));
+
+        for cell in level.food.iter() {
+            field.put_entity(*cell, Entity::Food);
+        }
+
+        let mut game = Game {
+            ruleset,
+            field,
+            head,
+            tail: level.snake[0],
+            dir: level.dir,
+            score: 0,
+            length: level.snake.len(),
+            ticks: 0,
+            over: false,
+            stalled: 0,
+            food_since: 0,
+            rng: rng::stream(seed, Stream::Food),
+        };
+
+        if level.food.is_empty() {
+            game.spawn_food();
+        }
+
+        game
+    }
+
+    /// The food to go for: the only piece of food there is, or the pellet with the lowest
+    /// number in sequence mode
+    pub fn next_food(&self) -> Option<Cell> {
+        self.field
+            .entities()
+            .min_by_key(|(_, entity)| match entity {
+                Entity::Food => 0,
+                Entity::Pellet(n) => *n,
+            })
+            .map(|(cell, _)| cell)
+    }
+
+    /// Whether all the food was eaten. Only happens in puzzles, anywhere else new food comes
+    /// as long as there is room for it
+    pub fn cleared(&self) -> bool {
+        self.field.entities().next().is_none()
+    }
+
+    /// How long the snake is once the field is cleared, if it eats what's left in order: a
+    /// cell longer for every piece of food still there. A level without food of its own gets
+    /// some at random, that counts too
+    pub fn cleared_length(&self) -> usize {
+        self.length + self.field.entities().count()
+    }
+
+    /// Place the food to come with another seed. The game doesn't play the way its challenge
+    /// says from then on, this is for trying things out
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = rng::stream(seed, Stream::Food);
+    }
+
+    /// How many points the food is worth right now, before the weight of the snake. Always
+    /// one, unless it decays: then it's `FOOD_VALUE` when it comes, and a point less every
+    /// `DECAY_TICKS` until it's down to one
+    pub fn decay_value(&self) -> u32 {
+        if !self.ruleset.has(Mutator::Decay) {
+            return 1;
+        }
+
+        let age = self.ticks.saturating_sub(self.food_since) / DECAY_TICKS;
+        FOOD_VALUE - age.min(FOOD_VALUE as u64 - 1) as u32
+    }
+
+    /// How many points the food is worth right now. A heavy snake gets a point more for every
+    /// `HEAVY_CELLS` of its length
+    pub fn food_value(&self) -> u32 {
+        let weight = match self.ruleset.has(Mutator::Heavy) {
+            true => (self.length / HEAVY_CELLS) as u32,
+            false => 0,
+        };
+
+        self.decay_value() + weight
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let food = self
+            .field
+            .entities()
+            .filter(|(_, entity)| *entity == Entity::Food)
+            .map(|(cell, _)| cell)
+            .collect();
+
+        let pellets = self
+            .field
+            .entities()
+            .filter_map(|(cell, entity)| match entity {
+                Entity::Pellet(n) => Some((n, cell)),
+                Entity::Food => None,
+            })
+            .collect();
+
+        Snapshot {
+            ticks: self.ticks,
+            rng_pos: self.rng.get_word_pos(),
+            dir: self.dir,
+            score: self.score,
+            food,
+            pellets,
+            body: self.body(),
+            stalled: self.stalled,
+            food_since: self.food_since,
+        }
+    }
+
+    /// A hash of the whole state of the game: the field, the snake and the score. Two games
+    /// with the same hash are (almost certainly) in the same state.
+    ///
+    /// This is FNV-1a, fed with the numbers making up the game, so it doesn't depend on the
+    /// Rust version or the platform
+    pub fn hash(&self) -> u64 {
+        fn feed(hash: u64, value: u64) -> u64 {
+            value.to_le_bytes().iter().fold(hash, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+        }
+
+        let mut hash = 0xcbf29ce484222325;
+
+        for row in 0..self.rows() {
+            for tile in self.field.row(row) {
+                hash = match tile {
+                    Empty => feed(hash, 0),
+                    Snake(next) => feed(feed(feed(hash, 1), next.row as u64), next.col as u64),
+                    Wall => feed(hash, 2),
+                };
+            }
+        }
+
+        for (cell, entity) in self.field.entities() {
+            let kind = match entity {
+                Entity::Food => 1,
+                Entity::Pellet(n) => 2 + n as u64,
+            };
+
+            hash = feed(feed(feed(hash, kind), cell.row as u64), cell.col as u64);
+        }
+
+        let dir = match self.dir {
+            Ver(Neg) => 0,
+            Ver(Pos) => 1,
+            Hor(Neg) => 2,
+            Hor(Pos) => 3,
+        };
+
+        for value in [self.head.row, self.head.col, self.tail.row, self.tail.col] {
+            hash = feed(hash, value as u64);
+        }
+
+        for value in [dir, self.score as u64, self.length as u64, self.ticks, self.over as u64] {
+            hash = feed(hash, value);
+        }
+
+        // Only games with grace ticks can be stalled, the hashes of all the others stay the
+        // same as before there were any
+        if self.stalled > 0 {
+            hash = feed(hash, self.stalled as u64);
+        }
+
+        // The same goes for the age of the food, it only matters with the decay mutator
+        if self.ruleset.has(Mutator::Decay) {
+            hash = feed(hash, self.food_since);
+        }
+
+        hash
+    }
+
+    /// A hash of the state of the game that costs next to nothing, unlike `hash` which goes
+    /// through the whole field. It's what replays and spectators check on every tick to notice
+    /// the very tick a game stops going the way it went when it was recorded
+    pub fn state_hash(&self) -> u64 {
+        let dir = match self.dir {
+            Ver(Neg) => 0,
+            Ver(Pos) => 1,
+            Hor(Neg) => 2,
+            Hor(Pos) => 3,
+        };
+
+        let values = [
+            self.head.row as u64,
+            self.head.col as u64,
+            self.tail.row as u64,
+            self.tail.col as u64,
+            dir,
+            self.score as u64,
+            self.length as u64,
+            self.ticks,
+            self.over as u64,
+        ];
+
+        let stalled = (self.stalled > 0).then_some(self.stalled as u64);
+        let food_since = self.ruleset.has(Mutator::Decay).then_some(self.food_since);
+
+        values
+            .iter()
+            .chain(stalled.iter())
+            .chain(food_since.iter())
+            .fold(self.field.hash(), |hash, value| mix(hash.wrapping_add(0x9e3779b97f4a7c15) ^ value))
+    }
+
+    /// The cells of the snake from the tail to the head
+    pub fn body(&self) -> Vec<Cell> {
+        let mut body = vec![self.tail];
+        let mut cell = self.tail;
+
+        while cell != self.head {
+            match self.field.tile(cell) {
+                Snake(next) if body.len() < self.rows() * self.cols() => {
+                    body.push(next);
+                    cell = next;
+                },
+                // A broken snake. That should never happen
+                _ => break,
+            }
+        }
+
+        body
+    }
+
+    pub fn rows(&self) -> usize {
+        self.field.rows()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.field.cols()
+    }
+
+    // Get a random empty cell on the field.
+    // Only used to place food
+    //
+    // The method I've chosen is not the most efficient. The better way would probably be to
+    // keep track of empty/non-empy cells during the game update. But I'm lazy so this will do.
+    fn rnd_empty_cell(&mut self) -> Option<Cell> {
+        let mut empty_cells = self.field.free_cells();
+
+        if empty_cells.is_empty() {
+            return None;
+        }
+
+        empty_cells.shuffle(&mut self.rng);
+
+        Some(empty_cells[0])
+    }
+
+    /// The food was just put on the field. How old it is only matters when it decays, the
+    /// games without the decay mutator play (and hash) as they always did
+    fn fresh_food(&mut self) {
+        if self.ruleset.has(Mutator::Decay) {
+            self.food_since = self.ticks;
+        }
+    }
+
+    /// Put new food on the field after some was eaten, if there's room for it. In sequence mode
+    /// a new round of pellets comes once the last one is gone
+    fn spawn_food(&mut self) {
+        if self.ruleset.mode != Mode::Sequence {
+            if let Some(cell) = self.rnd_empty_cell() {
+                self.field.put_entity(cell, Entity::Food);
+                self.fresh_food();
+            }
+
+            return;
+        }
+
+        if self.field.entities().next().is_some() {
+            return;
+        }
+
+        self.fresh_food();
+
+        for n in 1..=SEQUENCE_LENGTH {
+            match self.rnd_empty_cell() {
+                Some(cell) => self.field.put_entity(cell, Entity::Pellet(n)),
+                None => break,
+            }
+        }
+    }
+
+    /// The cell the head would end up in when moving in the given direction. `None` means the
+    /// snake would leave the field
+    pub fn next_cell(&self, dir: Direction) -> Option<Cell> {
+        self.neighbour(self.head, dir)
+    }
+
+    /// The cell next to a cell in the given direction, if the field goes on there
+    pub fn neighbour(&self, mut cell: Cell, dir: Direction) -> Option<Cell> {
+        let wrap = self.ruleset.has(Mutator::Wrap);
+
+        match dir {
+            Hor(pol) => cell.col = step(cell.col, pol, self.cols(), wrap)?,
+            Ver(pol) => cell.row = step(cell.row, pol, self.rows(), wrap)?,
+        }
+
+        Some(cell)
+    }
+
+    /// Where the snake goes instead when going in the given direction would run it into a wall
+    /// or the edge, with the bounce mutator. If both turns are blocked as well it goes on and
+    /// dies
+    fn bounce(&self, dir: Direction) -> Direction {
+        let walled = |dir: Direction| match self.next_cell(dir) {
+            Some(cell) => self.field.tile(cell) == Wall,
+            None => true,
+        };
+
+        if !self.ruleset.has(Mutator::Bounce) || !walled(dir) {
+            return dir;
+        }
+
+        // Turning never takes the snake back the way it came
+        [dir.clockwise(), dir.counterclockwise()]
+            .into_iter()
+            .filter(|turn| *turn == self.dir || self.dir.can_turn_to(*turn))
+            .find(|turn| self.next_cell(*turn).is_some_and(|cell| self.field.tile(cell) == Empty))
+            .unwrap_or(dir)
+    }
+
+    /// The turn that saves the snake when going in the given direction would kill it, if there
+    /// is one. Clockwise first, like a bounce
+    pub fn safe_turn(&self, dir: Direction) -> Option<Direction> {
+        if !self.is_deadly(dir) {
+            return None;
+        }
+
+        [dir.clockwise(), dir.counterclockwise()]
+            .into_iter()
+            .filter(|turn| *turn == self.dir || self.dir.can_turn_to(*turn))
+            .find(|turn| !self.is_deadly(*turn))
+    }
+
+    /// Whether moving in this direction would kill the snake right away
+    pub fn is_deadly(&self, dir: Direction) -> bool {
+        match self.next_cell(dir) {
+            Some(cell) => matches!(self.field.tile(cell), Snake(_) | Wall),
+            None => true,
+        }
+    }
+
+    /// Move the game one step forward with the snake going in the given direction. If the
+    /// direction would turn the snake around, it just keeps going straight.
+    ///
+    /// When the snake dies, the field is left as it was before the deadly move. In zen mode the
+    /// snake doesn't die, it stays where it is for the tick instead. With grace ticks, running
+    /// into the body does the same until the grace ticks are used up.
+    pub fn tick(&mut self, dir: Direction) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        if self.over {
+            return events;
+        }
+
+        let dir = if self.dir.can_turn_to(dir) { dir } else { self.dir };
+
+        if dir != self.dir && self.is_deadly(self.dir) && !self.is_deadly(dir) {
+            events.push(NearMiss);
+        }
+
+        let dir = self.bounce(dir);
+        let dir = match self.safe_turn(dir) {
+            Some(turn) if self.ruleset.has(Mutator::Assist) => turn,
+            _ => dir,
+        };
+        self.dir = dir;
+        self.ticks += 1;
+
+        let head_prev = self.head;
+
+        // Let's check the type of the tile the head will end up in. Hitting the edge of the
+        // field is just as deadly as hitting the snake itself
+        let head = match self.next_cell(dir) {
+            Some(head) if self.field.tile(head) == Empty => head,
+            // In zen mode the snake just waits for a turn
+            _ if self.ruleset.mode == Mode::Zen => return events,
+            // With grace ticks, it waits for a while before running into itself kills it
+            Some(cell) if matches!(self.field.tile(cell), Snake(_)) && self.stalled < self.ruleset.grace => {
+                self.stalled += 1;
+                return events;
+            },
+            // The snake hit itself, a wall or the edge... It is a game over
+            _ => {
+                self.over = true;
+                events.push(Died);
+                return events;
+            }
+        };
+
+        self.stalled = 0;
+
+        let next_food = self.next_food();
+
+        match self.field.take_entity(head) {
+            Some(Entity::Food) => {
+                self.score += self.food_value();
+                self.length += 1;
+                events.push(Ate);
+            },
+            Some(Entity::Pellet(_)) if next_food == Some(head) => {
+                self.score += self.food_value();
+                self.length += 1;
+                events.push(Ate);
+            },
+            // Out of order: the pellet is gone, and the snake doesn't grow
+            Some(Entity::Pellet(_)) => {
+                self.score = self.score.saturating_sub(1);
+                events.push(WrongOrder);
+
+                if let Snake(next) = self.field.tile(self.tail) {
+                    self.field.set_tile(self.tail, Empty);
+                    self.tail = next;
+                }
+            },
+            None => {
+                // Empty cell, so just pull the tail forward
+                if let Snake(next) = self.field.tile(self.tail) {
+                    self.field.set_tile(self.tail, Empty);
+                    self.tail = next;
+                }
+            },
+        };
+
+        self.head = head;
+
+        // Replace the tile at the previous head position to a new Snake tile referencing the
+        // new head position
+        self.field.set_tile(head_prev, Snake(head));
+
+        // Put a new Snake tile in the new head position. The `head` value doesn't have any use
+        // here. Ideally we should allow this valu to be empty with Option for example.
+        self.field.set_tile(head, Snake(head));
+
+        // Puzzles come with all their food, there's no more once it's eaten
+        if (events.contains(&Ate) || events.contains(&WrongOrder)) && self.ruleset.mode != Mode::Puzzle {
+            // If it is food - try to find a random empty cell and put another piece of food there.
+            // If there are no empty cells, I guess we're not going to do anything here...
+            self.spawn_food();
+        }
+
+        events
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A game on a 5x5 field with the mutator, with the snake from the tail to the head, going
+    /// down
+    pub(crate) fn small(mutator: Mutator, body: &[(usize, usize)]) -> Game {
+        let challenge = Challenge {
+            ruleset: Ruleset {
+                rows: 5,
+                cols: 5,
+                mutators: vec![mutator],
+                ..Ruleset::default()
+            },
+            seed: 1,
+        };
+
+        let snapshot = Snapshot {
+            ticks: 0,
+            rng_pos: 0,
+            dir: Ver(Pos),
+            score: 0,
+            food: vec![Cell { row: 0, col: 0 }],
+            pellets: Vec::new(),
+            body: body.iter().map(|(row, col)| Cell { row: *row, col: *col }).collect(),
+            stalled: 0,
+            food_since: 0,
+        };
+
+        Game::from_snapshot(&challenge, &snapshot).unwrap()
+    }
+
+    /// Going on runs into the tail, turning left runs into the body, there's only room on the
+    /// right
+    pub(crate) const CORNERED: [(usize, usize); 6] = [(3, 2), (3, 1), (2, 1), (1, 1), (1, 2), (2, 2)];
+
+    #[test]
+    fn a_cleared_field_is_as_long_as_the_food_makes_the_snake() {
+        let level = |food: &str| {
+            Level::parse(&format!("snek-level 1\nname Goal\nmap\n{}\n.o>..\n.....", food)).unwrap()
+        };
+
+        let mut game = Game::from_level(&level("..$.$"), Mode::Puzzle, 0);
+        assert_eq!(game.cleared_length(), 4);
+
+        game.tick(Ver(Neg));
+        assert_eq!((game.length, game.cleared_length()), (3, 4));
+
+        // Without food in the level, the one placed at random is all there is to eat
+        let game = Game::from_level(&level("....."), Mode::Puzzle, 0);
+        assert_eq!(game.cleared_length(), 3);
+    }
+
+    #[test]
+    fn an_assisted_snake_takes_the_way_out() {
+        let mut game = small(Mutator::Assist, &CORNERED);
+
+        assert_eq!(game.safe_turn(Ver(Pos)), Some(Hor(Pos)));
+        game.tick(Ver(Pos));
+        assert!(!game.over);
+        assert_eq!(game.head, Cell { row: 2, col: 3 });
+    }
+
+    #[test]
+    fn a_hint_only_shows_the_way_out() {
+        let mut game = small(Mutator::Hint, &CORNERED);
+
+        assert_eq!(game.safe_turn(Ver(Pos)), Some(Hor(Pos)));
+        assert_eq!(game.tick(Ver(Pos)), vec![Died]);
+    }
+
+    #[test]
+    fn the_food_of_a_heavy_snake_is_worth_more() {
+        let challenge = Challenge {
+            ruleset: Ruleset {
+                mutators: vec![Mutator::Heavy],
+                ..Ruleset::default()
+            },
+            seed: 1,
+        };
+
+        let mut game = Game::new(&challenge);
+        assert_eq!(game.food_value(), 1);
+
+        game.length = HEAVY_CELLS * 2;
+        assert_eq!(game.food_value(), 3);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The biggest code LZW can have in a GIF. The table starts over before it gets there
+const MAX_CODE: u16 = 4095;
+
+/// Writes an animated GIF, just enough of the format for footage of the game: one palette for
+/// all the frames, every frame the size of the whole picture, looping forever.
+pub struct Encoder<W: Write> {
+    out: W,
+    width: u16,
+    height: u16,
+    /// The number of bits of a color of the palette
+    depth: u8,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Start a GIF of the given size, with up to 256 colors
+    pub fn new(mut out: W, width: u16, height: u16, palette: &[[u8; 3]]) -> io::Result<Encoder<W>> {
+        assert!(!palette.is_empty() && palette.len() <= 256, "a GIF has 1 to 256 colors");
+
+        let mut depth = 1;
+        while 1 << depth < palette.len() {
+            depth += 1;
+        }
+
+        out.write_all(b"GIF89a")?;
+        out.write_all(&width.to_le_bytes())?;
+        out.write_all(&height.to_le_bytes())?;
+
+        // A global palette with 8 bits per channel, padded to a power of two
+        out.write_all(&[0xf0 | (depth - 1), 0, 0])?;
+        for i in 0..1 << depth {
+            out.write_all(&palette.get(i).copied().unwrap_or_default())?;
+        }
+
+        // The extension that makes it loop, for ever
+        out.write_all(&[0x21, 0xff, 11])?;
+        out.write_all(b"NETSCAPE2.0")?;
+        out.write_all(&[3, 1, 0, 0, 0])?;
+
+        Ok(Encoder {
+            out,
+            width,
+            height,
+            depth,
+        })
+    }
+
+    /// Add a frame, given as the colors of its pixels row by row, shown for `delay`
+    /// hundredths of a second
+    pub fn frame(&mut self, pixels: &[u8], delay: u16) -> io::Result<()> {
+        assert_eq!(pixels.len(), self.width as usize * self.height as usize);
+
+        // Nothing transparent, just the delay
+        self.out.write_all(&[0x21, 0xf9, 4, 0])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0, 0])?;
+
+        // The whole picture, with the global palette
+        self.out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.out.write_all(&self.width.to_le_bytes())?;
+        self.out.write_all(&self.height.to_le_bytes())?;
+        self.out.write_all(&[0])?;
+
+        // Pictures with only two colors still need codes of at least three bits
+        let min = self.depth.max(2);
+        self.out.write_all(&[min])?;
+
+        for block in lzw(pixels, min).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+
+        self.out.write_all(&[0])
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.write_all(&[0x3b])?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Codes of any size from 3 to 12 bits packed one after the other, from the lowest bit
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    pending: u32,
+    count: u8,
+}
+
+impl Bits {
+    fn push(&mut self, code: u16, width: u8) {
+        self.pending |= (code as u32) << self.count;
+        self.count += width;
+
+        while self.count >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.bytes.push(self.pending as u8);
+        }
+
+        self.bytes
+    }
+}
+
+/// The pixels compressed the way GIF does it, LZW with codes that grow as the table does
+fn lzw(pixels: &[u8], min: u8) -> Vec<u8> {
+    let clear = 1u16 << min;
+    let end = clear + 1;
+
+    let mut bits = Bits::default();
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut width = min + 1;
+    let mut next = end + 1;
+
+    bits.push(clear, width);
+
+    let Some((first, rest)) = pixels.split_first() else {
+        bits.push(end, width);
+        return bits.finish();
+    };
+
+    let mut prefix = *first as u16;
+
+    for pixel in rest.iter().copied() {
+        if let Some(code) = table.get(&(prefix, pixel)) {
+            prefix = *code;
+            continue;
+        }
+
+        bits.push(prefix, width);
+
+        if next == MAX_CODE {
+            bits.push(clear, width);
+
+            table.clear();
+            width = min + 1;
+            next = end + 1;
+        } else {
+            table.insert((prefix, pixel), next);
+            next += 1;
+
+            // The decoder is one code behind, it only needs the wider codes from here on
+            if next > 1 << width {
+                width += 1;
+            }
+        }
+
+        prefix = pixel as u16;
+    }
+
+    bits.push(prefix, width);
+    bits.push(end, width);
+    bits.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode LZW the way a GIF reader does, and count the clears on the way
+    fn unlzw(data: &[u8], min: u8) -> (Vec<u8>, usize) {
+        let clear = 1usize << min;
+        let end = clear + 1;
+
+        let mut pixels = Vec::new();
+        let mut clears = 0;
+        let mut table: Vec<Vec<u8>> = Vec::new();
+        let mut width = min + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let (mut pending, mut count, mut bytes) = (0u32, 0u8, data.iter());
+
+        loop {
+            while count < width {
+                pending |= (*bytes.next().expect("the end code is missing") as u32) << count;
+                count += 8;
+            }
+            let code = (pending & ((1 << width) - 1)) as usize;
+            pending >>= width;
+            count -= width;
+
+            if code == clear {
+                table = (0..=end).map(|i| vec![i as u8]).collect();
+                width = min + 1;
+                prev = None;
+                clears += 1;
+                continue;
+            }
+            if code == end {
+                return (pixels, clears);
+            }
+
+            let entry = match (table.get(code), &prev) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(prev)) if code == table.len() => [&prev[..], &prev[..1]].concat(),
+                _ => panic!("code {} is not in the table yet", code),
+            };
+            pixels.extend_from_slice(&entry);
+
+            if let Some(prev) = prev {
+                table.push([&prev[..], &entry[..1]].concat());
+                if table.len() == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+            prev = Some(entry);
+        }
+    }
+
+    /// Pixels that don't repeat much, so the table fills up fast
+    fn noise(len: usize, colors: u8) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state % colors as u32) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn two_colors_come_back() {
+        let pixels: Vec<u8> = (0..300).map(|i| (i / 7 % 2) as u8).collect();
+        assert_eq!(unlzw(&lzw(&pixels, 2), 2), (pixels, 1));
+    }
+
+    #[test]
+    fn sixteen_colors_come_back() {
+        let pixels = noise(1000, 16);
+        assert_eq!(unlzw(&lzw(&pixels, 4), 4), (pixels, 1));
+    }
+
+    #[test]
+    fn the_table_starts_over_once_full() {
+        let pixels = noise(40_000, 16);
+        let (back, clears) = unlzw(&lzw(&pixels, 4), 4);
+
+        assert!(clears > 1, "{} clears", clears);
+        assert_eq!(back, pixels);
+    }
+
+    #[test]
+    fn nothing_is_just_clear_and_end() {
+        assert_eq!(unlzw(&lzw(&[], 2), 2), (vec![], 1));
+    }
+
+    #[test]
+    fn a_gif_of_one_frame() {
+        let mut gif = Encoder::new(Vec::new(), 2, 1, &[[0, 0, 0], [255, 255, 255]]).unwrap();
+        gif.frame(&[0, 1], 5).unwrap();
+        let bytes = gif.finish().unwrap();
+
+        let mut header = b"GIF89a".to_vec();
+        header.extend_from_slice(&[2, 0, 1, 0, 0xf0, 0, 0, 0, 0, 0, 255, 255, 255]);
+        header.extend_from_slice(&[0x21, 0xff, 11]);
+        header.extend_from_slice(b"NETSCAPE2.0");
+        header.extend_from_slice(&[3, 1, 0, 0, 0]);
+        header.extend_from_slice(&[0x21, 0xf9, 4, 0, 5, 0, 0, 0]);
+        header.extend_from_slice(&[0x2c, 0, 0, 0, 0, 2, 0, 1, 0, 0]);
+        assert_eq!(bytes[..header.len()], header[..]);
+
+        // The code size, one block of data, the empty block after it and the trailer
+        let rest = &bytes[header.len()..];
+        let data = &rest[2..2 + rest[1] as usize];
+        assert_eq!(rest[0], 2);
+        assert_eq!(rest[2 + data.len()..], [0, 0x3b]);
+        assert_eq!(unlzw(data, 2).0, [0, 1]);
+    }
+}
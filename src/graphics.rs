@@ -0,0 +1,218 @@
+use std::env;
+use std::fmt::Write as _;
+
+use crate::pixels::{Canvas, PALETTE};
+
+/// The id the field has with the kitty protocol. Sending it again replaces the picture on the
+/// screen instead of adding one more
+const KITTY_ID: u32 = 1;
+/// The most bytes of a picture with the kitty protocol in one escape sequence, and how many
+/// pixels that is in base64. Chunks of whole pixels are also whole groups of base64
+const KITTY_CHUNK: usize = 4096;
+const KITTY_CHUNK_PIXELS: usize = KITTY_CHUNK / 4;
+
+/// A way to draw pixels on a terminal
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Protocol {
+    /// The kitty graphics protocol: an RGB picture, scaled by the terminal to the cells it's
+    /// given. Kitty, WezTerm, Konsole and Ghostty have it
+    Kitty,
+    /// Sixel, the old DEC one: the pixels as they are, in bands of six rows. xterm (with
+    /// `-ti vt340`), mlterm, foot and a few others have it
+    Sixel,
+}
+
+impl Protocol {
+    pub fn from_name(name: &str) -> Option<Protocol> {
+        match name {
+            "kitty" => Some(Protocol::Kitty),
+            "sixel" => Some(Protocol::Sixel),
+            _ => None,
+        }
+    }
+
+    /// What the terminal the game runs in is likely to have. Asking the terminal would need an
+    /// answer read from the same place as the keys, so this only goes by what the terminals
+    /// say about themselves in the environment. `None` means characters it is
+    pub fn detect() -> Option<Protocol> {
+        let var = |name: &str| env::var(name).unwrap_or_default().to_lowercase();
+
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+
+        let kitty = term.contains("kitty") || env::var_os("KITTY_WINDOW_ID").is_some();
+        if kitty || program == "wezterm" || program == "ghostty" {
+            return Some(Protocol::Kitty);
+        }
+
+        if term.contains("sixel") || term.starts_with("mlterm") || term.starts_with("foot") || term == "yaft-256color" {
+            return Some(Protocol::Sixel);
+        }
+
+        None
+    }
+
+    /// Take the picture off the screen. Sixels are just like characters, whatever is drawn
+    /// over them is enough
+    pub fn clear(self, out: &mut String) {
+        if self == Protocol::Kitty {
+            write!(out, "\x1b_Ga=d,d=i,i={},q=2\x1b\\", KITTY_ID).unwrap();
+        }
+    }
+
+    /// The escape sequences that show the picture at the cursor, taking `cols` by `lines`
+    /// cells. Where the cursor ends up depends on the protocol and the terminal. `scratch` is
+    /// kept by the caller from one picture to the next, so it doesn't have to grow every time
+    pub fn encode(self, canvas: &Canvas, cols: usize, lines: usize, scratch: &mut Vec<u8>, out: &mut String) {
+        match self {
+            Protocol::Kitty => kitty(canvas, cols, lines, scratch, out),
+            Protocol::Sixel => sixel(canvas, out),
+        }
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(bytes: &[u8], out: &mut String) {
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+}
+
+/// The picture as RGB, sent in chunks. Nothing is asked back from the terminal (`q=2`), an
+/// answer would turn up among the keys. It goes below the text (`z=-1`), so what's written
+/// over the field, like the game over, can still be read
+fn kitty(canvas: &Canvas, cols: usize, lines: usize, rgb: &mut Vec<u8>, out: &mut String) {
+    let chunks = canvas.pixels.len().div_ceil(KITTY_CHUNK_PIXELS);
+
+    for (i, pixels) in canvas.pixels.chunks(KITTY_CHUNK_PIXELS).enumerate() {
+        let more = (i + 1 < chunks) as u8;
+
+        if i == 0 {
+            write!(
+                out,
+                "\x1b_Ga=T,f=24,s={},v={},i={},p=1,c={},r={},C=1,z=-1,q=2,m={};",
+                canvas.width, canvas.height, KITTY_ID, cols, lines, more,
+            )
+            .unwrap();
+        } else {
+            write!(out, "\x1b_Gm={};", more).unwrap();
+        }
+
+        rgb.clear();
+        rgb.extend(pixels.iter().flat_map(|color| PALETTE[*color as usize]));
+        base64(rgb, out);
+        out.push_str("\x1b\\");
+    }
+}
+
+/// The picture in sixels: for every band of six rows, one line of sixels for each color in
+/// it, runs of the same sixel written once with a count
+fn sixel(canvas: &Canvas, out: &mut String) {
+    // Square pixels, and the size of the picture up front
+    write!(out, "\x1bP0;1q\"1;1;{};{}", canvas.width, canvas.height).unwrap();
+
+    // The colors are given in percents
+    for (i, [r, g, b]) in PALETTE.iter().enumerate() {
+        let percent = |c: u8| c as u32 * 100 / 255;
+        write!(out, "#{};2;{};{};{}", i, percent(*r), percent(*g), percent(*b)).unwrap();
+    }
+
+    for top in (0..canvas.height).step_by(6) {
+        let rows = top..(top + 6).min(canvas.height);
+
+        for color in 0..PALETTE.len() as u8 {
+            let sixels: Vec<u8> = (0..canvas.width)
+                .map(|x| {
+                    rows.clone()
+                        .enumerate()
+                        .filter(|(_, y)| canvas.pixels[y * canvas.width + x] == color)
+                        .fold(0, |bits, (i, _)| bits | 1 << i)
+                })
+                .collect();
+
+            if sixels.iter().all(|bits| *bits == 0) {
+                continue;
+            }
+
+            write!(out, "#{}", color).unwrap();
+
+            let mut x = 0;
+            while x < sixels.len() {
+                let run = sixels[x..].iter().take_while(|bits| **bits == sixels[x]).count();
+                let ch = (63 + sixels[x]) as char;
+
+                if run > 3 {
+                    write!(out, "!{}{}", run, ch).unwrap();
+                } else {
+                    out.extend(std::iter::repeat_n(ch, run));
+                }
+
+                x += run;
+            }
+
+            // Back to the start of the band for the next color
+            out.push('$');
+        }
+
+        out.push('-');
+    }
+
+    out.push_str("\x1b\\");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas(width: usize, height: usize, pixels: Vec<u8>) -> Canvas {
+        Canvas { width, height, pixels }
+    }
+
+    #[test]
+    fn base64_pads_what_is_left() {
+        for (bytes, text) in [("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foob", "Zm9vYg==")] {
+            let mut out = String::new();
+            base64(bytes.as_bytes(), &mut out);
+            assert_eq!(out, text);
+        }
+    }
+
+    #[test]
+    fn sixels_count_only_the_long_runs() {
+        // A band that's all color 0, then a row of four pixels of it and three of color 1
+        let mut pixels = vec![0; 7 * 7];
+        pixels[6 * 7 + 4..].fill(1);
+
+        let mut out = String::new();
+        sixel(&canvas(7, 7, pixels), &mut out);
+
+        assert!(out.starts_with("\x1bP0;1q\"1;1;7;7#0;2;"), "{:?}", out);
+        assert!(out.ends_with("#0!7~$-#0!4@???$#1!4?@@@$-\x1b\\"), "{:?}", out);
+    }
+
+    #[test]
+    fn kitty_says_there_is_more_on_every_chunk_but_the_last() {
+        let mut out = String::new();
+        kitty(&canvas(50, 50, vec![0; 2500]), 10, 5, &mut Vec::new(), &mut out);
+
+        let chunks: Vec<&str> = out.split_terminator("\x1b\\").collect();
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=24,s=50,v=50,i=1,p=1,c=10,r=5,"), "{:?}", chunks[0]);
+        assert!(chunks[0].contains(",m=1;"));
+        assert!(chunks[1].starts_with("\x1b_Gm=1;"));
+        assert!(chunks[2].starts_with("\x1b_Gm=0;"));
+
+        // All the pixels are there, in base64
+        let data: usize = chunks.iter().map(|chunk| chunk.len() - chunk.find(';').unwrap() - 1).sum();
+        assert_eq!(data, 2500 * 4);
+    }
+}
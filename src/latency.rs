@@ -0,0 +1,152 @@
+use std::fmt::Write as _;
+use std::io;
+use std::process::exit;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console::{Key, Term};
+
+use crate::error::{self, Error, Result};
+use crate::render::Frame;
+use crate::title;
+
+/// The size of the marker that flashes on every key
+const MARKER_COLS: usize = 12;
+const MARKER_LINES: usize = 4;
+
+/// What the trials came out at
+pub struct Report {
+    pub trials: usize,
+    pub mean: Duration,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Report {
+    /// Nearest rank percentiles of the samples. There has to be at least one
+    pub fn new(mut samples: Vec<Duration>) -> Report {
+        samples.sort();
+
+        let percentile = |p: usize| samples[(samples.len() * p).div_ceil(100).max(1) - 1];
+        let total: Duration = samples.iter().sum();
+
+        Report {
+            trials: samples.len(),
+            mean: total / samples.len() as u32,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Measure how long it takes from a key coming in to the frame that shows it being on the
+/// screen, drawing frames every `tick` like the game does. Every key flashes the marker, and
+/// after `trials` keys the results are printed
+pub fn run(trials: usize, tick: Duration) {
+    let term = Term::stdout();
+
+    if let Err(e) = title::push(&term, "Snek! (latency)") {
+        error::report(Error::Terminal(e));
+    }
+
+    // When the key that wasn't drawn yet came in
+    let pressed: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+    let pressed_thread = Arc::clone(&pressed);
+    let (done, report) = mpsc::channel();
+
+    // The same split as in the game: one thread draws, the other one reads the keys. The
+    // report is printed by the keys, the terminal is raw until the read going on there is over
+    thread::spawn(move || match measure(&pressed_thread, trials, tick) {
+        Ok(report) => {
+            let _ = done.send(report);
+
+            let mut frame = Frame::default();
+            frame.begin();
+            writeln!(frame, "All {} trials are done, press any key for the results", trials).unwrap();
+            if let Err(e) = frame.present(&mut io::stdout()) {
+                error::hand_over(Error::Terminal(e));
+            }
+        },
+        Err(e) => error::hand_over(e),
+    });
+
+    loop {
+        let key = error::read_key(&term);
+
+        if let Ok(report) = report.try_recv() {
+            let _ = title::pop(&term);
+
+            println!("{} trials with a frame every {} ms", report.trials, tick.as_millis());
+            println!(
+                "key to screen: mean {:.1} ms  p50 {:.1}  p90 {:.1}  p99 {:.1}  max {:.1}",
+                ms(report.mean),
+                ms(report.p50),
+                ms(report.p90),
+                ms(report.p99),
+                ms(report.max),
+            );
+            exit(0);
+        }
+
+        match key {
+            Key::Escape => {
+                let _ = title::pop(&term);
+                exit(0);
+            },
+            // A key that comes before the previous one was drawn is part of the same frame
+            _ => {
+                pressed.lock().unwrap().get_or_insert_with(Instant::now);
+            },
+        }
+    }
+}
+
+fn measure(pressed: &Mutex<Option<Instant>>, trials: usize, tick: Duration) -> Result<Report> {
+    let mut frame = Frame::default();
+    let mut stdout = io::stdout();
+
+    let mut samples = Vec::with_capacity(trials);
+    let mut lit = false;
+
+    loop {
+        let press = pressed.lock().unwrap().take();
+        if press.is_some() {
+            lit = !lit;
+        }
+
+        frame.begin();
+
+        let cell = if lit { '#' } else { '.' };
+        for _ in 0..MARKER_LINES {
+            writeln!(frame, "{}", cell.to_string().repeat(MARKER_COLS)).unwrap();
+        }
+
+        writeln!(frame, "Press any key, {} more to go. Escape quits", trials - samples.len()).unwrap();
+        if let Some(last) = samples.last() {
+            writeln!(frame, "last {:.1} ms", ms(*last)).unwrap();
+        }
+
+        frame.present(&mut stdout).map_err(Error::Terminal)?;
+
+        // The frame is on its way to the terminal, that's as far as the game can tell
+        if let Some(at) = press {
+            samples.push(at.elapsed());
+
+            if samples.len() == trials {
+                return Ok(Report::new(samples));
+            }
+        }
+
+        thread::sleep(tick);
+    }
+}
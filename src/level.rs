@@ -0,0 +1,499 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::board::Cell;
+use crate::game::{Direction, Direction::*, Polarity::*};
+use crate::ruleset::{Mutator, MAX_SIZE, MIN_SIZE};
+
+/// A handcrafted field: where the walls, the food and the snake are at the start.
+///
+/// Levels are text files, so they're easy to draw in any editor:
+///
+/// ```text
+/// snek-level 1
+/// name First steps
+/// moves 9
+/// limit 12
+/// map
+/// ##########
+/// #........#
+/// #.o>...$.#
+/// #........#
+/// ##########
+/// ```
+///
+/// After the header come the options, one per line: `name` (required), `moves` (the fewest
+/// moves the level can be cleared in), `limit` (the most moves allowed, the level is failed
+/// when they run out), `mutators` (`wrap`, or `-` for none) and `symmetry` (see
+/// [`Symmetry`]). The map comes last: `#` is a wall, `.` an empty cell, `$` food, one of `>`,
+/// `<`, `^` and `v` the head of the snake going in that direction, and `o` the rest of the
+/// snake. The body has to start right behind the head and go on from there without ever
+/// branching.
+#[derive(Clone, Debug)]
+pub struct Level {
+    pub name: String,
+    /// The fewest moves the level can be cleared in, if the author knows it
+    pub moves: Option<u32>,
+    /// The most moves the food can be eaten in, there's no limit without it
+    pub limit: Option<u32>,
+    pub mutators: Vec<Mutator>,
+    /// How the walls and the food have to be laid out for the level to be fair from every side
+    pub symmetry: Option<Symmetry>,
+    pub rows: usize,
+    pub cols: usize,
+    pub walls: Vec<Cell>,
+    pub food: Vec<Cell>,
+    /// From the tail to the head
+    pub snake: Vec<Cell>,
+    pub dir: Direction,
+}
+
+/// A level laid out the same way from two sides, so that players coming from either of them
+/// are as well off: the walls and the food on one side have their match on the other.
+///
+/// A level with a symmetry is checked when it loads. One half of it can also be drawn by hand
+/// and the other one made from it with [`Level::symmetric`]. The snake isn't part of it:
+/// there's only ever one on a field, and the players of hot-seat all start from the same one.
+/// Spawns for several snakes on one field would need such a field first
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Symmetry {
+    /// The right half is the left half seen in a mirror
+    Mirror,
+    /// The map is the same turned upside down
+    Rotate,
+}
+
+impl Symmetry {
+    pub fn name(self) -> &'static str {
+        match self {
+            Symmetry::Mirror => "mirror",
+            Symmetry::Rotate => "rotate",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Symmetry> {
+        [Symmetry::Mirror, Symmetry::Rotate].into_iter().find(|s| s.name() == name)
+    }
+
+    /// The cell matching this one on the other side of a map of the given size
+    pub fn across(self, cell: Cell, rows: usize, cols: usize) -> Cell {
+        match self {
+            Symmetry::Mirror => Cell {
+                row: cell.row,
+                col: cols - 1 - cell.col,
+            },
+            Symmetry::Rotate => Cell {
+                row: rows - 1 - cell.row,
+                col: cols - 1 - cell.col,
+            },
+        }
+    }
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+fn dir_from_char(ch: char) -> Option<Direction> {
+    match ch {
+        '^' => Some(Ver(Neg)),
+        'v' => Some(Ver(Pos)),
+        '<' => Some(Hor(Neg)),
+        '>' => Some(Hor(Pos)),
+        _ => None,
+    }
+}
+
+/// The cell next to this one in the given direction, if it's on the map
+fn neighbour(cell: Cell, dir: Direction, rows: usize, cols: usize) -> Option<Cell> {
+    let Cell { row, col } = cell;
+
+    let (row, col) = match dir {
+        Ver(Neg) => (row.checked_sub(1)?, col),
+        Ver(Pos) => (row + 1, col),
+        Hor(Neg) => (row, col.checked_sub(1)?),
+        Hor(Pos) => (row, col + 1),
+    };
+
+    (row < rows && col < cols).then_some(Cell { row, col })
+}
+
+fn dir_to_char(dir: Direction) -> char {
+    match dir {
+        Ver(Neg) => '^',
+        Ver(Pos) => 'v',
+        Hor(Neg) => '<',
+        Hor(Pos) => '>',
+    }
+}
+
+fn opposite(dir: Direction) -> Direction {
+    match dir {
+        Ver(Neg) => Ver(Pos),
+        Ver(Pos) => Ver(Neg),
+        Hor(Neg) => Hor(Pos),
+        Hor(Pos) => Hor(Neg),
+    }
+}
+
+impl Level {
+    pub fn parse(text: &str) -> io::Result<Level> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        if lines.next().map(|(_, line)| line) != Some("snek-level 1") {
+            return Err(invalid(1, "not a level file"));
+        }
+
+        let mut name = None;
+        let mut moves = None;
+        let mut limit = None;
+        let mut mutators = Vec::new();
+        let mut symmetry = None;
+
+        // The options, up to the map
+        loop {
+            let (n, line) = lines.next().ok_or_else(|| invalid(1, "missing the map"))?;
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "map" {
+                break;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+
+            match key {
+                "name" if !value.is_empty() => name = Some(value.to_string()),
+                "moves" => moves = Some(value.parse().map_err(|_| invalid(n, "expected a number of moves"))?),
+                "limit" => limit = Some(value.parse().map_err(|_| invalid(n, "expected a number of moves"))?),
+                "mutators" if value == "-" => mutators.clear(),
+                "mutators" => {
+                    mutators = value
+                        .split('+')
+                        .map(Mutator::from_name)
+                        .collect::<Option<Vec<Mutator>>>()
+                        .ok_or_else(|| invalid(n, "unknown mutator"))?;
+                },
+                "symmetry" => {
+                    let kind = Symmetry::from_name(value).ok_or_else(|| invalid(n, "expected mirror or rotate"))?;
+                    symmetry = Some((n, kind));
+                },
+                _ => return Err(invalid(n, "expected name, moves, limit, mutators, symmetry or map")),
+            }
+        }
+
+        let name = name.ok_or_else(|| invalid(1, "the level has no name"))?;
+
+        if let (Some(moves), Some(limit)) = (moves, limit) {
+            if limit < moves {
+                return Err(invalid(1, "the limit is lower than the fewest moves the level can be cleared in"));
+            }
+        }
+
+        let level = Level {
+            name,
+            moves,
+            limit,
+            mutators,
+            symmetry: symmetry.map(|(_, kind)| kind),
+            ..Level::parse_map(lines)?
+        };
+
+        if let Some((n, kind)) = symmetry {
+            if let Some(msg) = level.asymmetry(kind) {
+                return Err(invalid(n, &msg));
+            }
+        }
+
+        Ok(level)
+    }
+
+    /// What breaks the symmetry of the level, if anything: the first wall or food that has
+    /// nothing to match it on the other side
+    pub fn asymmetry(&self, symmetry: Symmetry) -> Option<String> {
+        for (cells, what) in [(&self.walls, "wall"), (&self.food, "food")] {
+            for cell in cells.iter() {
+                if !cells.contains(&symmetry.across(*cell, self.rows, self.cols)) {
+                    return Some(format!(
+                        "the map isn't symmetric ({}): the {} at row {}, column {} has nothing across from it",
+                        symmetry.name(),
+                        what,
+                        cell.row + 1,
+                        cell.col + 1,
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// The level with every wall and food copied across, so that it has the symmetry. Drawing
+    /// one half is enough, what's already on the other one is kept. Fails when a copy lands on
+    /// the snake, or a wall and food end up in the same cell
+    pub fn symmetric(&self, symmetry: Symmetry) -> Result<Level, String> {
+        let mut level = self.clone();
+        level.symmetry = Some(symmetry);
+
+        for (cells, across) in [(&self.walls, &mut level.walls), (&self.food, &mut level.food)] {
+            for cell in cells.iter() {
+                let cell = symmetry.across(*cell, self.rows, self.cols);
+                if !across.contains(&cell) {
+                    across.push(cell);
+                }
+            }
+        }
+
+        for cell in level.walls.iter().chain(level.food.iter()) {
+            let what = match (level.snake.contains(cell), level.walls.contains(cell) && level.food.contains(cell)) {
+                (true, _) => "the snake is in the way",
+                (_, true) => "a wall and food are in the same cell",
+                _ => continue,
+            };
+
+            return Err(format!("{} at row {}, column {}", what, cell.row + 1, cell.col + 1));
+        }
+
+        Ok(level)
+    }
+
+    /// The map of a level, the lines after `map`. Everything that isn't on the map is left
+    /// empty, for whatever the map comes with to fill in
+    pub(crate) fn parse_map<'a>(lines: impl Iterator<Item = (usize, &'a str)>) -> io::Result<Level> {
+        let map: Vec<(usize, &str)> = lines.filter(|(_, line)| !line.is_empty()).collect();
+        let first = map.first().map(|(n, _)| *n).unwrap_or(1);
+
+        let rows = map.len();
+        let cols = map.first().map(|(_, line)| line.chars().count()).unwrap_or(0);
+
+        if !(MIN_SIZE..=MAX_SIZE).contains(&rows) || !(MIN_SIZE..=MAX_SIZE).contains(&cols) {
+            return Err(invalid(first, &format!("a map has to be {} to {} cells wide and high", MIN_SIZE, MAX_SIZE)));
+        }
+
+        let mut walls = Vec::new();
+        let mut food = Vec::new();
+        let mut body = BTreeSet::new();
+        let mut head = None;
+
+        for (row, (n, line)) in map.iter().enumerate() {
+            if line.chars().count() != cols {
+                return Err(invalid(*n, "all the rows of the map must have the same length"));
+            }
+
+            for (col, ch) in line.chars().enumerate() {
+                let cell = Cell { row, col };
+
+                match ch {
+                    '#' => walls.push(cell),
+                    '.' => {},
+                    '$' => food.push(cell),
+                    'o' => {
+                        body.insert(cell);
+                    },
+                    _ => match dir_from_char(ch) {
+                        Some(_) if head.is_some() => return Err(invalid(*n, "the snake can only have one head")),
+                        Some(dir) => head = Some((cell, dir)),
+                        None => return Err(invalid(*n, &format!("unknown cell {:?}", ch))),
+                    },
+                }
+            }
+        }
+
+        let (head, dir) = head.ok_or_else(|| invalid(first, "the map has no snake"))?;
+
+        // Follow the body from the head, there must be only one way to go on every step
+        let mut snake = vec![head];
+        let mut neck = neighbour(head, opposite(dir), rows, cols);
+
+        while let Some(cell) = neck.filter(|cell| body.remove(cell)) {
+            snake.push(cell);
+
+            let mut next = [Ver(Neg), Ver(Pos), Hor(Neg), Hor(Pos)]
+                .into_iter()
+                .filter_map(|d| neighbour(cell, d, rows, cols))
+                .filter(|c| body.contains(c));
+
+            neck = next.next();
+
+            if next.next().is_some() {
+                return Err(invalid(map[cell.row].0, "the body of the snake branches"));
+            }
+        }
+
+        if snake.len() < 2 {
+            return Err(invalid(map[head.row].0, "the body of the snake has to start right behind its head"));
+        }
+
+        if !body.is_empty() {
+            return Err(invalid(first, "part of the body isn't attached to the snake"));
+        }
+
+        snake.reverse();
+
+        Ok(Level {
+            name: String::new(),
+            moves: None,
+            limit: None,
+            mutators: Vec::new(),
+            symmetry: None,
+            rows,
+            cols,
+            walls,
+            food,
+            snake,
+            dir,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Level> {
+        Level::parse(&fs::read_to_string(path)?)
+    }
+
+    /// A level piped in, from a chat or a gist that may have given it Windows line endings
+    pub fn read(mut reader: impl Read) -> io::Result<Level> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Level::parse(&text)
+    }
+
+    /// The level as a level file, the way `parse` reads it
+    pub fn to_text(&self) -> String {
+        let mut text = format!("snek-level 1\nname {}\n", self.name);
+
+        if let Some(moves) = self.moves {
+            text.push_str(&format!("moves {}\n", moves));
+        }
+
+        if let Some(limit) = self.limit {
+            text.push_str(&format!("limit {}\n", limit));
+        }
+
+        if !self.mutators.is_empty() {
+            let mutators: Vec<&str> = self.mutators.iter().map(|m| m.name()).collect();
+            text.push_str(&format!("mutators {}\n", mutators.join("+")));
+        }
+
+        if let Some(symmetry) = self.symmetry {
+            text.push_str(&format!("symmetry {}\n", symmetry.name()));
+        }
+
+        let mut map = vec![vec!['.'; self.cols]; self.rows];
+
+        for (cells, ch) in [(&self.walls, '#'), (&self.food, '$'), (&self.snake, 'o')] {
+            for cell in cells.iter() {
+                map[cell.row][cell.col] = ch;
+            }
+        }
+
+        let head = self.snake[self.snake.len() - 1];
+        map[head.row][head.col] = dir_to_char(self.dir);
+
+        text.push_str("map\n");
+        for row in map {
+            text.extend(row);
+            text.push('\n');
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(food: &str) -> io::Result<Level> {
+        Level::parse(&format!("snek-level 1\nname Fair\nsymmetry mirror\nmap\n#.....#\n{}\n#.o>..#\n#.....#", food))
+    }
+
+    #[test]
+    fn a_level_exported_reads_back_the_same() {
+        for text in [
+            include_str!("../levels/01-first-steps.snklvl"),
+            include_str!("../levels/04-the-long-way.snklvl"),
+        ] {
+            let level = Level::parse(text).unwrap();
+            let exported = level.to_text();
+
+            assert_eq!(exported, text.replace("\r\n", "\n"));
+            assert_eq!(Level::read(exported.replace('\n', "\r\n").as_bytes()).unwrap().to_text(), exported);
+        }
+    }
+
+    #[test]
+    fn a_limit_comes_after_the_fewest_moves() {
+        let level = |options: &str| {
+            Level::parse(&format!("snek-level 1\nname Far\n{}\nmap\n#####\n#o>.$\n#####", options))
+        };
+
+        let far = level("moves 2\nlimit 3").unwrap();
+        assert_eq!((far.moves, far.limit), (Some(2), Some(3)));
+        assert_eq!(Level::parse(&far.to_text()).unwrap().limit, Some(3));
+
+        assert_eq!(
+            level("moves 2\nlimit 1").unwrap_err().to_string(),
+            "line 1: the limit is lower than the fewest moves the level can be cleared in"
+        );
+        assert_eq!(level("limit soon").unwrap_err().to_string(), "line 3: expected a number of moves");
+    }
+
+    #[test]
+    fn a_symmetric_level_loads_and_keeps_its_symmetry() {
+        let fair = level("#$...$#").unwrap();
+
+        assert_eq!(fair.symmetry, Some(Symmetry::Mirror));
+        assert_eq!(fair.asymmetry(Symmetry::Mirror), None);
+        assert!(fair.asymmetry(Symmetry::Rotate).is_some());
+        assert_eq!(Level::parse(&fair.to_text()).unwrap().symmetry, Some(Symmetry::Mirror));
+    }
+
+    #[test]
+    fn an_asymmetric_level_is_refused() {
+        let unfair = level("#$....#").unwrap_err().to_string();
+
+        assert_eq!(
+            unfair,
+            "line 3: the map isn't symmetric (mirror): the food at row 2, column 2 has nothing across from it"
+        );
+    }
+
+    #[test]
+    fn half_a_map_is_enough() {
+        let half = Level::parse("snek-level 1\nname Half\nmap\n##....\n.$....\no>....").unwrap();
+
+        let mirrored = half.symmetric(Symmetry::Mirror).unwrap();
+        assert_eq!(mirrored.asymmetry(Symmetry::Mirror), None);
+        assert!(mirrored.to_text().ends_with("symmetry mirror\nmap\n##..##\n.$..$.\no>....\n"));
+        assert_eq!(Level::parse(&mirrored.to_text()).unwrap().symmetry, Some(Symmetry::Mirror));
+
+        let rotated = half.symmetric(Symmetry::Rotate).unwrap();
+        assert!(rotated.to_text().ends_with("map\n##....\n.$..$.\no>..##\n"));
+    }
+
+    #[test]
+    fn a_copy_can_not_land_on_something_else() {
+        let level = |map: &str| Level::parse(&format!("snek-level 1\nname Half\nmap\n{}", map)).unwrap();
+
+        assert_eq!(
+            level("#.....\n......\n....o>").symmetric(Symmetry::Rotate).unwrap_err(),
+            "the snake is in the way at row 3, column 6"
+        );
+        assert_eq!(
+            level("#....$\n......\n.o>...").symmetric(Symmetry::Mirror).unwrap_err(),
+            "a wall and food are in the same cell at row 1, column 1"
+        );
+    }
+
+    #[test]
+    fn a_rotated_cell_is_across_the_middle() {
+        let cell = Cell { row: 0, col: 1 };
+
+        assert_eq!(Symmetry::Rotate.across(cell, 4, 7), Cell { row: 3, col: 5 });
+        assert_eq!(Symmetry::Mirror.across(cell, 4, 7), Cell { row: 0, col: 5 });
+    }
+}
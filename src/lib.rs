@@ -0,0 +1,44 @@
+//! The engine and everything around it. The binary in `main.rs` is the terminal game itself,
+//! everything it's built from lives here so it can be used from tests too.
+
+pub mod board;
+pub mod boost;
+pub mod bot;
+pub mod challenge;
+pub mod codec;
+pub mod config;
+pub mod crash;
+pub mod cues;
+pub mod demo;
+pub mod error;
+pub mod fetch;
+pub mod game;
+pub mod gif;
+pub mod graphics;
+pub mod latency;
+pub mod level;
+pub mod overlay;
+pub mod pack;
+pub mod perf;
+pub mod photo;
+pub mod pixels;
+pub mod player;
+pub mod png;
+pub mod profile;
+pub mod puzzle;
+pub mod render;
+pub mod replay;
+pub mod rivalry;
+pub mod rng;
+pub mod rules;
+pub mod ruleset;
+pub mod scenario;
+pub mod scores;
+pub mod session;
+pub mod simulate;
+#[cfg(unix)]
+pub mod spectate;
+pub mod subtitles;
+pub mod title;
+pub mod trace;
+pub mod tuning;
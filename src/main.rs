@@ -1,257 +1,1474 @@
-use std::mem::discriminant;
+use std::env;
+use std::fmt::Write as _;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
 use std::process::exit;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use console::Key::*;
-use console::Term;
-use rand::prelude::*;
+use console::{Key, Term};
 
-use Direction::*;
-use Polarity::*;
-use Tile::*;
+use snake_video::boost::{self, Hold, Stamina};
+use snake_video::bot::Bot;
+use snake_video::challenge::{self, Challenge};
+use snake_video::config::{Action, Config};
+use snake_video::cues::Cues;
+use snake_video::error::{self, Error, Result};
+use snake_video::game::{Direction, Direction::*, Game, Polarity::*};
+use snake_video::graphics::Protocol;
+use snake_video::level::{Level, Symmetry};
+use snake_video::overlay::Overlay;
+use snake_video::pack::{self, Pack};
+use snake_video::photo::{self, Camera};
+use snake_video::render::{Scale, Transition};
+use snake_video::replay::Replay;
+use snake_video::scenario::Scenario;
+use snake_video::rivalry::{self, Round, Turn};
+use snake_video::session::{Outcome, Session};
+use snake_video::subtitles::{self, Subtitles};
+use snake_video::trace::{Recorder, Trace};
+use snake_video::tuning::Console;
+use snake_video::ruleset::{parse_size, Mode, Mutator, Ruleset, MAX_GRACE};
+use snake_video::{crash, demo, fetch, latency, perf, player, profile, puzzle, render, rules, scores, simulate, title};
+#[cfg(unix)]
+use snake_video::spectate;
 
-const ROWS:usize = 15;
-const COLS:usize = 30;
+/// What the input thread tells the game thread, in the order it happened
+enum InputEvent {
+    /// A key came in, with what it does if anything. What it does comes right after it as an
+    /// event of its own, this one is for what cares about the keys themselves: the input trace,
+    /// and telling whether a key is held down
+    Key(Key, Option<Action>),
+    /// Go that way from the next tick on, if the snake can
+    Turn(Direction),
+    /// Show or hide how many ticks per second the game really manages
+    Perf,
+    /// Stop the game, or go on with it
+    Pause,
+    /// Show the rules instead of the field, or go back to the game
+    Rules,
+    /// Open the tuning console, or close it
+    Console,
+    /// A key typed into the open console
+    Typed(Key),
+    Quit,
+    /// The terminal might not have the same size anymore
+    Resize,
+}
+
+/// Everything the game is played with
+struct PlayOptions {
+    challenge: Challenge,
+    /// Where to keep the stats of the game as text files for streaming software
+    overlay: Option<PathBuf>,
+    /// Draw a fading trail behind the snake
+    trail: bool,
+    /// Ring the terminal bell for what's ahead of the snake
+    cues: bool,
+    /// The biggest the field is drawn, if that's large print
+    scale: Scale,
+    /// How to draw the field as pixels, if the terminal can
+    graphics: Option<Protocol>,
+    /// Where to write the input trace of the game
+    trace: Option<PathBuf>,
+    /// The challenge was given, with the weight of its heavy snake. Otherwise that comes from
+    /// the config of the profile
+    shared: bool,
+    /// Keep the last ticks before the death as a photo finish
+    photo_finish: bool,
+    /// With a PNG of each of them
+    photo_pictures: bool,
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // The profile goes with any command, all the files are read from its directory
+    if let Some(i) = args.iter().position(|arg| arg == "--profile") {
+        match args.get(i + 1) {
+            Some(name) if profile::select(name) => {},
+            Some(name) => {
+                eprintln!("Invalid profile name: {} (only letters, digits, - and _)", name);
+                exit(2);
+            },
+            None => {
+                eprintln!("Missing value for --profile");
+                exit(2);
+            },
+        }
+
+        args.drain(i..i + 2);
+    }
+
+    // Everything played until the program quits, whatever the command
+    let mut session = Session::default();
 
-#[derive(Clone, Copy)]
-struct Cell {
-    row: usize, 
-    col: usize,
+    match args.first().map(|s| s.as_str()) {
+        Some("scores") => show_scores(&args[1..]),
+        Some("profiles") => show_profiles(),
+        Some("hotseat") => hotseat(&args[1..], &mut session),
+        Some("rivalry") => show_rivalries(&args[1..]),
+        Some("photos") => show_photos(),
+        Some("latency") => measure_latency(&args[1..]),
+        Some("simulate") => run_simulation(&args[1..], false),
+        Some("bench") => run_simulation(&args[1..], true),
+        Some("demo") => record_demo(&args[1..]),
+        Some("replay") => match &args[1..] {
+            [path] => watch_replay(Path::new(path), &subtitles::sidecar(Path::new(path))),
+            [path, flag, subs] if flag == "--subs" => watch_replay(Path::new(path), Path::new(subs)),
+            _ => usage(),
+        },
+        Some("compare") => match &args[1..] {
+            [left, right] => compare_replays(Path::new(left), Path::new(right)),
+            _ => usage(),
+        },
+        Some("verify") => match args.get(1) {
+            Some(path) => verify_replay(Path::new(path), &args[2..]),
+            None => usage(),
+        },
+        Some("scenario") => match &args[1..] {
+            [path] => run_scenario(Path::new(path), false),
+            [path, flag] if flag == "--watch" => run_scenario(Path::new(path), true),
+            _ => usage(),
+        },
+        Some("trace") => match args.get(1) {
+            Some(path) => watch_trace(Path::new(path)),
+            None => usage(),
+        },
+        Some("puzzle") => puzzle_command(&args[1..], &mut session),
+        Some("levels") => levels_command(&args[1..]),
+        #[cfg(unix)]
+        Some("spectate") => match &args[1..] {
+            [] => spectate::run(&spectate::socket_path(), None),
+            [flag, subs] if flag == "--subs" => spectate::run(&spectate::socket_path(), Some(Path::new(subs))),
+            _ => usage(),
+        },
+        None => {
+            play_game(parse_play_args(&[]), &mut session);
+        },
+        Some(arg) if arg.starts_with("--") => {
+            play_game(parse_play_args(&args), &mut session);
+        },
+        Some(other) => {
+            eprintln!("Unknown command: {}", other);
+            usage();
+        }
+    }
+
+    // Once the terminal is back to normal
+    if session.played() {
+        session.print();
+    }
 }
 
-#[derive(Clone)]
-enum Tile {
-    Empty,
-    Snake(Cell),
-    Food,
+fn usage() -> ! {
+    eprintln!("Usage: snake_video [--profile NAME] [COMMAND]");
+    eprintln!();
+    eprintln!("       snake_video [--seed N | --challenge CODE] [--mode MODE] [--grace N]");
+    eprintln!("                   [--mutator NAME]...");
+    eprintln!("                   [--overlay DIR] [--trail on|off] [--cues on|off] [--trace FILE]");
+    eprintln!("                   [--large-print 2|3|off] [--graphics auto|kitty|sixel|off]");
+    eprintln!("                   [--photo-finish text|png|off]");
+    eprintln!("       snake_video replay FILE [--subs FILE]");
+    eprintln!("       snake_video compare FILE FILE");
+    eprintln!("       snake_video verify FILE [--expect-score N] [--expect-length N]");
+    eprintln!("       snake_video trace FILE");
+    eprintln!("       snake_video photos");
+    eprintln!("       snake_video scenario FILE [--watch]");
+    eprintln!("       snake_video puzzle [N | PACK [N] | FILE [--watch-level] | -]");
+    eprintln!("       snake_video puzzle export N|PACK N|FILE");
+    eprintln!("       snake_video puzzle symmetric mirror|rotate N|PACK N|FILE");
+    eprintln!("       snake_video levels [fetch URL [--sha256 HEX] [--name NAME]]");
+    eprintln!("       snake_video spectate [--subs FILE]");
+    eprintln!("       snake_video simulate|bench [--games N] [--threads N] [--seed N] [--max-ticks N]");
+    eprintln!("                            [--size COLSxROWS]");
+    eprintln!("       snake_video demo [--bot greedy|astar] [--seconds N] [--gif FILE] [--seed N]");
+    eprintln!("                        [--size COLSxROWS]");
+    eprintln!("       snake_video scores [--mode MODE] [--size COLSxROWS] [--mutator NAME]...");
+    eprintln!("       snake_video profiles");
+    eprintln!("       snake_video hotseat NAME NAME [--rounds N] [game options]");
+    eprintln!("       snake_video rivalry [NAME NAME]");
+    eprintln!("       snake_video latency [--trials N] [--tick MS]");
+    exit(2);
 }
 
-#[derive(Clone)]
-enum Polarity {
-    Pos,
-    Neg,
+/// Options of the game itself. Without any, a random run with the default rules is played
+fn parse_play_args(args: &[String]) -> PlayOptions {
+    let mut options = PlayOptions {
+        challenge: Challenge {
+            ruleset: Ruleset::default(),
+            seed: rand::random(),
+        },
+        overlay: None,
+        trail: false,
+        cues: false,
+        scale: Scale::Full,
+        graphics: None,
+        trace: None,
+        shared: false,
+        photo_finish: true,
+        photo_pictures: false,
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
+
+        let ok = match arg.as_str() {
+            "--seed" => value.parse().map(|seed| options.challenge.seed = seed).is_ok(),
+            "--mode" if value == Mode::Puzzle.name() => {
+                eprintln!("Puzzles are played on levels, see `snake_video puzzle`");
+                exit(2);
+            },
+            "--mode" => Mode::from_name(value).map(|m| options.challenge.ruleset.mode = m).is_some(),
+            "--grace" => value
+                .parse()
+                .ok()
+                .filter(|grace| *grace <= MAX_GRACE)
+                .map(|grace| options.challenge.ruleset.grace = grace)
+                .is_some(),
+            "--challenge" => Challenge::parse(value)
+                .map(|c| {
+                    options.challenge = c;
+                    options.shared = true;
+                })
+                .is_some(),
+            "--mutator" => Mutator::from_name(value)
+                .map(|m| {
+                    if !options.challenge.ruleset.has(m) {
+                        options.challenge.ruleset.mutators.push(m);
+                    }
+                })
+                .is_some(),
+            "--overlay" => {
+                options.overlay = Some(PathBuf::from(value));
+                true
+            },
+            "--trace" => {
+                options.trace = Some(PathBuf::from(value));
+                true
+            },
+            "--trail" => match value.as_str() {
+                "on" | "off" => {
+                    options.trail = value == "on";
+                    true
+                },
+                _ => false,
+            },
+            "--large-print" => match value.as_str() {
+                "2" | "3" => value.parse().map(|n| options.scale = Scale::Large(n)).is_ok(),
+                "off" => {
+                    options.scale = Scale::Full;
+                    true
+                },
+                _ => false,
+            },
+            "--graphics" => match value.as_str() {
+                "auto" => {
+                    options.graphics = Protocol::detect();
+                    true
+                },
+                "off" => {
+                    options.graphics = None;
+                    true
+                },
+                name => Protocol::from_name(name).map(|p| options.graphics = Some(p)).is_some(),
+            },
+            "--cues" => match value.as_str() {
+                "on" | "off" => {
+                    options.cues = value == "on";
+                    true
+                },
+                _ => false,
+            },
+            "--photo-finish" => match value.as_str() {
+                "text" | "png" | "off" => {
+                    options.photo_finish = value != "off";
+                    options.photo_pictures = value == "png";
+                    true
+                },
+                _ => false,
+            },
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
+        };
+
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
+
+    options
 }
 
-#[derive(Clone)]
-enum Direction { 
-    Hor(Polarity),
-    Ver(Polarity),
+/// Print the high score table, grouped by ruleset so that scores made with different
+/// settings are never compared to each other
+fn show_scores(args: &[String]) {
+    let mut filter = scores::Filter::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
+
+        let ok = match arg.as_str() {
+            "--mode" => Mode::from_name(value).map(|m| filter.mode = Some(m)).is_some(),
+            "--size" => parse_size(value).map(|s| filter.size = Some(s)).is_some(),
+            "--mutator" => Mutator::from_name(value).map(|m| filter.mutators.push(m)).is_some(),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
+        };
+
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
+
+    let entries = scores::load().unwrap_or_else(|e| error::report(Error::Scores(e)));
+    let groups = scores::grouped(&entries, &filter);
+
+    if groups.is_empty() {
+        println!("No high scores yet!");
+        return;
+    }
+
+    for (ruleset, group) in groups {
+        println!("{}", ruleset);
+
+        for (place, entry) in group.iter().take(10).enumerate() {
+            println!("  {:>2}. {:>5} pts  len {}", place + 1, entry.score, entry.length);
+        }
+
+        println!();
+    }
 }
 
-struct Control {
-    dir_current: Direction,
-    dir_next: Direction,
+/// List the profiles with a few stats about each of them
+fn show_profiles() {
+    let names = profile::list().unwrap_or_else(|e| error::report(Error::Scores(e)));
+
+    for name in [None].into_iter().chain(names.iter().map(|name| Some(name.as_str()))) {
+        let summary = profile::summary(name).unwrap_or_else(|e| error::report(Error::Scores(e)));
+        let current = if name == profile::current().as_deref() { " *" } else { "" };
+
+        println!(
+            "{:<16} {:>5} games  best {:>5}  {:>6} food eaten{}",
+            summary.name, summary.games, summary.best, summary.food, current,
+        );
+    }
 }
 
-fn main() {
-    let control = Arc::new(Mutex::new(Control {
-        dir_current: Hor(Pos),
-        dir_next: Hor(Pos)
-    }));
-
-    let control_thread = Arc::clone(&control);
-
-    // Spawn a thread where the game state will be updated and rendered
-    thread::spawn(move || {
-        // We want a buffered stdout to print the resulting game state at once
-        let term = Term::buffered_stdout();
-
-        let mut field:Vec<Vec<Tile>> = vec![vec![Empty; COLS]; ROWS];
-
-        // Get a random empty cell on the field.
-        // Only used to place food
-        //
-        // The method I've chosen is not the most efficient. The better way would probably be to
-        // keep track of empty/non-empy cells during the game update. But I'm lazy so this will do.
-        let get_rnd_empty_cell = |field:&Vec<Vec<Tile>>| -> Option<Cell> {
-            let mut empty_cells:Vec<(usize, usize)> = Vec::new();
-
-            for row in 0..ROWS {
-                for col in 0..COLS {
-                    if matches!(field[row][col], Empty) {
-                        empty_cells.push((row, col));
-                    }
-                }
+/// Two profiles take turns on the same terminal, each with its own keys, scores and replays.
+/// Both play the same challenge in a round, and who goes first changes every round. A game
+/// left before its game over ends the match, without the round it was in
+fn hotseat(args: &[String], session: &mut Session) {
+    let (players, args) = match args {
+        [a, b, rest @ ..] if !a.starts_with("--") && !b.starts_with("--") => ([a, b], rest),
+        _ => usage(),
+    };
+
+    for name in players {
+        if !profile::valid_name(name) {
+            eprintln!("Invalid profile name: {} (only letters, digits, - and _)", name);
+            exit(2);
+        }
+    }
+
+    if players[0] == players[1] {
+        eprintln!("Hot-seat needs two different profiles");
+        exit(2);
+    }
+
+    // The rounds are for the match, everything else is for the games
+    let mut rounds = 3;
+    let mut play_args = Vec::new();
+
+    let mut rest = args.iter();
+    while let Some(arg) = rest.next() {
+        if arg != "--rounds" {
+            play_args.push(arg.clone());
+            continue;
+        }
+
+        match rest.next().and_then(|value| value.parse().ok()).filter(|n| *n > 0) {
+            Some(n) => rounds = n,
+            None => {
+                eprintln!("Invalid value for --rounds");
+                exit(2);
+            },
+        }
+    }
+
+    let names = [players[0].as_str(), players[1].as_str()];
+    let previous = profile::current();
+    let term = Term::stdout();
+
+    let mut played = Vec::new();
+
+    'rounds: for round in 0..rounds {
+        let challenge = parse_play_args(&play_args).challenge;
+        let mut turns: [Option<Turn>; 2] = [None, None];
+
+        let order = if round % 2 == 0 { [0, 1] } else { [1, 0] };
+
+        for i in order {
+            if !turn_screen(&term, round + 1, rounds, names[i]) {
+                break 'rounds;
             }
 
-            if empty_cells.is_empty() {
-                return None;
+            // Both players play the very same challenge, whatever their configs say
+            let mut options = parse_play_args(&play_args);
+            options.challenge = challenge.clone();
+            options.shared = true;
+
+            profile::switch(Some(names[i]));
+            let outcome = play_game(options, session);
+            profile::switch(previous.as_deref());
+
+            match outcome {
+                Some(outcome) if outcome.over => {
+                    turns[i] = Some(Turn {
+                        player: names[i].to_string(),
+                        score: outcome.score,
+                        length: outcome.length,
+                    });
+                },
+                _ => break 'rounds,
             }
+        }
+
+        let [Some(a), Some(b)] = turns else {
+            break;
+        };
 
-            let mut rng = rand::thread_rng();
+        let round = Round::new(challenge.ruleset.fingerprint(), [a, b]);
+        rivalry::save(&round).unwrap_or_else(|e| error::report(Error::Rivalry(e)));
+        played.push(round);
+    }
 
-            empty_cells.shuffle(&mut rng);
+    let _ = term.clear_screen();
 
-            Some(Cell {
-                row: empty_cells[0].0,
-                col: empty_cells[0].1,
-            })
+    for (n, round) in played.iter().enumerate() {
+        let [a, b] = &round.turns;
+        let winner = match round.winner() {
+            Some(winner) => format!("{} wins", winner),
+            None => "draw".to_string(),
         };
 
-        let mut head = Cell {
-            row: 7,
-            col: 14
+        println!("Round {}: {} {}, {} {}, {}", n + 1, a.player, a.score, b.player, b.score, winner);
+    }
+
+    let all = rivalry::load().unwrap_or_else(|e| error::report(Error::Rivalry(e)));
+    let rivalry = rivalry::between(&all, names[0], names[1]);
+
+    if rivalry.rounds > 0 {
+        println!();
+        rivalry.lines().iter().for_each(|line| println!("{}", line));
+    }
+}
+
+/// Who plays next, until they press a key. Escape ends the match instead
+fn turn_screen(term: &Term, round: u32, rounds: u32, player: &str) -> bool {
+    let show = || -> io::Result<Key> {
+        term.clear_screen()?;
+        term.write_line(&format!("Round {} of {}: {}'s turn", round, rounds, player))?;
+        term.write_line("")?;
+        term.write_line("Press any key to start, escape to stop the match")?;
+        term.read_key()
+    };
+
+    match show() {
+        Ok(key) => key != Key::Escape,
+        Err(e) => error::report(Error::Terminal(e)),
+    }
+}
+
+/// How the profiles did against each other in hot-seat, every pair of them or just the two
+/// given
+fn show_rivalries(args: &[String]) {
+    let rounds = rivalry::load().unwrap_or_else(|e| error::report(Error::Rivalry(e)));
+
+    let pairs = match args {
+        [] => rivalry::pairs(&rounds),
+        [a, b] => vec![[a.clone(), b.clone()]],
+        _ => usage(),
+    };
+
+    if pairs.is_empty() {
+        println!("No hot-seat rounds played yet, see `snake_video hotseat`");
+    }
+
+    for (i, [a, b]) in pairs.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+
+        for line in rivalry::between(&rounds, a, b).lines() {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Go through the last moments of the games lost with the profile in use
+fn show_photos() {
+    let photos = photo::list().unwrap_or_else(|e| error::report(Error::Photos(e)));
+
+    if photos.is_empty() {
+        println!("No photo finishes yet, one is kept after every game over");
+        return;
+    }
+
+    let term = Term::stdout();
+    photo::view(&term, &photos).unwrap_or_else(|e| error::fail(&term, Error::Terminal(e)));
+}
+
+/// Let a bot play without a terminal and make a GIF of it
+fn record_demo(args: &[String]) {
+    let mut bot = Bot::Astar;
+    let mut seconds = 30;
+    let mut path = PathBuf::from("demo.gif");
+    let mut challenge = Challenge {
+        ruleset: Ruleset::default(),
+        seed: rand::random(),
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
+
+        let ok = match arg.as_str() {
+            "--bot" => Bot::from_name(value).map(|b| bot = b).is_some(),
+            "--seconds" => value.parse().ok().filter(|s| *s > 0).map(|s| seconds = s).is_some(),
+            "--gif" => {
+                path = PathBuf::from(value);
+                true
+            },
+            "--seed" => value.parse().map(|seed| challenge.seed = seed).is_ok(),
+            "--size" => parse_size(value)
+                .map(|(cols, rows)| {
+                    challenge.ruleset.cols = cols;
+                    challenge.ruleset.rows = rows;
+                })
+                .is_some(),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
         };
 
-        field[head.row][head.col] = Snake(head);
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
+
+    let demo = demo::record(&challenge, bot, seconds, &path).unwrap_or_else(|e| error::report(Error::Save("demo GIF", e)));
+
+    println!(
+        "{} frames of the {} bot on {} saved to {}, score {}  len {}",
+        demo.frames,
+        bot.name(),
+        challenge.code(),
+        path.display(),
+        demo.score,
+        demo.length,
+    );
+}
+
+/// Measure how long a key takes to show on the screen, to tune the frame pacing
+fn measure_latency(args: &[String]) {
+    let mut trials: usize = 20;
+    let mut tick = boost::TICK;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
 
-        let mut tail = Cell {
-            row: 7,
-            col: 13
+        let ok = match arg.as_str() {
+            "--trials" => value.parse().ok().filter(|n| *n > 0).map(|n| trials = n).is_some(),
+            "--tick" => value.parse().ok().filter(|ms| *ms > 0).map(|ms| tick = Duration::from_millis(ms)).is_some(),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
         };
 
-        field[tail.row][tail.col] = Snake(head.clone());
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
+
+    latency::run(trials, tick);
+}
 
-        // Place the first food on the field
-        let rnd_cell = get_rnd_empty_cell(&field).unwrap();
-        field[rnd_cell.row][rnd_cell.col] = Food;
+/// Play a lot of games with the bot, without a terminal. `simulate` is about the results of the
+/// games, `bench` about how fast the engine plays them
+fn run_simulation(args: &[String], bench: bool) {
+    let mut options = simulate::Options {
+        games: 1000,
+        threads: 0,
+        seed: rand::random(),
+        max_ticks: 10_000,
+    };
 
-        loop {
-            let dir_current = {
-                let mut control = control_thread.lock().unwrap();
-                control.dir_current = control.dir_next.clone();
+    // There is no terminal to fit in, so the field can be as big as you like
+    let mut ruleset = Ruleset::default();
 
-                control.dir_current.clone()
-            };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
 
-            /**
-             * This function is used to increase or decrease the horizontal or vertical position of
-             * the snake's head on the field.
-             * 
-             * p - current position
-             * pol - polarity (negative or positive)
-             * lim - maximum value. We will use COLS or ROWS here
-             */
-            fn step(p:usize, pol:Polarity, lim:usize) -> usize {
-                match pol {
-                    Pos => if p == lim - 1 {
-                        0
-                    } else {
-                        p + 1
-                    },
-                    Neg => if p == 0 {
-                        lim - 1
-                    } else {
-                        p - 1
-                    },
-                }
+        let ok = match arg.as_str() {
+            "--games" => value.parse().map(|n| options.games = n).is_ok(),
+            "--threads" => value.parse().map(|n| options.threads = n).is_ok(),
+            "--seed" => value.parse().map(|n| options.seed = n).is_ok(),
+            "--max-ticks" => value.parse().map(|n| options.max_ticks = n).is_ok(),
+            "--size" => parse_size(value)
+                .map(|(cols, rows)| {
+                    ruleset.cols = cols;
+                    ruleset.rows = rows;
+                })
+                .is_some(),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
             }
+        };
+
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
 
-            let head_prev = head.clone();
+    let (stats, elapsed) = simulate::run(&ruleset, &options);
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
 
-            // Move the head position according to the direction
-            match dir_current {
-                Hor(pol) => {
-                    head.col = step(head.col, pol, COLS);
+    if bench {
+        println!("{} games, {} ticks in {:.3}s", stats.games, stats.ticks, secs);
+        println!("{:.0} games/s, {:.0} ticks/s", stats.games as f64 / secs, stats.ticks as f64 / secs);
+        return;
+    }
+
+    println!("Ruleset: {}  Seed: {}", ruleset, options.seed);
+    println!("Games: {}  Died: {}  (the others hit the {} ticks limit)", stats.games, stats.deaths, options.max_ticks);
+    println!("Score: mean {:.2}  best {}  worst {}", stats.mean_score(), stats.best_score, stats.worst_score);
+    println!("Length: mean {:.2}  Ticks: mean {:.1}", stats.mean_length(), stats.mean_ticks());
+    println!("Took {:.3}s", secs);
+}
+
+/// Everything about puzzles: the level packs, playing a level and exporting one
+fn puzzle_command(args: &[String], session: &mut Session) {
+    match args {
+        [] => show_puzzles(),
+        [export, rest @ ..] if export == "export" => print!("{}", find_level(rest).1.to_text()),
+        [symmetric, kind, rest @ ..] if symmetric == "symmetric" => {
+            let symmetry = Symmetry::from_name(kind).unwrap_or_else(|| usage());
+            let (_, level) = find_level(rest);
+
+            match level.symmetric(symmetry) {
+                Ok(level) => print!("{}", level.to_text()),
+                Err(msg) => {
+                    eprintln!("{} can't be made symmetric: {}", level.name, msg);
+                    exit(65);
                 },
-                Ver(pol) => {
-                    head.row = step(head.row, pol, ROWS);
-                }
+            }
+        },
+        [pack] if pack::find(pack).is_some() => show_pack(&load_pack(pack)),
+        [file, watch] if watch == "--watch-level" => {
+            if !Path::new(file).is_file() {
+                eprintln!("Only a level file can be watched, {} isn't one", file);
+                exit(2);
             }
 
-            // Let's check the type of the tile the head will end up in
-            match field[head.row][head.col] {
-                Food => {
-                    // If it is food - try to find a random empty cell and put another piece of
-                    // food there
-                    match get_rnd_empty_cell(&field) {
-                        Some(cell) => {
-                            // Create a new food tile in an empty place
-                            field[cell.row][cell.col] = Food;
-                        },
-                        None => {
-                            // No empty cells to put food into
-                            // I guess we're not going to do anything here...
-                        }
-                    }
-                },
-                Snake(_) => {
-                    // The snake hit itself... It is a game over
-                    term.move_cursor_to(10, ROWS/2).unwrap();
-                    term.write_str("GAME OVER!").unwrap();
-                    term.flush().unwrap();
-                    break;
-                },
-                Empty => {
-                    // Empty cell, so just pull the tail forward
-                    if let Snake(next) = field[tail.row][tail.col] {
-                        field[tail.row][tail.col] = Empty;
-                        tail = next.clone();
-                    }
-                },
-            };
+            let (record, level) = find_level(std::slice::from_ref(file));
+            play_puzzle(level, &record, Some(Path::new(file)), session);
+        },
+        rest => {
+            let (record, level) = find_level(rest);
+            play_puzzle(level, &record, None, session);
+        },
+    }
+}
 
-            // Replace the tile at the previous head position to a new Snake tile referencing the
-            // new head position
-            field[head_prev.row][head_prev.col] = Snake(head.clone());
+/// List the built-in levels and the installed packs
+fn show_puzzles() {
+    show_pack(&puzzle::builtin());
 
-            // Put a new Snake tile in the new head position. The `head` value doesn't have any use
-            // here. Ideally we should allow this valu to be empty with Option for example.
-            field[head.row][head.col] = Snake(head);
+    if show_installed() {
+        println!();
+    }
 
-            // Clear the screen
-            term.clear_screen().unwrap();
+    println!("Play a level with `snake_video puzzle N`, `snake_video puzzle PACK N` or `snake_video puzzle FILE`");
+}
 
-            // Render the field
-            for row in 0..ROWS {
-                for col in 0..COLS {
-                    let ch = match field[row][col] {
-                        Empty => ".",
-                        Snake(_) => "@",
-                        Food => "$"
-                    };
+/// The installed level packs, and getting more of them
+fn levels_command(args: &[String]) {
+    let (url, options) = match args {
+        [] => {
+            if !show_installed() {
+                println!("No level packs yet, get some with `snake_video levels fetch URL`");
+            }
+            return;
+        },
+        [fetch, url, options @ ..] if fetch == "fetch" => (url, options),
+        _ => usage(),
+    };
 
-                    term.write_str(ch).unwrap();
-                }
+    let mut checksum = None;
+    let mut name = None;
 
-                term.move_cursor_down(1).unwrap();
-                term.clear_line().unwrap();
+    let mut options = options.iter();
+    while let Some(arg) = options.next() {
+        let value = match options.next() {
+            Some(value) => value.as_str(),
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
             }
+        };
 
-            // Flush the buffered output to the terminal
-            term.flush().unwrap();
+        match arg.as_str() {
+            "--sha256" => checksum = Some(value),
+            "--name" => name = Some(value),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
+        }
+    }
 
-            // Sleep for some time. The amount of milliseconds to sleep can control the pace of the
-            // game
-            thread::sleep(Duration::from_millis(100));
+    match fetch::pack(url, checksum, name) {
+        Ok(path) => println!("Installed {}", path.display()),
+        Err(e) => error::report(Error::Download(url.clone(), e)),
+    }
+}
+
+/// List the level packs in the packs directory. Returns whether there are any
+fn show_installed() -> bool {
+    let installed = pack::installed().unwrap_or_else(|e| error::report(Error::Pack(pack::dir(), e)));
+
+    if !installed.is_empty() {
+        println!("Installed packs:");
+    }
+
+    for path in installed.iter() {
+        let id = path.file_stem().unwrap_or_default().to_string_lossy();
+
+        // One broken pack shouldn't hide the others
+        match Pack::load(path) {
+            Ok(pack) => {
+                let by = pack.author.as_ref().map(|author| format!(" by {}", author)).unwrap_or_default();
+                println!("  {:<16} {}{}, {} levels", id, pack.name, by, pack.levels.len());
+            },
+            Err(e) => println!("  {:<16} can't be played: {}", id, e),
         }
-    });
+    }
+
+    !installed.is_empty()
+}
+
+/// List the levels of a pack with the best result of the profile on each of them
+fn show_pack(pack: &Pack) {
+    let records = puzzle::records().unwrap_or_else(|e| error::report(Error::Scores(e)));
+
+    match pack.author.as_ref() {
+        Some(author) => println!("{} by {}", pack.name, author),
+        None => println!("{}", pack.name),
+    }
+
+    for (n, level) in pack.levels.iter().enumerate() {
+        let target = level.moves.map(|moves| format!("{} moves", moves)).unwrap_or_default();
+        let record = pack.record_name(level);
+
+        let best = match records.iter().find(|(name, _)| *name == record) {
+            Some((_, moves)) => {
+                let stars = puzzle::stars(*moves, level.moves) as usize;
+                format!("best {:>3}  [{:*<stars$}{:.<rest$}]", moves, "", "", stars = stars, rest = 3 - stars)
+            },
+            None => "not cleared yet".to_string(),
+        };
+
+        println!("  {:>2}. {:<20} {:>9}  {}", n + 1, level.name, target, best);
+    }
+
+    println!();
+}
+
+fn load_pack(arg: &str) -> Pack {
+    let path = pack::find(arg).unwrap_or_else(|| PathBuf::from(arg));
+
+    Pack::load(&path).unwrap_or_else(|e| error::report(Error::Pack(path, e)))
+}
+
+/// A level of a pack by its number, along with the name its records are kept under
+fn pick_level(mut pack: Pack, arg: &str) -> (String, Level) {
+    match arg.parse::<usize>() {
+        Ok(n) if (1..=pack.levels.len()).contains(&n) => {
+            let level = pack.levels.swap_remove(n - 1);
+            (pack.record_name(&level), level)
+        },
+        _ => {
+            eprintln!("{} has levels 1 to {}", pack.name, pack.levels.len());
+            exit(2);
+        },
+    }
+}
+
+/// A built-in level by its number, a level of a pack, a level file, or `-` for a level piped
+/// in, e.g. pasted from a chat
+fn find_level(args: &[String]) -> (String, Level) {
+    let arg = match args {
+        [pack, n] => return pick_level(load_pack(pack), n),
+        [arg] => arg,
+        _ => usage(),
+    };
+
+    if arg.parse::<usize>().is_ok() {
+        return pick_level(puzzle::builtin(), arg);
+    }
+
+    let loaded = if arg == "-" {
+        Level::read(io::stdin())
+    } else {
+        Level::load(Path::new(arg))
+    };
+
+    let level = loaded.unwrap_or_else(|e| error::report(Error::Level(PathBuf::from(arg), e)));
+    (level.name.clone(), level)
+}
+
+fn play_puzzle(level: Level, record: &str, watch: Option<&Path>, session: &mut Session) {
+    let config = Config::load().unwrap_or_else(|e| error::screen(&Term::stdout(), Error::Config(e)));
+
+    puzzle::run(level, record, &config, watch, session);
+}
+
+/// Watch a replay with the captions from the given file, if there is one
+fn watch_replay(path: &Path, subs: &Path) {
+    let replay = match Replay::load(path) {
+        Ok(replay) => replay,
+        Err(e) => error::report(Error::Replay(path.to_path_buf(), e)),
+    };
+
+    let subtitles = match Subtitles::load(subs) {
+        Ok(subtitles) => subtitles,
+        Err(e) => error::report(Error::Subtitles(subs.to_path_buf(), e)),
+    };
+
+    player::run(replay, subtitles);
+}
+
+/// Watch two replays of the same challenge side by side
+fn compare_replays(left: &Path, right: &Path) {
+    let load = |path: &Path| Replay::load(path).unwrap_or_else(|e| error::report(Error::Replay(path.to_path_buf(), e)));
+    let (a, b) = (load(left), load(right));
+
+    // Anything else and they'd go different ways from the first tick on
+    if a.challenge.code() != b.challenge.code() {
+        let e = io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("it's {}, not the same challenge as {}", b.challenge.code(), a.challenge.code()),
+        );
+        error::report(Error::Replay(right.to_path_buf(), e));
+    }
+
+    player::compare(a, b);
+}
+
+/// Play a replay again without showing it and check that it makes the score it's said to.
+/// Exits with 1 if it doesn't, or if there's anything else wrong with it
+fn verify_replay(path: &Path, args: &[String]) {
+    let mut score: Option<u32> = None;
+    let mut length: Option<usize> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => {
+                eprintln!("Missing value for {}", arg);
+                exit(2);
+            }
+        };
+
+        let ok = match arg.as_str() {
+            "--expect-score" => value.parse().map(|n| score = Some(n)).is_ok(),
+            "--expect-length" => value.parse().map(|n| length = Some(n)).is_ok(),
+            _ => {
+                eprintln!("Unknown option: {}", arg);
+                usage();
+            }
+        };
+
+        if !ok {
+            eprintln!("Invalid value for {}: {}", arg, value);
+            exit(2);
+        }
+    }
+
+    let replay = Replay::load(path).unwrap_or_else(|e| error::report(Error::Replay(path.to_path_buf(), e)));
+    let verdict = replay.verify();
+    let game = &verdict.game;
+
+    println!("Challenge: {}", replay.challenge.code());
+    println!(
+        "Score: {}  Length: {}  Ticks: {}{}",
+        game.score,
+        game.length,
+        game.ticks,
+        if game.over { "" } else { " (the replay ends before the game over)" },
+    );
+
+    let mut problems = verdict.problems();
+
+    if let Some(score) = score.filter(|score| *score != game.score) {
+        problems.push(format!("it scores {}, not {}", game.score, score));
+    }
+    if let Some(length) = length.filter(|length| *length != game.length) {
+        problems.push(format!("the snake gets {} long, not {}", game.length, length));
+    }
+
+    if problems.is_empty() {
+        println!("OK");
+        return;
+    }
+
+    for problem in problems.iter() {
+        println!("Rejected: {}", problem);
+    }
+
+    exit(1);
+}
+
+/// Play the inputs of a scenario, on the screen or not, and check that it ends the way it
+/// says. It exits with 1 if it doesn't, like `verify`
+fn run_scenario(path: &Path, watch: bool) {
+    let scenario = match Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(e) => error::report(Error::Scenario(path.to_path_buf(), e)),
+    };
+
+    let game = if watch {
+        let term = Term::stdout();
+        let game = scenario.watch(&term).unwrap_or_else(|e| error::fail(&term, Error::Terminal(e)));
+        println!();
+        game
+    } else {
+        scenario.run()
+    };
+
+    println!("Scenario: {} ({})", scenario.name, game.ruleset);
+    println!(
+        "Ticks: {}  Score: {}  Length: {}  {}",
+        game.ticks,
+        game.score,
+        game.length,
+        if game.over { "dead" } else { "alive" },
+    );
+
+    let problems = scenario.check(&game);
+
+    if problems.is_empty() {
+        println!("OK");
+        return;
+    }
+
+    for problem in problems.iter() {
+        println!("Failed: {}", problem);
+    }
+
+    exit(1);
+}
+
+/// Play a game again from its input trace
+fn watch_trace(path: &Path) {
+    let trace = match Trace::load(path) {
+        Ok(trace) => trace,
+        Err(e) => error::report(Error::Trace(path.to_path_buf(), e)),
+    };
+
+    player::run(trace.replay(), Subtitles::default());
+}
+
+/// Play a game with the profile in use, until it's over or left, counting it in the session.
+/// Returns how it ended, unless it never got a tick or the game thread went down
+fn play_game(mut options: PlayOptions, session: &mut Session) -> Option<Outcome> {
+    // The keys of the profile, and how it likes its heavy snakes
+    let config = Config::load().unwrap_or_else(|e| error::screen(&Term::stdout(), Error::Config(e)));
+
+    let ruleset = &mut options.challenge.ruleset;
+    if ruleset.has(Mutator::Heavy) && !options.shared {
+        ruleset.weight = config.weight;
+    }
+
+    let trace = match options.trace.as_ref() {
+        Some(path) => {
+            let recorder = Recorder::create(path, &options.challenge);
+            Some(recorder.unwrap_or_else(|e| error::report(Error::Save("input trace", e))))
+        },
+        None => None,
+    };
 
     // Terminal to use for the user's inpu
     let term = Term::stdout();
-    term.set_title("Snek!");
+
+    if let Err(e) = title::push(&term, "Snek!") {
+        error::report(Error::Terminal(e));
+    }
+
+    let (events, events_game) = mpsc::channel();
+    let rules_key = config.key_of(Action::Rules).unwrap_or_default();
+
+    let events_size = events.clone();
+    thread::spawn(move || render::watch_size(|| events_size.send(InputEvent::Resize).is_ok()));
+
+    // The outcome of an earlier game mustn't pass for this one's
+    session.last = None;
+
+    let played = thread::scope(|scope| {
+        // Spawn a thread where the game state will be updated and rendered
+        let game = scope.spawn(|| {
+            if let Err(e) = run_game(options, &rules_key, trace, events_game, session) {
+                error::hand_over(e);
+            }
+        });
+
+        read_input(&term, &config, &events);
+
+        // The game thread is done once the game is over, or right after it gets the quit,
+        // when the trace is written
+        game.join()
+    });
+
+    if let Err(e) = title::pop(&term) {
+        error::report(Error::Terminal(e));
+    }
+
+    played.ok().and(session.last)
+}
+
+/// The game thread: update the game and draw it until the game over, taking the events from
+/// the input thread as they come in between the ticks
+fn run_game(
+    options: PlayOptions,
+    rules_key: &str,
+    mut trace: Option<Recorder>,
+    events: Receiver<InputEvent>,
+    session: &mut Session,
+) -> Result<()> {
+    let challenge = options.challenge;
+
+    // We want a buffered stdout to print the resulting game state at once
+    let term = Term::buffered_stdout();
+    let mut frame = render::Frame::default();
+    let mut stdout = io::stdout();
+
+    if options.trail {
+        frame.show_trail();
+    }
+
+    if let Some(protocol) = options.graphics {
+        frame.use_graphics(protocol);
+    }
+
+    let mut game = Game::new(&challenge);
+    let mut title = title::Title::default();
+    let mut meter = perf::Meter::default();
+    let mut stamina = Stamina::default();
+    let mut hold = Hold::default();
+    let mut cues = options.cues.then(Cues::default);
+    let mut camera = options.photo_finish.then(Camera::default);
+
+    if let Some(camera) = camera.as_mut() {
+        camera.shoot(&game);
+    }
+
+    let mut dir_current = game.dir;
+    let mut dir_next = game.dir;
+    let mut show_perf = false;
+    let mut paused = false;
+    // The rules are on the screen, the game waits for them to be read
+    let mut rules = false;
+    // The game waits while the console is open too
+    let mut console = Console::default();
+
+    // Every tick is recorded, so the run can be watched again after the game over
+    let mut replay = Replay::new(challenge.clone());
+    let started = Instant::now();
+
+    // Anyone can watch from another terminal. If another game is already being watched, this
+    // one just can't be
+    #[cfg(unix)]
+    let mut broadcast = spectate::Broadcast::start(&spectate::socket_path()).ok();
+
+    let mut overlay = match options.overlay {
+        Some(dir) => Some(Overlay::new(&dir).map_err(|e| Error::Save("stream overlay", e))?),
+        None => None,
+    };
+
+    // If even the half resolution field doesn't fit (with the stamina bar and the perf overlay
+    // below it), the game waits for the terminal to be made bigger, a snake that runs off the
+    // screen can't be played
+    let mut scale = Scale::pick_up_to(&term, &game, 2, options.scale);
+    let mut next_tick = Instant::now() + boost::TICK;
 
     loop {
-        // Read a key from the terminal. The thread will be blocked until the user hits anything
-        let key = term.read_key().unwrap();
-
-        match key {
-            ArrowLeft | ArrowRight | ArrowUp | ArrowDown => {
-                let mut ctrl = control.lock().unwrap();
-
-                let dir_next = match key {
-                    ArrowLeft => Hor(Neg),
-                    ArrowRight => Hor(Pos),
-                    ArrowUp => Ver(Neg),
-                    ArrowDown => Ver(Pos),
-                    _ => unreachable!("It can't be any other key!"),
+        let mut bell = false;
+
+        // Nothing happens until the next event while the game can't go on
+        let event = if paused || rules || console.open || scale.is_err() {
+            events.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            events.recv_timeout(next_tick.saturating_duration_since(Instant::now()))
+        };
+
+        match event {
+            Ok(InputEvent::Key(key, action)) => {
+                if let Some(trace) = trace.as_mut() {
+                    trace.key(&key, action).map_err(|e| Error::Save("input trace", e))?;
+                }
+
+                // Holding the way the snake goes or the boost key makes it go faster
+                if let Some(action) = action.filter(|action| boost::speeds_up(*action, game.dir)) {
+                    hold.press(action);
+                }
+
+                continue;
+            },
+            // Only change the direction if the current direction and the new selected
+            // direction are not of the same discriminant.
+            // E.g. only Ver vs Hor or vice versa.
+            // If the snake moves horizontally, we only can change its direction to vertical
+            // and the other way around.
+            Ok(InputEvent::Turn(dir)) => {
+                if dir_current.can_turn_to(dir) {
+                    dir_next = dir;
+                }
+
+                continue;
+            },
+            Ok(InputEvent::Perf) => show_perf = !show_perf,
+            Ok(InputEvent::Pause) => {
+                paused = !paused;
+                next_tick = Instant::now() + boost::TICK;
+            },
+            Ok(InputEvent::Rules) => {
+                rules = !rules;
+                next_tick = Instant::now() + boost::TICK;
+            },
+            Ok(InputEvent::Console) => {
+                console.toggle();
+                next_tick = Instant::now() + boost::TICK;
+            },
+            Ok(InputEvent::Typed(key)) => console.key(&key, &mut game),
+            Ok(InputEvent::Resize) => {
+                let resized = Scale::pick_up_to(&term, &game, 2, options.scale);
+
+                // The field drawn another way comes in over the old one
+                if resized != scale {
+                    frame.transition(Transition::Wipe);
+                }
+
+                scale = resized;
+            },
+            Ok(InputEvent::Quit) | Err(RecvTimeoutError::Disconnected) => {
+                if let Some(trace) = trace.as_mut() {
+                    trace.flush().map_err(|e| Error::Save("input trace", e))?;
+                }
+
+                session.finish(&game);
+                return Ok(());
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                dir_current = dir_next;
+
+                if let Some(trace) = trace.as_mut() {
+                    trace.tick().map_err(|e| Error::Save("input trace", e))?;
+                }
+
+                // If the engine ever panics, keep everything needed to find out why
+                let events = match panic::catch_unwind(AssertUnwindSafe(|| game.tick(dir_current))) {
+                    Ok(events) => events,
+                    Err(_) => return Err(Error::Crash(crash::dump(&replay, &game, dir_current))),
                 };
+                meter.tick();
+                replay.record(&game, &events);
+                session.tick(&game, &events);
+
+                if let Some(camera) = camera.as_mut() {
+                    camera.shoot(&game);
+                }
+
+                if let Some(cues) = cues.as_mut() {
+                    cues.update(&game);
+                    bell = cues.ring();
+                }
+
+                #[cfg(unix)]
+                if let Some(broadcast) = broadcast.as_mut() {
+                    broadcast.send(&game, dir_current);
+                }
 
-                // Only change the direction if the current direction and the new selected
-                // direction are not of the same discriminant.
-                // E.g. only Ver vs Hor or vice versa.
-                // If the snake moves horizontally, we only can change its direction to vertical
-                // and the other way around.
-                if discriminant(&ctrl.dir_current) != discriminant(&dir_next) {
-                    ctrl.dir_next = dir_next;
+                if let Some(overlay) = overlay.as_mut() {
+                    overlay
+                        .update(game.score, game.length, started.elapsed())
+                        .map_err(|e| Error::Save("stream overlay", e))?;
                 }
+
+                // Also wins back stamina when not boosting. The time to the next tick is the
+                // same whatever drawing takes
+                let tick = stamina.tick(hold.held());
+                let tick = match game.ruleset.has(Mutator::Heavy) {
+                    true => game.ruleset.weight.tick(tick, game.length),
+                    false => tick,
+                };
+
+                next_tick = Instant::now() + console.speed.unwrap_or(tick);
             },
-            Escape => {
-                exit(0);
+        }
+
+        // The snake died on this tick, or it was killed from the console
+        if game.over {
+            if let Some(trace) = trace.as_mut() {
+                trace.flush().map_err(|e| Error::Save("input trace", e))?;
+            }
+
+            session.finish(&game);
+
+            // Once this thread is gone, the next key ends the input thread too
+            let photo = camera.as_ref().map(|camera| (camera, options.photo_pictures));
+            return game_over(&term, &game, &challenge, &replay, photo, console.used, started.elapsed());
+        }
+
+        let render_started = Instant::now();
+
+        frame.begin();
+
+        if bell {
+            write!(frame, "\x07").unwrap();
+        }
+
+        match scale {
+            // The rules are a few short lines, whatever size the field is
+            _ if rules => rules::draw(&mut frame, &game.ruleset, rules_key),
+            Ok(scale) => {
+                frame.field(&game, scale);
+
+                if console.open {
+                    console.draw(&mut frame);
+                } else if paused {
+                    writeln!(frame, "paused").unwrap();
+                } else {
+                    stamina.draw(&mut frame);
+                }
+
+                if show_perf {
+                    meter.draw(&mut frame);
+                }
+
+                // Keep the live score in the terminal title, so it can be seen from other tabs too
+                title.update(&mut frame, game.score, game.length);
             },
-            _ => {},
+            Err(size) => frame.too_small(size),
+        }
+
+        // Send the whole frame to the terminal at once
+        frame.present(&mut stdout).map_err(Error::Terminal)?;
+        meter.rendered(render_started.elapsed());
+    }
+}
+
+/// The snake hit itself... It is a game over. Keep the score, the challenge, the replay and the
+/// photo finish (with pictures or not), and show how the run compares to the best ones. A game
+/// tuned from the console only keeps its photo finish, the rest wouldn't play back
+fn game_over(
+    term: &Term,
+    game: &Game,
+    challenge: &Challenge,
+    replay: &Replay,
+    photo: Option<(&Camera, bool)>,
+    tuned: bool,
+    duration: Duration,
+) -> Result<()> {
+    let ruleset = &game.ruleset;
+
+    let entry = scores::Entry::new(game.score, game.length, ruleset);
+    let ranked = ruleset.ranked() && !tuned;
+
+    if ranked {
+        scores::save(&entry).map_err(Error::Scores)?;
+    }
+
+    let mut lines = vec![
+        format!("Score: {}  Length: {}  Time: {}", game.score, game.length, challenge::format_duration(duration)),
+        challenge.stamp(),
+    ];
+
+    if !tuned {
+        let saved_to = challenge
+            .save(game.score, game.length, duration)
+            .map_err(|e| Error::Save("challenge", e))?;
+        let replay_saved_to = replay
+            .save_new(entry.time, game.score)
+            .map_err(|e| Error::Save("replay", e))?;
+
+        lines.push(format!("Challenge saved to {}", saved_to.display()));
+        lines.push(format!("Replay saved to {}", replay_saved_to.display()));
+    }
+
+    if let Some((camera, pictures)) = photo {
+        let photo_saved_to = camera
+            .save(entry.time, pictures)
+            .map_err(|e| Error::Save("photo finish", e))?;
+        lines.push(format!("Photo finish saved to {}", photo_saved_to.display()));
+    }
+
+    lines.push(String::new());
+
+    if !ranked {
+        lines.push(if tuned {
+            "Tuned from the console: no high score, challenge or replay kept".to_string()
+        } else if ruleset.assisted() {
+            "No high scores with a hint or an assist".to_string()
+        } else {
+            format!("No high scores in {} mode", ruleset.mode.name())
+        });
+        return draw_game_over(term, game, &lines);
+    }
+
+    // Only show the scores made with the same ruleset. Comparing them with the scores from a
+    // different board size wouldn't be fair
+    lines.push(format!("Best scores ({}):", ruleset));
+
+    let entries = scores::load().map_err(Error::Scores)?;
+    for (place, best) in scores::top(&entries, ruleset, 5).iter().enumerate() {
+        let marker = if best.time == entry.time && best.score == entry.score {
+            " <- you!"
+        } else {
+            ""
+        };
+
+        lines.push(format!("  {}. {:>5} pts  len {}{}", place + 1, best.score, best.length, marker));
+    }
+
+    draw_game_over(term, game, &lines)
+}
+
+/// Write "GAME OVER!" over the field and the lines below it
+fn draw_game_over(term: &Term, game: &Game, lines: &[String]) -> Result<()> {
+    let draw = || -> io::Result<()> {
+        term.move_cursor_to(10, game.rows() / 2)?;
+        term.write_str("GAME OVER!")?;
+
+        term.move_cursor_to(0, game.rows() + 1)?;
+        for line in lines.iter() {
+            term.write_line(line)?;
+        }
+
+        term.write_line("")?;
+        term.write_line("Press any key to leave")?;
+
+        term.flush()
+    };
+
+    draw().map_err(Error::Terminal)
+}
+
+/// The input thread: send the keys of the profile to the game thread until quit is hit, or
+/// until the game thread is gone after the game over
+fn read_input(term: &Term, config: &Config, events: &Sender<InputEvent>) {
+    // The console is open, the keys are typed into it
+    let mut typing = false;
+
+    loop {
+        // Read a key from the terminal. The thread will be blocked until the user hits anything
+        let key = error::read_key(term);
+
+        let action = config.action(&key);
+
+        // Only escape and the console key itself close the console, everything else is text
+        if typing {
+            let event = match (&key, action) {
+                (Key::Escape, _) | (_, Some(Action::Console)) => {
+                    typing = false;
+                    InputEvent::Console
+                },
+                _ => InputEvent::Typed(key),
+            };
+
+            if events.send(event).is_err() {
+                return;
+            }
+
+            continue;
+        }
+
+        let event = match action {
+            Some(Action::Left) => Some(InputEvent::Turn(Hor(Neg))),
+            Some(Action::Right) => Some(InputEvent::Turn(Hor(Pos))),
+            Some(Action::Up) => Some(InputEvent::Turn(Ver(Neg))),
+            Some(Action::Down) => Some(InputEvent::Turn(Ver(Pos))),
+            Some(Action::Perf) => Some(InputEvent::Perf),
+            Some(Action::Pause) => Some(InputEvent::Pause),
+            Some(Action::Rules) => Some(InputEvent::Rules),
+            Some(Action::Console) => {
+                typing = true;
+                Some(InputEvent::Console)
+            },
+            Some(Action::Quit) => Some(InputEvent::Quit),
+            Some(Action::Boost) | None => None,
+        };
+
+        let quit = matches!(event, Some(InputEvent::Quit));
+
+        // Whatever key it is once the game is over, it's not for the game anymore
+        if events.send(InputEvent::Key(key, action)).is_err() {
+            return;
+        }
+
+        if let Some(event) = event {
+            if events.send(event).is_err() || quit {
+                return;
+            }
         }
     }
 }
@@ -0,0 +1,51 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::challenge::format_duration;
+
+/// The files written, one value in each
+const FILES: [&str; 3] = ["score.txt", "length.txt", "time.txt"];
+
+/// Live stats of the game written to text files, for streaming software like OBS to show on
+/// top of the video. Each file holds a single value and nothing else, so it can be used as
+/// the source of a text element as it is.
+pub struct Overlay {
+    dir: PathBuf,
+    /// What is in each of the files, so they're only written when something changed
+    written: [String; 3],
+}
+
+impl Overlay {
+    pub fn new(dir: &Path) -> io::Result<Overlay> {
+        fs::create_dir_all(dir)?;
+
+        Ok(Overlay {
+            dir: dir.to_path_buf(),
+            written: Default::default(),
+        })
+    }
+
+    pub fn update(&mut self, score: u32, length: usize, time: Duration) -> io::Result<()> {
+        let values = [score.to_string(), length.to_string(), format_duration(time)];
+
+        for ((name, value), written) in FILES.iter().zip(values).zip(self.written.iter_mut()) {
+            if *written == value {
+                continue;
+            }
+
+            // Write the whole file next to the old one and swap them, so the streaming software
+            // can't read a half written file
+            let path = self.dir.join(name);
+            let tmp = path.with_extension("txt.tmp");
+
+            fs::write(&tmp, &value)?;
+            fs::rename(&tmp, &path)?;
+
+            *written = value;
+        }
+
+        Ok(())
+    }
+}
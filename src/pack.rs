@@ -0,0 +1,258 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::level::Level;
+use crate::scores::base_dir;
+
+const PACKS_DIR: &str = "packs";
+const MANIFEST_FILE: &str = "pack.txt";
+
+/// Nothing in a pack is bigger than this. A level of the biggest size is about 1 MB, this is
+/// so that a zip bomb can't make the game eat all the memory
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// A set of levels played one after the other, as a directory or a zip file with a
+/// `pack.txt` manifest in it:
+///
+/// ```text
+/// snek-pack 1
+/// name Winter
+/// author Somebody
+/// level 01-ice.snklvl
+/// level 02-snow.snklvl 14
+/// ```
+///
+/// The levels are played in the order they're listed in. The number after a level is its
+/// par, the fewest moves it can be cleared in, for levels that don't say it themselves or get
+/// it wrong.
+pub struct Pack {
+    pub name: String,
+    pub author: Option<String>,
+    pub levels: Vec<Level>,
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Where installed packs are. They're the same for all the profiles
+pub fn dir() -> PathBuf {
+    base_dir().join(PACKS_DIR)
+}
+
+/// The installed packs, sorted by name: the directories and the zip files in the packs
+/// directory
+pub fn installed() -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.is_dir() || is_zip(&path) {
+            paths.push(path);
+        }
+    }
+
+    paths.sort();
+    Ok(paths)
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// The pack an argument stands for: a path to a pack, or the name of an installed one
+pub fn find(arg: &str) -> Option<PathBuf> {
+    let path = Path::new(arg);
+    if path.is_dir() || is_zip(path) {
+        return Some(path.to_path_buf());
+    }
+
+    [dir().join(arg), dir().join(format!("{}.zip", arg))]
+        .into_iter()
+        .find(|path| path.exists())
+}
+
+fn read_limited(reader: impl Read, name: &str) -> io::Result<String> {
+    let mut text = String::new();
+    reader.take(MAX_FILE_SIZE + 1).read_to_string(&mut text)?;
+
+    if text.len() as u64 > MAX_FILE_SIZE {
+        return Err(invalid(format!("{} is too big", name)));
+    }
+
+    Ok(text)
+}
+
+impl Pack {
+    /// Load a pack from a directory or a zip file
+    pub fn load(path: &Path) -> io::Result<Pack> {
+        if path.is_dir() {
+            return Pack::parse(|name| read_limited(File::open(path.join(name))?, name));
+        }
+
+        let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(|e| invalid(e.to_string()))?;
+
+        Pack::parse(|name| {
+            let file = archive.by_name(name).map_err(|e| invalid(format!("{}: {}", name, e)))?;
+            read_limited(file, name)
+        })
+    }
+
+    /// Read a pack from its manifest, with `read` giving the content of any file in the pack
+    pub fn parse(mut read: impl FnMut(&str) -> io::Result<String>) -> io::Result<Pack> {
+        let manifest = read(MANIFEST_FILE)?;
+        let mut lines = manifest.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        if lines.next().map(|(_, line)| line) != Some("snek-pack 1") {
+            return Err(invalid(format!("{} line 1: not a pack manifest", MANIFEST_FILE)));
+        }
+
+        let mut name = None;
+        let mut author = None;
+        let mut levels = Vec::new();
+
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let bad = |msg: &str| invalid(format!("{} line {}: {}", MANIFEST_FILE, n, msg));
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+
+            match key {
+                "name" if !value.is_empty() => name = Some(value.to_string()),
+                "author" if !value.is_empty() => author = Some(value.to_string()),
+                "level" => {
+                    let mut parts = value.split_whitespace();
+                    let file = parts.next().ok_or_else(|| bad("expected the file of the level"))?;
+
+                    // Only files right in the pack, whatever the manifest says
+                    if file.contains(['/', '\\']) || file.starts_with('.') {
+                        return Err(bad("levels have to be files in the pack itself"));
+                    }
+
+                    let mut level = Level::parse(&read(file)?).map_err(|e| invalid(format!("{}: {}", file, e)))?;
+
+                    if let Some(par) = parts.next() {
+                        let par = par.parse().map_err(|_| bad("expected a number of moves"))?;
+
+                        if level.limit.is_some_and(|limit| limit < par) {
+                            return Err(bad("the par is higher than the limit of the level"));
+                        }
+
+                        level.moves = Some(par);
+                    }
+
+                    levels.push(level);
+                },
+                _ => return Err(bad("expected name, author or level")),
+            }
+        }
+
+        let name = name.ok_or_else(|| invalid(format!("{}: the pack has no name", MANIFEST_FILE)))?;
+
+        if levels.is_empty() {
+            return Err(invalid(format!("{}: the pack has no levels", MANIFEST_FILE)));
+        }
+
+        Ok(Pack { name, author, levels })
+    }
+
+    /// What the records of a level of the pack are kept under, so that levels with the same
+    /// name in different packs don't share them
+    pub fn record_name(&self, level: &Level) -> String {
+        format!("{}/{}", self.name, level.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const MANIFEST: &str = "snek-pack 1\nname Winter\nauthor Somebody\nlevel 02-snow.snklvl 14\nlevel 01-ice.snklvl\n";
+
+    fn level(name: &str) -> String {
+        format!("snek-level 1\nname {}\nmap\n#####\n#o>$#\n#####", name)
+    }
+
+    /// A directory of its own for a test, empty
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("snake-video-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_pack(dir: &Path, manifest: Option<&str>) {
+        if let Some(manifest) = manifest {
+            fs::write(dir.join(MANIFEST_FILE), manifest).unwrap();
+        }
+        fs::write(dir.join("01-ice.snklvl"), level("Ice")).unwrap();
+        fs::write(dir.join("02-snow.snklvl"), level("Snow")).unwrap();
+    }
+
+    #[test]
+    fn a_pack_has_its_levels_in_the_order_of_the_manifest() {
+        let dir = scratch("pack-dir");
+        write_pack(&dir, Some(MANIFEST));
+
+        let pack = Pack::load(&dir).unwrap();
+        assert_eq!(pack.name, "Winter");
+        assert_eq!(pack.author.as_deref(), Some("Somebody"));
+
+        let levels: Vec<_> = pack.levels.iter().map(|level| (level.name.as_str(), level.moves)).collect();
+        assert_eq!(levels, [("Snow", Some(14)), ("Ice", None)]);
+        assert_eq!(pack.record_name(&pack.levels[0]), "Winter/Snow");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_pack_without_a_manifest_is_refused() {
+        let dir = scratch("pack-bare");
+        write_pack(&dir, None);
+
+        assert_eq!(Pack::load(&dir).err().map(|e| e.kind()), Some(io::ErrorKind::NotFound));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_level_missing_from_the_pack_is_refused() {
+        let read = |name: &str| match name {
+            MANIFEST_FILE => Ok("snek-pack 1\nname Winter\nlevel 03-slush.snklvl\n".to_string()),
+            _ => Err(io::Error::new(io::ErrorKind::NotFound, format!("no {}", name))),
+        };
+
+        assert_eq!(Pack::parse(read).err().map(|e| e.to_string()), Some("no 03-slush.snklvl".to_string()));
+    }
+
+    #[test]
+    fn a_zipped_pack_loads_like_a_directory() {
+        let dir = scratch("pack-zip");
+        let path = dir.join("winter.zip");
+
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        let files = [(MANIFEST_FILE, MANIFEST.to_string()), ("01-ice.snklvl", level("Ice")), ("02-snow.snklvl", level("Snow"))];
+
+        for (name, text) in files {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(text.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let pack = Pack::load(&path).unwrap();
+        assert_eq!(pack.name, "Winter");
+        assert_eq!(pack.levels.len(), 2);
+        assert_eq!(pack.levels[0].moves, Some(14));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
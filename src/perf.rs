@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::render::Frame;
+
+/// How many of the last ticks the rates are averaged over
+const WINDOW: usize = 20;
+
+/// Measures how fast the game really goes: how many ticks per second it manages and how long
+/// drawing a frame takes
+#[derive(Default)]
+pub struct Meter {
+    ticks: VecDeque<Instant>,
+    render_times: VecDeque<Duration>,
+}
+
+fn push<T>(queue: &mut VecDeque<T>, value: T) {
+    // Drop the oldest value first so the queue never has to grow past the window
+    if queue.len() == WINDOW {
+        queue.pop_front();
+    }
+
+    queue.push_back(value);
+}
+
+impl Meter {
+    /// Call once per tick
+    pub fn tick(&mut self) {
+        push(&mut self.ticks, Instant::now());
+    }
+
+    /// Call once a frame was drawn with how long it took
+    pub fn rendered(&mut self, took: Duration) {
+        push(&mut self.render_times, took);
+    }
+
+    /// Ticks per second over the last few ticks
+    pub fn tps(&self) -> f64 {
+        match (self.ticks.front(), self.ticks.back()) {
+            (Some(first), Some(last)) if self.ticks.len() > 1 => {
+                let secs = last.duration_since(*first).as_secs_f64();
+                (self.ticks.len() - 1) as f64 / secs.max(f64::EPSILON)
+            },
+            _ => 0.0,
+        }
+    }
+
+    /// The average time it took to draw the last few frames, in milliseconds
+    pub fn render_ms(&self) -> f64 {
+        if self.render_times.is_empty() {
+            return 0.0;
+        }
+
+        let total: Duration = self.render_times.iter().sum();
+        total.as_secs_f64() * 1000.0 / self.render_times.len() as f64
+    }
+
+    /// Add the overlay line to the frame
+    pub fn draw(&self, frame: &mut Frame) {
+        writeln!(frame, "TPS {:.1}  render {:.2} ms", self.tps(), self.render_ms()).unwrap();
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+use console::{Key, Term};
+
+use crate::board::{Cell, Entity, Tile};
+use crate::game::{Direction::*, Game, Polarity::*};
+use crate::pixels::{Canvas, PALETTE};
+use crate::png;
+use crate::render::Frame;
+use crate::scores::data_dir;
+
+const PHOTOS_DIR: &str = "photo-finish";
+
+/// How many ticks the photo finish goes back, the death included
+const FRAMES: usize = 3;
+
+/// Where the snake died, on the last frame of a photo finish. Running off the edge has no cell
+/// to mark, the head points at it
+const FATAL: char = 'X';
+const FATAL_COLOR: &str = "\x1b[91m";
+const DEFAULT_COLOR: &str = "\x1b[39m";
+
+/// Keeps the last few ticks of a game, for the photo finish if it ends
+#[derive(Default)]
+pub struct Camera {
+    frames: VecDeque<Game>,
+}
+
+/// What the snake ran into
+fn cause(game: &Game) -> (&'static str, Option<Cell>) {
+    match game.next_cell(game.dir) {
+        None => ("the edge", None),
+        Some(cell) => match game.field.tile(cell) {
+            Tile::Wall => ("a wall", Some(cell)),
+            Tile::Snake(_) => ("its own body", Some(cell)),
+            Tile::Empty => ("nothing, it was killed from the console,", None),
+        },
+    }
+}
+
+/// The field as text, the way a level map draws it, with the cell given marked
+fn map(game: &Game, fatal: Option<Cell>) -> Vec<String> {
+    (0..game.rows())
+        .map(|row| {
+            (0..game.cols())
+                .map(|col| {
+                    let cell = Cell { row, col };
+
+                    if Some(cell) == fatal {
+                        return FATAL;
+                    }
+
+                    match (game.field.tile(cell), game.field.entity(cell)) {
+                        _ if cell == game.head => match game.dir {
+                            Ver(Neg) => '^',
+                            Ver(Pos) => 'v',
+                            Hor(Neg) => '<',
+                            Hor(Pos) => '>',
+                        },
+                        (Tile::Snake(_), _) => 'o',
+                        (Tile::Wall, _) => '#',
+                        (Tile::Empty, Some(Entity::Food)) => '$',
+                        (Tile::Empty, Some(Entity::Pellet(n))) => char::from_digit(n as u32 % 10, 10).unwrap(),
+                        (Tile::Empty, None) => '.',
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+impl Camera {
+    /// Keep the game as it is on this tick
+    pub fn shoot(&mut self, game: &Game) {
+        if self.frames.len() == FRAMES {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(game.clone());
+    }
+
+    /// The last frames as text: what the game was and what the snake ran into, then the map
+    /// of every tick
+    pub fn text(&self) -> String {
+        let last = self.frames.back().expect("the camera has nothing to show");
+        let (what, fatal) = cause(last);
+
+        let mut text = format!(
+            "Photo finish: {}, score {}, length {}\nRan into {} on tick {}\n",
+            last.ruleset, last.score, last.length, what, last.ticks,
+        );
+
+        for (i, game) in self.frames.iter().enumerate() {
+            let fatal = if i + 1 == self.frames.len() { fatal } else { None };
+
+            writeln!(text, "\ntick {}", game.ticks).unwrap();
+            for line in map(game, fatal) {
+                writeln!(text, "{}", line).unwrap();
+            }
+        }
+
+        text
+    }
+
+    /// Save the last frames as a text file in the photo finish directory, with a PNG of each
+    /// next to it if asked. The names are made of the given timestamp and the score, like
+    /// the replays. Returns the path of the text file
+    pub fn save(&self, time: u64, pictures: bool) -> io::Result<PathBuf> {
+        let last = self.frames.back().expect("the camera has nothing to save");
+
+        let dir = data_dir().join(PHOTOS_DIR);
+        fs::create_dir_all(&dir)?;
+
+        let stem = format!("{}-{}", time, last.score);
+
+        if pictures {
+            for (i, game) in self.frames.iter().enumerate() {
+                let mut canvas = Canvas::new(game, true);
+                canvas.draw(game);
+
+                let file = BufWriter::new(File::create(dir.join(format!("{}-{}.png", stem, i + 1)))?);
+                png::write(file, canvas.width, canvas.height, &canvas.pixels, &PALETTE)?;
+            }
+        }
+
+        let path = dir.join(format!("{}.txt", stem));
+        fs::write(&path, self.text())?;
+
+        Ok(path)
+    }
+}
+
+/// A photo finish as it was saved
+pub struct Photo {
+    pub path: PathBuf,
+    /// What the game was and what killed the snake
+    pub header: Vec<String>,
+    /// The tick and the map of every frame
+    pub frames: Vec<(String, Vec<String>)>,
+}
+
+impl Photo {
+    pub fn parse(path: &Path, text: &str) -> Photo {
+        let mut sections = text.split("\n\n");
+
+        let header = sections.next().unwrap_or_default().lines().map(String::from).collect();
+        let frames = sections
+            .filter_map(|section| {
+                let mut lines = section.lines().map(String::from);
+                Some((lines.next()?, lines.collect()))
+            })
+            .collect();
+
+        Photo {
+            path: path.to_path_buf(),
+            header,
+            frames,
+        }
+    }
+}
+
+/// All the photo finishes of the profile in use, the newest first
+pub fn list() -> io::Result<Vec<Photo>> {
+    let entries = match fs::read_dir(data_dir().join(PHOTOS_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+
+        if path.extension().is_some_and(|ext| ext == "txt") {
+            paths.push(path);
+        }
+    }
+
+    // The names start with the time of the game over
+    paths.sort_by_key(|path| {
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+        stem.split('-').next().and_then(|time| time.parse::<u64>().ok()).unwrap_or(0)
+    });
+    paths.reverse();
+
+    paths.into_iter().map(|path| Ok(Photo::parse(&path, &fs::read_to_string(&path)?))).collect()
+}
+
+/// Go through the photo finishes: left and right from one game to another, up and down
+/// through the frames of one, a number for a frame, escape to leave. The death is shown first
+pub fn view(term: &Term, photos: &[Photo]) -> io::Result<()> {
+    let mut frame = Frame::default();
+    let mut stdout = io::stdout();
+
+    let mut shown = 0;
+    let mut tick = usize::MAX;
+
+    loop {
+        let photo = &photos[shown];
+        tick = tick.min(photo.frames.len().saturating_sub(1));
+
+        frame.begin();
+        writeln!(frame, "Photo finish {} of {}: {}", shown + 1, photos.len(), photo.path.display()).unwrap();
+        for line in photo.header.iter() {
+            writeln!(frame, "{}", line).unwrap();
+        }
+        writeln!(frame).unwrap();
+
+        if let Some((title, map)) = photo.frames.get(tick) {
+            writeln!(frame, "{} ({} of {})", title, tick + 1, photo.frames.len()).unwrap();
+
+            for line in map {
+                let line = line.replace(FATAL, &format!("{}{}{}", FATAL_COLOR, FATAL, DEFAULT_COLOR));
+                writeln!(frame, "{}", line).unwrap();
+            }
+        }
+
+        writeln!(frame).unwrap();
+        writeln!(frame, "left/right: another game, up/down: another tick, escape: leave").unwrap();
+        frame.present(&mut stdout)?;
+
+        match term.read_key()? {
+            // Another game starts from its death again
+            Key::ArrowLeft => (shown, tick) = (shown.saturating_sub(1), usize::MAX),
+            Key::ArrowRight => (shown, tick) = ((shown + 1).min(photos.len() - 1), usize::MAX),
+            Key::ArrowUp => tick = tick.saturating_sub(1),
+            Key::ArrowDown => tick += 1,
+            Key::Char(ch @ '1'..='9') => tick = ch as usize - '1' as usize,
+            Key::Escape | Key::Char('q') => return Ok(()),
+            _ => {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::{small, CORNERED};
+    use crate::ruleset::Mutator;
+
+    #[test]
+    fn a_photo_finish_marks_what_killed_the_snake() {
+        let mut game = small(Mutator::Hint, &CORNERED);
+        let mut camera = Camera::default();
+
+        camera.shoot(&game);
+        game.tick(Ver(Pos));
+        camera.shoot(&game);
+
+        let text = camera.text();
+        assert!(text.contains("Ran into its own body on tick 1"), "{}", text);
+
+        let (_, last) = text.rsplit_once("tick 1\n").unwrap();
+        assert_eq!(last.lines().nth(3), Some(".oX.."));
+        assert_eq!(text.matches(FATAL).count(), 1);
+    }
+
+    #[test]
+    fn a_saved_photo_reads_back_frame_by_frame() {
+        let mut game = small(Mutator::Hint, &CORNERED);
+        let mut camera = Camera::default();
+
+        camera.shoot(&game);
+        game.tick(Ver(Pos));
+        camera.shoot(&game);
+
+        let photo = Photo::parse(Path::new("1-0.txt"), &camera.text());
+        assert_eq!(photo.header.len(), 2);
+        assert_eq!(photo.frames.len(), 2);
+        assert_eq!(photo.frames[1].0, "tick 1");
+        assert_eq!(photo.frames[1].1.len(), 5);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::board::{Cell, Entity, Tile};
+use crate::game::Game;
+
+/// The size of a cell of the field, in pixels
+pub const CELL: usize = 8;
+/// The height of the strip with the score below the field
+const STRIP: usize = 9;
+
+const BACKGROUND: u8 = 0;
+const CHECKER: u8 = 1;
+const WALL: u8 = 2;
+const BODY: u8 = 3;
+const HEAD: u8 = 4;
+const FOOD: u8 = 5;
+const PELLET: u8 = 6;
+const TEXT: u8 = 7;
+
+/// The colors of the picture, as RGB
+pub const PALETTE: [[u8; 3]; 8] = [
+    [0x12, 0x14, 0x1c],
+    [0x17, 0x1a, 0x24],
+    [0x5c, 0x63, 0x70],
+    [0x3f, 0xa3, 0x4d],
+    [0x8b, 0xe0, 0x6a],
+    [0xe8, 0x4a, 0x3f],
+    [0xf2, 0xc1, 0x4e],
+    [0xd8, 0xde, 0xe9],
+];
+
+/// Food is round, more or less
+const FOOD_SHAPE: [u8; 6] = [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b011110];
+
+/// What the score strip is written with, 3x5 pixels a character
+fn glyph(ch: char) -> [u8; 5] {
+    match ch {
+        '0' | 'O' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' | 'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'C' => [0b111, 0b100, 0b100, 0b100, 0b111],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        _ => [0; 5],
+    }
+}
+
+/// A picture of the game, one color of `PALETTE` per pixel. It's drawn with no font at all, so
+/// it looks the same wherever it ends up: in a GIF, or on a terminal that can show pixels
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// A canvas the size of the field of the game, with room for the score strip below it
+    /// if `strip` is set
+    pub fn new(game: &Game, strip: bool) -> Canvas {
+        let (width, height) = Canvas::size(game, strip);
+
+        Canvas {
+            width,
+            height,
+            pixels: vec![BACKGROUND; width * height],
+        }
+    }
+
+    /// Whether the canvas is the one `new` makes for the game, so it can be drawn on again
+    pub fn fits(&self, game: &Game, strip: bool) -> bool {
+        (self.width, self.height) == Canvas::size(game, strip)
+    }
+
+    fn size(game: &Game, strip: bool) -> (usize, usize) {
+        (game.cols() * CELL, game.rows() * CELL + if strip { STRIP } else { 0 })
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u8) {
+        for row in y..(y + height).min(self.height) {
+            for col in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + col] = color;
+            }
+        }
+    }
+
+    fn text(&mut self, mut x: usize, y: usize, text: &str) {
+        for ch in text.chars() {
+            for (row, bits) in glyph(ch).iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) != 0 {
+                        self.fill(x + col, y + row, 1, 1, TEXT);
+                    }
+                }
+            }
+
+            x += 4;
+        }
+    }
+
+    /// Draw the field, and the score strip if the canvas has room for it
+    pub fn draw(&mut self, game: &Game) {
+        // A faint checkerboard, so it's easy to count cells
+        for row in 0..game.rows() {
+            for col in 0..game.cols() {
+                let color = match game.field.tile(Cell { row, col }) {
+                    Tile::Wall => WALL,
+                    _ if (row + col) % 2 == 0 => BACKGROUND,
+                    _ => CHECKER,
+                };
+
+                self.fill(col * CELL, row * CELL, CELL, CELL, color);
+            }
+        }
+
+        for (cell, entity) in game.field.entities() {
+            let color = match entity {
+                Entity::Food => FOOD,
+                Entity::Pellet(_) => PELLET,
+            };
+
+            for (i, bits) in FOOD_SHAPE.iter().enumerate() {
+                for j in 0..6 {
+                    if bits & (0b100000 >> j) != 0 {
+                        self.fill(cell.col * CELL + 1 + j, cell.row * CELL + 1 + i, 1, 1, color);
+                    }
+                }
+            }
+
+            if let Entity::Pellet(n) = entity {
+                let (x, y) = (cell.col * CELL + 2, cell.row * CELL + 1);
+
+                for (row, bits) in glyph(char::from(b'0' + n)).iter().enumerate() {
+                    for col in 0..3 {
+                        if bits & (0b100 >> col) != 0 {
+                            self.fill(x + col + 1, y + row + 1, 1, 1, BACKGROUND);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Every segment is a square with a gap around it, except towards the segments next to
+        // it on the snake, so the snake is in one piece but its turns can be told apart
+        let body = game.body();
+
+        for (i, cell) in body.iter().enumerate() {
+            let color = if *cell == game.head { HEAD } else { BODY };
+            let (x, y) = (cell.col * CELL, cell.row * CELL);
+
+            self.fill(x + 1, y + 1, CELL - 2, CELL - 2, color);
+
+            for other in [i.checked_sub(1).map(|j| body[j]), body.get(i + 1).copied()].into_iter().flatten() {
+                // Where the snake goes around the field, there's nothing to join
+                match (other.row as isize - cell.row as isize, other.col as isize - cell.col as isize) {
+                    (0, 1) => self.fill(x + CELL - 1, y + 1, 1, CELL - 2, color),
+                    (0, -1) => self.fill(x, y + 1, 1, CELL - 2, color),
+                    (1, 0) => self.fill(x + 1, y + CELL - 1, CELL - 2, 1, color),
+                    (-1, 0) => self.fill(x + 1, y, CELL - 2, 1, color),
+                    _ => {},
+                }
+            }
+        }
+
+        let strip = game.rows() * CELL;
+        if self.height > strip {
+            self.fill(0, strip, self.width, STRIP, BACKGROUND);
+            self.text(2, strip + 2, &format!("SCORE {}  LEN {}", game.score, game.length));
+        }
+    }
+}
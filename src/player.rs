@@ -0,0 +1,484 @@
+use std::fmt::Write;
+use std::io;
+use std::process::exit;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use console::Key::*;
+use console::Term;
+
+use crate::error::{self, Error};
+use crate::game::{Event, Game};
+use crate::perf;
+use crate::render::{self, Scale};
+use crate::replay::Replay;
+use crate::subtitles::Subtitles;
+use crate::title;
+
+/// A replay ready to be shown from any tick
+pub struct Timeline {
+    replay: Replay,
+    /// The keyframes of the replay, turned into games, sorted by tick. The first one is the
+    /// start of the game
+    keyframes: Vec<Game>,
+    /// The number of the last tick of the replay
+    pub total: u64,
+    /// The tick the game stops going the way it did when it was recorded, if it does
+    pub divergence: Option<u64>,
+}
+
+impl Timeline {
+    pub fn new(replay: Replay) -> Timeline {
+        let mut keyframes = vec![Game::new(&replay.challenge)];
+
+        for snapshot in replay.keyframes.iter() {
+            // A broken keyframe is not the end of the world, seeking just has to simulate a
+            // bit more
+            if let Some(game) = Game::from_snapshot(&replay.challenge, snapshot) {
+                keyframes.push(game);
+            }
+        }
+
+        keyframes.sort_by_key(|game| game.ticks);
+
+        let total = match replay.events.iter().find(|(_, event)| *event == Event::Died) {
+            Some((tick, _)) => *tick,
+            None => replay.inputs.len() as u64,
+        };
+
+        let divergence = replay.divergence();
+
+        Timeline {
+            replay,
+            keyframes,
+            total,
+            divergence,
+        }
+    }
+
+    pub fn events(&self) -> &[(u64, Event)] {
+        &self.replay.events
+    }
+
+    /// Move the game one tick forward, the same way it went in the recording. Returns `false`
+    /// if it can't go any further
+    pub fn step(&self, game: &mut Game) -> bool {
+        let ticks = game.ticks;
+
+        if let Some(dir) = self.replay.inputs.get(ticks as usize) {
+            game.tick(*dir);
+        }
+
+        game.ticks > ticks
+    }
+
+    /// The state of the game after the given number of ticks. Starts from the closest
+    /// keyframe, so only a few ticks have to be simulated
+    pub fn seek(&self, tick: u64) -> Game {
+        let tick = tick.min(self.total);
+        let keyframe = self.keyframes.partition_point(|game| game.ticks <= tick) - 1;
+
+        // A replay that doesn't match its challenge can end before its last tick
+        let mut game = self.keyframes[keyframe].clone();
+        while game.ticks < tick && self.step(&mut game) {}
+
+        game
+    }
+
+    /// The closest tick with an event before the given one
+    pub fn prev_event(&self, tick: u64) -> Option<u64> {
+        self.events().iter().rev().map(|(t, _)| *t).find(|t| *t < tick)
+    }
+
+    /// The closest tick with an event after the given one
+    pub fn next_event(&self, tick: u64) -> Option<u64> {
+        self.events().iter().map(|(t, _)| *t).find(|t| *t > tick)
+    }
+
+    /// The column of the timeline bar the tick is shown in
+    pub fn column(&self, tick: u64, width: usize) -> usize {
+        if self.total == 0 || tick == 0 {
+            return 0;
+        }
+
+        // The last tick always ends up in the last column
+        let tick = tick.min(self.total) as u128;
+        ((tick * width as u128 - 1) / self.total as u128) as usize
+    }
+
+    /// Draw the markers of the timeline bar of the given width.
+    ///
+    /// Every column covers a few ticks. If several things happened in the same column, the
+    /// most important one is shown
+    pub fn markers(&self, width: usize) -> String {
+        fn importance(ch: char) -> u8 {
+            match ch {
+                'X' => 3,
+                '!' => 2,
+                '*' => 1,
+                _ => 0,
+            }
+        }
+
+        let mut markers = vec!['-'; width];
+
+        for (t, event) in self.events().iter() {
+            let ch = match event {
+                Event::Ate | Event::WrongOrder => '*',
+                Event::NearMiss => '!',
+                Event::Died => 'X',
+            };
+
+            let col = self.column(*t, width);
+            if importance(ch) > importance(markers[col]) {
+                markers[col] = ch;
+            }
+        }
+
+        markers.into_iter().collect()
+    }
+}
+
+/// How often the replay moves on while it plays
+const FRAME: Duration = Duration::from_millis(100);
+
+/// Wait for the next frame while the replay moves, and for a key or a resize when it doesn't.
+/// A paused replay doesn't have to be drawn again and again, it keeps the CPU idle
+fn wait(woken: &Receiver<()>, moving: bool) {
+    if moving {
+        thread::sleep(FRAME);
+    } else {
+        let _ = woken.recv();
+    }
+
+    // Several keys at once only need one frame
+    while woken.try_recv().is_ok() {}
+}
+
+/// Where we are in the replay
+struct Cursor {
+    tick: u64,
+    playing: bool,
+    show_perf: bool,
+}
+
+/// Play a replay in the terminal.
+///
+/// Space pauses, left/right pause and step one tick back/forward, `[`/`]` jump to the
+/// previous/next event, home/end jump to the start/end, `f` shows the frame rate and escape
+/// quits. The captions are shown below the timeline, at their ticks
+pub fn run(replay: Replay, subtitles: Subtitles) {
+    let timeline = Arc::new(Timeline::new(replay));
+
+    let cursor = Arc::new(Mutex::new(Cursor {
+        tick: 0,
+        playing: true,
+        show_perf: false,
+    }));
+
+    let cursor_thread = Arc::clone(&cursor);
+
+    let (wake, woken) = mpsc::channel();
+    let wake_size = wake.clone();
+    thread::spawn(move || render::watch_size(|| wake_size.send(()).is_ok()));
+    let timeline_thread = Arc::clone(&timeline);
+
+    let term = Term::stdout();
+
+    if let Err(e) = title::push(&term, "Snek! (replay)") {
+        error::report(Error::Terminal(e));
+    }
+
+    // Same as in the game itself: one thread draws, the other one reads the keys
+    thread::spawn(move || {
+        let timeline = timeline_thread;
+        let mut game = timeline.seek(0);
+
+        let mut frame = render::Frame::default();
+        let mut stdout = io::stdout();
+        let mut meter = perf::Meter::default();
+
+        // The events don't change, so the bar only has to be drawn once for each width it can
+        // have: the width of the field at either scale
+        let markers_full = timeline.markers(Scale::Full.field_size(&game).0);
+        let markers_half = timeline.markers(Scale::Half.field_size(&game).0);
+
+        let term = Term::stdout();
+
+        loop {
+            // Same as in the game, the field is drawn at half resolution if it has to, with the
+            // timeline and the perf overlay below it. If it doesn't fit at all, the replay
+            // doesn't move on until the terminal is bigger
+            let scale = match Scale::pick(&term, &game, 7) {
+                Ok(scale) => scale,
+                Err(size) => {
+                    frame.too_small(size);
+
+                    if let Err(e) = frame.present(&mut stdout) {
+                        error::hand_over(Error::Terminal(e));
+                    }
+
+                    // Only a key or a resize can change that
+                    let _ = woken.recv();
+                    continue;
+                }
+            };
+
+            let width = scale.field_size(&game).0;
+            let markers = match scale {
+                Scale::Half => &markers_half,
+                // Replays are never drawn any bigger
+                Scale::Full | Scale::Large(_) => &markers_full,
+            };
+
+            let (tick, playing, show_perf) = {
+                let mut cursor = cursor_thread.lock().unwrap();
+
+                if cursor.playing && cursor.tick < timeline.total {
+                    cursor.tick += 1;
+                }
+
+                (cursor.tick, cursor.playing, cursor.show_perf)
+            };
+
+            meter.tick();
+            let render_started = Instant::now();
+
+            // Playing forward is just one more tick, anything else is a jump
+            if tick == game.ticks + 1 {
+                timeline.step(&mut game);
+            } else if tick != game.ticks {
+                game = timeline.seek(tick);
+            }
+
+            frame.begin();
+            frame.field(&game, scale);
+
+            // The timeline with a cursor pointing at the current tick below it
+            writeln!(frame).unwrap();
+            writeln!(frame, "{}", markers).unwrap();
+            writeln!(frame, "{:>1$}", "^", timeline.column(tick, width) + 1).unwrap();
+            writeln!(
+                frame,
+                "tick {}/{}  score {}  len {}{}",
+                tick,
+                timeline.total,
+                game.score,
+                game.length,
+                if playing { "" } else { "  [paused]" }
+            ).unwrap();
+            writeln!(frame, "* food  ! near miss  X death").unwrap();
+            writeln!(frame, "{}", subtitles.at(tick).unwrap_or_default()).unwrap();
+
+            // From there on what's shown isn't what happened
+            if let Some(divergence) = timeline.divergence {
+                writeln!(frame, "out of sync with the recording from tick {}", divergence).unwrap();
+            }
+
+            if show_perf {
+                meter.draw(&mut frame);
+            }
+
+            if let Err(e) = frame.present(&mut stdout) {
+                error::hand_over(Error::Terminal(e));
+            }
+            meter.rendered(render_started.elapsed());
+
+            wait(&woken, playing && tick < timeline.total);
+        }
+    });
+
+    loop {
+        let key = error::read_key(&term);
+        let mut cursor = cursor.lock().unwrap();
+
+        // Whatever the key does, the drawing thread has to know
+        let _ = wake.send(());
+
+        match key {
+            Char(' ') => cursor.playing = !cursor.playing,
+            Char('f') => cursor.show_perf = !cursor.show_perf,
+            // Stepping tick by tick only makes sense while paused
+            ArrowLeft => {
+                cursor.playing = false;
+                cursor.tick = cursor.tick.saturating_sub(1);
+            },
+            ArrowRight => {
+                cursor.playing = false;
+                cursor.tick = (cursor.tick + 1).min(timeline.total);
+            },
+            Home => cursor.tick = 0,
+            End => cursor.tick = timeline.total,
+            Char('[') => cursor.tick = timeline.prev_event(cursor.tick).unwrap_or(0),
+            Char(']') => cursor.tick = timeline.next_event(cursor.tick).unwrap_or(timeline.total),
+            Escape => {
+                let _ = title::pop(&term);
+                exit(0);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// The first tick the two games aren't in the same state anymore, if that ever happens before
+/// one of them ends
+fn split(left: &Timeline, right: &Timeline) -> Option<u64> {
+    let mut a = left.seek(0);
+    let mut b = right.seek(0);
+
+    loop {
+        let (moved_a, moved_b) = (left.step(&mut a), right.step(&mut b));
+
+        if !moved_a || !moved_b {
+            return None;
+        }
+
+        if a.state_hash() != b.state_hash() {
+            return Some(a.ticks);
+        }
+    }
+}
+
+/// Play two replays of the same challenge next to each other, tick for tick, e.g. a run and
+/// the run of whoever did best on the daily challenge. Where the snakes aren't in the same
+/// place is highlighted, and the timeline shows the tick they went different ways on.
+///
+/// The keys are the same as when watching a single replay, except for jumping between events
+pub fn compare(left: Replay, right: Replay) {
+    let left = Timeline::new(left);
+    let right = Timeline::new(right);
+    let total = left.total.max(right.total);
+    let split = split(&left, &right);
+
+    let cursor = Arc::new(Mutex::new(Cursor {
+        tick: 0,
+        playing: true,
+        show_perf: false,
+    }));
+
+    let cursor_thread = Arc::clone(&cursor);
+
+    let (wake, woken) = mpsc::channel();
+    let wake_size = wake.clone();
+    thread::spawn(move || render::watch_size(|| wake_size.send(()).is_ok()));
+
+    let term = Term::stdout();
+
+    if let Err(e) = title::push(&term, "Snek! (comparing replays)") {
+        error::report(Error::Terminal(e));
+    }
+
+    thread::spawn(move || {
+        let mut frame = render::Frame::default();
+        let mut stdout = io::stdout();
+
+        let term = Term::stdout();
+        let mut a = left.seek(0);
+        let mut b = right.seek(0);
+
+        // The timeline goes on to the end of the longer replay
+        let longer = if left.total >= right.total { &left } else { &right };
+
+        let width = render::Frame::pair_width(&a);
+        let mut bar = vec!['-'; width];
+        if let Some(split) = split {
+            bar[longer.column(split, width)] = '|';
+        }
+        let bar: String = bar.into_iter().collect();
+
+        loop {
+            // Two fields side by side don't fit in half as many columns at half scale, they
+            // only come at full scale. Same as for a single replay, nothing moves on until they fit
+            let (rows, cols) = term.size();
+            let needed = (width, a.rows() + 6);
+
+            if (cols as usize) < needed.0 || (rows as usize) < needed.1 {
+                frame.too_small(needed);
+
+                if let Err(e) = frame.present(&mut stdout) {
+                    error::hand_over(Error::Terminal(e));
+                }
+
+                let _ = woken.recv();
+                continue;
+            }
+
+            let (tick, playing) = {
+                let mut cursor = cursor_thread.lock().unwrap();
+
+                if cursor.playing && cursor.tick < total {
+                    cursor.tick += 1;
+                }
+
+                (cursor.tick, cursor.playing)
+            };
+
+            // Both at the same tick, or at their end for the one that ended first
+            if tick == a.ticks + 1 && tick == b.ticks + 1 {
+                left.step(&mut a);
+                right.step(&mut b);
+            } else if tick != a.ticks || tick != b.ticks {
+                a = left.seek(tick);
+                b = right.seek(tick);
+            }
+
+            frame.begin();
+            frame.pair(&a, &b);
+
+            writeln!(frame).unwrap();
+            writeln!(frame, "{}", bar).unwrap();
+            writeln!(frame, "{:>1$}", "^", longer.column(tick, width) + 1).unwrap();
+            writeln!(
+                frame,
+                "tick {}/{}  score {} vs {}  len {} vs {}{}",
+                tick,
+                total,
+                a.score,
+                b.score,
+                a.length,
+                b.length,
+                if playing { "" } else { "  [paused]" }
+            ).unwrap();
+
+            match split {
+                Some(split) => writeln!(frame, "| they went different ways at tick {}", split).unwrap(),
+                None => writeln!(frame, "they went the same way all along").unwrap(),
+            }
+
+            if let Err(e) = frame.present(&mut stdout) {
+                error::hand_over(Error::Terminal(e));
+            }
+
+            wait(&woken, playing && tick < total);
+        }
+    });
+
+    loop {
+        let key = error::read_key(&term);
+        let mut cursor = cursor.lock().unwrap();
+
+        // Whatever the key does, the drawing thread has to know
+        let _ = wake.send(());
+
+        match key {
+            Char(' ') => cursor.playing = !cursor.playing,
+            ArrowLeft => {
+                cursor.playing = false;
+                cursor.tick = cursor.tick.saturating_sub(1);
+            },
+            ArrowRight => {
+                cursor.playing = false;
+                cursor.tick = (cursor.tick + 1).min(total);
+            },
+            Home => cursor.tick = 0,
+            End => cursor.tick = total,
+            Escape => {
+                let _ = title::pop(&term);
+                exit(0);
+            },
+            _ => {},
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+fn chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+
+    // The CRC is of the kind and the data, not the length
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&crc.finalize().to_be_bytes())
+}
+
+/// Write a picture as a PNG with a palette, given as the colors of its pixels row by row
+pub fn write(mut out: impl Write, width: usize, height: usize, pixels: &[u8], palette: &[[u8; 3]]) -> io::Result<()> {
+    assert_eq!(pixels.len(), width * height);
+    assert!(!palette.is_empty() && palette.len() <= 256, "a PNG has 1 to 256 colors");
+
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+    // 8 bits per pixel, with a palette
+    let mut header = Vec::new();
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+    chunk(&mut out, b"IHDR", &header)?;
+
+    chunk(&mut out, b"PLTE", &palette.concat())?;
+
+    // Every row starts with the filter it's written with, none
+    let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in pixels.chunks(width) {
+        zlib.write_all(&[0])?;
+        zlib.write_all(row)?;
+    }
+    chunk(&mut out, b"IDAT", &zlib.finish()?)?;
+
+    chunk(&mut out, b"IEND", &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::ZlibDecoder;
+
+    use super::*;
+
+    /// The chunks of a PNG, once their CRCs are checked
+    fn chunks(mut png: &[u8]) -> Vec<([u8; 4], Vec<u8>)> {
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        png = &png[8..];
+
+        let mut chunks = Vec::new();
+        while !png.is_empty() {
+            let len = u32::from_be_bytes(png[..4].try_into().unwrap()) as usize;
+            let (checked, crc) = png[4..8 + len + 4].split_at(4 + len);
+
+            assert_eq!(crc32fast::hash(checked).to_be_bytes(), crc);
+            chunks.push((checked[..4].try_into().unwrap(), checked[4..].to_vec()));
+            png = &png[8 + len + 4..];
+        }
+
+        chunks
+    }
+
+    #[test]
+    fn the_pixels_come_back() {
+        let palette = [[0, 0, 0], [255, 0, 0], [0, 0, 255]];
+        let pixels: Vec<u8> = (0..12u8 * 7).map(|i| i % 3).collect();
+
+        let mut png = Vec::new();
+        write(&mut png, 12, 7, &pixels, &palette).unwrap();
+
+        let chunks = chunks(&png);
+        let kinds: Vec<&[u8; 4]> = chunks.iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, [b"IHDR", b"PLTE", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[0].1, [0, 0, 0, 12, 0, 0, 0, 7, 8, 3, 0, 0, 0]);
+        assert_eq!(chunks[1].1, palette.concat());
+
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&chunks[2].1[..]).read_to_end(&mut raw).unwrap();
+
+        let rows: Vec<&[u8]> = raw.chunks(13).collect();
+        assert_eq!(rows.len(), 7);
+        for (row, pixels) in rows.iter().zip(pixels.chunks(12)) {
+            assert_eq!(row[0], 0);
+            assert_eq!(&row[1..], pixels);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::game::START_LENGTH;
+use crate::scores::{self, base_dir, Entry};
+
+const PROFILES_DIR: &str = "profiles";
+
+/// The profile picked with `--profile`. It's chosen once at startup and every file of the game
+/// is read from and written to its directory from then on, so it's kept here rather than
+/// passed around to everything that touches a file. Only hot-seat changes it afterwards, for
+/// the player whose turn it is
+static CURRENT: RwLock<Option<String>> = RwLock::new(None);
+
+/// What the profiles screen shows about a profile
+pub struct Summary {
+    pub name: String,
+    pub games: usize,
+    pub best: u32,
+    /// The food eaten in all the games, whatever it was worth
+    pub food: u64,
+}
+
+/// A name is used as a directory name, so only letters, digits, `-` and `_` are allowed
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 32
+        && name.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_')
+}
+
+/// Use the given profile for the rest of the run. Returns `false` if the name is not valid or
+/// a profile was already picked
+pub fn select(name: &str) -> bool {
+    let mut current = CURRENT.write().unwrap();
+
+    if !valid_name(name) || current.is_some() {
+        return false;
+    }
+
+    *current = Some(name.to_string());
+    true
+}
+
+/// Hand the game to another profile, with hot-seat when the next player takes the terminal
+pub fn switch(name: Option<&str>) {
+    *CURRENT.write().unwrap() = name.map(String::from);
+}
+
+/// The profile in use, `None` being the default one
+pub fn current() -> Option<String> {
+    CURRENT.read().unwrap().clone()
+}
+
+/// The directory of a profile. The default profile keeps its files right in the data
+/// directory, as it always did
+pub fn dir(name: Option<&str>) -> PathBuf {
+    match name {
+        Some(name) => base_dir().join(PROFILES_DIR).join(name),
+        None => base_dir(),
+    }
+}
+
+/// The names of all the profiles that were played with, sorted
+pub fn list() -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(base_dir().join(PROFILES_DIR)) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+
+        if let Some(name) = entry.file_name().to_str() {
+            if entry.file_type()?.is_dir() && valid_name(name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names.sort();
+    Ok(names)
+}
+
+/// The food eaten in a game. With decay and heavy snakes, food isn't worth a point, but every
+/// piece of it makes the snake a cell longer
+fn food_eaten(entry: &Entry) -> u64 {
+    entry.length.saturating_sub(START_LENGTH) as u64
+}
+
+/// The stats of a profile, from its high score file
+pub fn summary(name: Option<&str>) -> io::Result<Summary> {
+    let entries = scores::load_from(&dir(name))?;
+    Ok(summarize(name, &entries))
+}
+
+fn summarize(name: Option<&str>, entries: &[Entry]) -> Summary {
+    Summary {
+        name: name.unwrap_or("default").to_string(),
+        games: entries.len(),
+        best: entries.iter().map(|e| e.score).max().unwrap_or(0),
+        food: entries.iter().map(food_eaten).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, length: usize) -> Entry {
+        Entry {
+            score,
+            length,
+            ruleset: "classic/30x15/decay".to_string(),
+            time: 0,
+        }
+    }
+
+    #[test]
+    fn the_food_eaten_is_counted_from_the_length() {
+        // Fresh decaying food is worth 5 points
+        let summary = summarize(Some("alice"), &[entry(15, 5), entry(4, 4)]);
+
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.best, 15);
+        assert_eq!(summary.food, 5);
+    }
+
+    #[test]
+    fn a_profile_without_games_has_nothing_to_show() {
+        let summary = summarize(None, &[]);
+
+        assert_eq!((summary.name.as_str(), summary.games, summary.best, summary.food), ("default", 0, 0, 0));
+    }
+
+    #[test]
+    fn a_profile_name_has_to_make_a_directory() {
+        for name in ["alice", "Bob-2", "x_y", &"a".repeat(32)] {
+            assert!(valid_name(name), "{}", name);
+        }
+
+        for name in ["", "..", "a/b", "al ice", "ålice", &"a".repeat(33)] {
+            assert!(!valid_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn every_profile_has_a_directory_of_its_own() {
+        assert_eq!(dir(None), base_dir());
+        assert_eq!(dir(Some("alice")), base_dir().join("profiles").join("alice"));
+    }
+
+    #[test]
+    fn a_profile_is_picked_once_and_handed_over_after() {
+        switch(None);
+
+        assert!(!select("al ice"));
+        assert!(select("alice"));
+        assert!(!select("bob"));
+        assert_eq!(current().as_deref(), Some("alice"));
+
+        switch(Some("bob"));
+        assert_eq!(current().as_deref(), Some("bob"));
+
+        switch(None);
+        assert_eq!(current(), None);
+    }
+}
@@ -0,0 +1,375 @@
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Duration;
+
+use console::{Key, Term};
+
+use crate::config::{Action, Config};
+use crate::error::{self, Error, Result};
+use crate::game::{Direction::*, Game, Polarity::*};
+use crate::level::Level;
+use crate::pack::Pack;
+use crate::render::{self, Scale, Transition};
+use crate::rules;
+use crate::ruleset::Mode;
+use crate::scores::data_dir;
+use crate::session::Session;
+use crate::title;
+
+const RECORDS_FILE: &str = "puzzles.tsv";
+
+/// How often a level file being edited is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The levels that come with the game, in the order they're meant to be played
+const BUILTIN: [&str; 4] = [
+    include_str!("../levels/01-first-steps.snklvl"),
+    include_str!("../levels/02-around-the-wall.snklvl"),
+    include_str!("../levels/03-corridors.snklvl"),
+    include_str!("../levels/04-the-long-way.snklvl"),
+];
+
+/// The levels that come with the game
+pub fn builtin() -> Pack {
+    Pack {
+        name: "Snek".to_string(),
+        author: None,
+        levels: BUILTIN
+            .iter()
+            .map(|text| Level::parse(text).expect("a built-in level is broken"))
+            .collect(),
+    }
+}
+
+/// How well a level was cleared: three stars for doing it in the fewest moves possible, two
+/// for needing at most half as many more, one otherwise. A level that doesn't know its
+/// minimum only ever gives one star
+pub fn stars(moves: u32, target: Option<u32>) -> u32 {
+    match target {
+        Some(target) if moves <= target => 3,
+        Some(target) if moves <= target + target / 2 => 2,
+        _ => 1,
+    }
+}
+
+/// The fewest moves each level was cleared in by the profile in use, by level name
+pub fn records() -> io::Result<Vec<(String, u32)>> {
+    let content = match fs::read_to_string(data_dir().join(RECORDS_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut records: Vec<(String, u32)> = Vec::new();
+
+    for (name, moves) in content.lines().filter_map(|line| line.rsplit_once('\t')) {
+        let Ok(moves) = moves.parse() else {
+            continue;
+        };
+
+        match records.iter_mut().find(|(n, _)| n == name) {
+            Some((_, best)) => *best = (*best).min(moves),
+            None => records.push((name.to_string(), moves)),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Keep the number of moves a level was cleared in
+fn save_record(name: &str, moves: u32) -> io::Result<()> {
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(RECORDS_FILE))?;
+
+    // A tab can't be in a name, it's one line of the level file
+    writeln!(file, "{}\t{}", name, moves)
+}
+
+/// What the puzzle waits for between two screens
+enum Input {
+    Key(Key),
+    /// The level file changed, and this is what's in it now
+    Changed(io::Result<Level>),
+}
+
+/// Play a level, keeping its records under the given name. Nothing happens between the key
+/// presses: every direction pressed moves the snake one cell, and the screen is drawn again
+/// after every key.
+///
+/// When the level comes from a file being edited, it's watched: every time it's saved the
+/// level is loaded again and started over, so whoever makes it sees the change right away.
+/// That's no way to set a record, there are none while watching
+pub fn run(level: Level, record: &str, config: &Config, watch: Option<&Path>, session: &mut Session) {
+    let term = Term::stdout();
+
+    if let Err(e) = title::push(&term, &format!("Snek! ({})", level.name)) {
+        error::report(Error::Terminal(e));
+    }
+
+    let (inputs, received) = mpsc::channel();
+
+    if let Some(path) = watch {
+        let path = path.to_path_buf();
+        let inputs = inputs.clone();
+
+        thread::spawn(move || watch_file(&path, inputs));
+    }
+
+    let record = if watch.is_some() { None } else { Some(record) };
+
+    // The level is played on a thread of its own, so that a change of the file doesn't have
+    // to wait for a key. The keys are read here until quit, the terminal is only left once
+    // the last read is over
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            if let Err(e) = play(level, record, config, &Term::stdout(), &received, session) {
+                error::hand_over(e);
+            }
+        });
+
+        loop {
+            let key = error::read_key(&term);
+            let quit = config.action(&key) == Some(Action::Quit);
+
+            if inputs.send(Input::Key(key)).is_err() || quit {
+                break;
+            }
+        }
+    });
+
+    if let Err(e) = title::pop(&term) {
+        error::report(Error::Terminal(e));
+    }
+}
+
+/// Check the file a few times a second, and load it again whenever it changed
+fn watch_file(path: &Path, inputs: Sender<Input>) {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    let mut last = modified(path);
+
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+
+        let now = modified(path);
+        if now == last {
+            continue;
+        }
+        last = now;
+
+        if inputs.send(Input::Changed(Level::load(path))).is_err() {
+            return;
+        }
+    }
+}
+
+/// Whether the level is failed for taking too many moves. Eating the last food with the last
+/// move still clears it
+fn out_of_moves(level: &Level, game: &Game) -> bool {
+    level.limit.is_some_and(|limit| game.ticks >= limit as u64) && !game.cleared()
+}
+
+fn play(
+    mut level: Level,
+    record: Option<&str>,
+    config: &Config,
+    term: &Term,
+    inputs: &Receiver<Input>,
+    session: &mut Session,
+) -> Result<()> {
+    let mut frame = render::Frame::default();
+    let mut stdout = io::stdout();
+
+    let best = match record {
+        Some(record) => records().map_err(Error::Scores)?.into_iter().find(|(name, _)| name == record),
+        None => None,
+    };
+    let mut best = best.map(|(_, moves)| moves);
+
+    // The food of a level is always in the same place, so is the snake
+    let mut game = Game::from_level(&level, Mode::Puzzle, 0);
+
+    // What's wrong with the level file since it was last saved, if anything
+    let mut broken: Option<io::Error> = None;
+    let mut rules = false;
+    let mut last_scale = None;
+    let rules_key = config.key_of(Action::Rules).unwrap_or_default();
+
+    loop {
+        let moves = game.ticks as u32;
+
+        // The terminal can still be resized while waiting for a key. The new size is only seen
+        // with the next key, which is good enough when nothing moves in between
+        let scale = Scale::pick(term, &game, 3);
+        if last_scale.is_some_and(|last| last != scale) {
+            frame.transition(Transition::Wipe);
+        }
+        last_scale = Some(scale);
+
+        match scale {
+            _ if rules => {
+                frame.begin();
+                rules::draw(&mut frame, &game.ruleset, &rules_key);
+            },
+            Ok(scale) => {
+                frame.begin();
+                frame.field(&game, scale);
+
+                frame.goal(game.length, level.snake.len(), game.cleared_length());
+
+                write!(frame, "{}  moves {}", level.name, moves).unwrap();
+                if let Some(target) = level.moves {
+                    write!(frame, " (can be done in {})", target).unwrap();
+                }
+                if let Some(best) = best {
+                    write!(frame, "  best {}", best).unwrap();
+                }
+                if let Some(limit) = level.limit {
+                    write!(frame, "  {} moves left", limit.saturating_sub(moves)).unwrap();
+                }
+                writeln!(frame).unwrap();
+
+                if let Some(e) = broken.as_ref() {
+                    writeln!(frame, "The level can't be loaded: {}", e).unwrap();
+                } else if game.over {
+                    writeln!(frame, "Ouch! r to try again").unwrap();
+                } else if out_of_moves(&level, &game) {
+                    writeln!(frame, "Out of moves! r to try again").unwrap();
+                } else if game.cleared() {
+                    let stars = stars(moves, level.moves) as usize;
+                    writeln!(frame, "Cleared! [{:*<stars$}{:.<rest$}]  r to play again", "", "", stars = stars, rest = 3 - stars).unwrap();
+                } else {
+                    writeln!(frame, "Eat all the food, r starts over").unwrap();
+                }
+            },
+            Err(size) => frame.too_small(size),
+        }
+
+        frame.present(&mut stdout).map_err(Error::Terminal)?;
+
+        let key = match inputs.recv() {
+            Ok(Input::Key(key)) => key,
+            // The level as it is now, from the start. Until it's fixed, a broken file leaves
+            // the level as it was
+            Ok(Input::Changed(Ok(changed))) => {
+                level = changed;
+                broken = None;
+                session.finish(&game);
+                game = Game::from_level(&level, Mode::Puzzle, 0);
+
+                // The new level pushes the old one off the screen, whatever size it's drawn at
+                frame.transition(Transition::Slide);
+                last_scale = None;
+                continue;
+            },
+            Ok(Input::Changed(Err(e))) => {
+                broken = Some(e);
+                continue;
+            },
+            Err(_) => return Ok(()),
+        };
+
+        let dir = match config.action(&key) {
+            Some(Action::Left) => Hor(Neg),
+            Some(Action::Right) => Hor(Pos),
+            Some(Action::Up) => Ver(Neg),
+            Some(Action::Down) => Ver(Pos),
+            Some(Action::Quit) => {
+                session.finish(&game);
+                return Ok(());
+            },
+            Some(Action::Rules) => {
+                rules = !rules;
+                continue;
+            },
+            Some(_) => continue,
+            // Nothing moves while the rules are read
+            None if rules => continue,
+            None if key == Key::Char('r') => {
+                session.finish(&game);
+                game = Game::from_level(&level, Mode::Puzzle, 0);
+                continue;
+            },
+            None => continue,
+        };
+
+        // Going on in the same direction is a move too, turning around isn't one
+        if rules || game.over || game.cleared() || out_of_moves(&level, &game) || !(dir == game.dir || game.dir.can_turn_to(dir)) {
+            continue;
+        }
+
+        let events = game.tick(dir);
+        session.tick(&game, &events);
+
+        if let (true, Some(record)) = (game.cleared(), record) {
+            let moves = game.ticks as u32;
+            save_record(record, moves).map_err(Error::Scores)?;
+
+            best = Some(best.map_or(moves, |best| best.min(moves)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_fewer_moves_the_more_stars() {
+        assert_eq!(stars(8, Some(10)), 3);
+        assert_eq!(stars(10, Some(10)), 3);
+        assert_eq!(stars(15, Some(10)), 2);
+        assert_eq!(stars(16, Some(10)), 1);
+        assert_eq!(stars(1, None), 1);
+    }
+
+    #[test]
+    fn the_built_in_levels_load() {
+        let pack = builtin();
+
+        assert_eq!(pack.levels.len(), BUILTIN.len());
+        assert!(pack.levels.iter().all(|level| level.moves.is_some()));
+    }
+
+    #[test]
+    fn a_puzzle_moves_one_key_at_a_time_and_ends_with_the_food() {
+        let level = Level::parse("snek-level 1\nname Two\nmap\n#####\n#o>$$\n#####").unwrap();
+        let mut game = Game::from_level(&level, Mode::Puzzle, 0);
+
+        game.tick(Hor(Pos));
+        assert_eq!((game.ticks, game.cleared()), (1, false));
+
+        // No food comes after the last piece
+        game.tick(Hor(Pos));
+        assert_eq!((game.ticks, game.length, game.cleared()), (2, 4, true));
+    }
+
+    #[test]
+    fn a_level_is_failed_once_its_moves_run_out() {
+        let mut level = Level::parse("snek-level 1\nname Far\nlimit 2\nmap\n#####\n#o>.$\n#####").unwrap();
+        let mut game = Game::from_level(&level, Mode::Puzzle, 0);
+
+        game.tick(Hor(Pos));
+        assert!(!out_of_moves(&level, &game));
+
+        // The last move can still clear it
+        game.tick(Hor(Pos));
+        assert!(game.cleared() && !out_of_moves(&level, &game));
+
+        level.limit = Some(1);
+        let mut game = Game::from_level(&level, Mode::Puzzle, 0);
+        game.tick(Hor(Pos));
+        assert!(out_of_moves(&level, &game));
+
+        level.limit = None;
+        assert!(!out_of_moves(&level, &game));
+    }
+}
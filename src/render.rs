@@ -0,0 +1,609 @@
+use std::fmt::{self, Write as _};
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use console::Term;
+
+use crate::board::{Cell, Entity, Tile::*};
+use crate::game::{Game, FOOD_VALUE};
+use crate::graphics::Protocol;
+use crate::pixels::Canvas;
+use crate::ruleset::Mutator;
+
+/// Clear the rest of the screen
+const CLEAR_BELOW: &str = "\x1b[J";
+/// Move the cursor to the top left corner
+const HOME: &str = "\x1b[H";
+/// How every line ends
+const LINE_END: &str = "\x1b[K\n";
+
+/// How many frames a transition between two boards takes, and how long each one stays on the
+/// screen
+const TRANSITION_FRAMES: usize = 8;
+const TRANSITION_FRAME: Duration = Duration::from_millis(25);
+
+/// Food pulses between these two colors, so it's easy to spot on a busy field
+const FOOD_COLORS: [&str; 2] = ["\x1b[33m", "\x1b[93m"];
+/// How many frames the food stays in each color
+const PULSE_FRAMES: u64 = 5;
+/// Food that decays goes from green to red as it loses its points, one color per point
+const DECAY_COLORS: [&str; FOOD_VALUE as usize] = ["\x1b[31m", "\x1b[91m", "\x1b[33m", "\x1b[93m", "\x1b[92m"];
+const DEFAULT_COLOR: &str = "\x1b[39m";
+
+/// The cell the hint says to turn to, when the snake is about to die
+const HINT_BACKGROUND: &str = "\x1b[42m";
+const DEFAULT_BACKGROUND: &str = "\x1b[49m";
+
+/// What a cell the snake just left looks like, one glyph per frame until it's empty again
+const TRAIL_GLYPHS: [char; 3] = ['o', ':', '·'];
+const DIM: &str = "\x1b[2m";
+const NORMAL: &str = "\x1b[22m";
+/// The age of a cell the snake is in. Other ages count the frames since the snake left it
+const TRAIL_SNAKE: u8 = u8::MAX;
+
+/// Where the snake isn't where the other snake is, when two games are compared
+const APART_COLOR: &str = "\x1b[36m";
+/// What's between two games drawn next to each other
+const PAIR_GAP: &str = "   ";
+
+/// The goal bar turns yellow half way to the goal, bright yellow at three quarters and green
+/// once it's reached
+const GOAL_COLORS: [&str; 4] = [DEFAULT_COLOR, "\x1b[33m", "\x1b[93m", "\x1b[32m"];
+const GOAL_WIDTH: usize = 20;
+
+/// The quadrant blocks, indexed by which quarters of the character are filled: 1 is the top
+/// left one, 2 the top right, 4 the bottom left and 8 the bottom right
+const QUADRANTS: [char; 16] = [
+    '.', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// How one board makes way for another, instead of just being replaced by it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Transition {
+    /// The new board is drawn over the old one from the top down, a few lines a frame. For the
+    /// same game drawn another way, e.g. when the terminal is resized
+    Wipe,
+    /// The old board goes up and out of the screen while the new one comes in from below. For
+    /// a board that's not the same anymore, like a new level
+    Slide,
+}
+
+/// How big the field is drawn
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Scale {
+    /// One character per cell
+    Full,
+    /// One character per 2x2 cells, drawn with quadrant blocks. Less readable, but it keeps the
+    /// game playable in a small terminal
+    Half,
+    /// Every cell is a block of that many characters each way, for big terminals and for
+    /// players who need the field bigger
+    Large(usize),
+}
+
+impl Scale {
+    /// The number of columns and lines the field takes on the screen
+    pub fn field_size(self, game: &Game) -> (usize, usize) {
+        match self {
+            Scale::Full => (game.cols(), game.rows()),
+            Scale::Half => (game.cols().div_ceil(2), game.rows().div_ceil(2)),
+            Scale::Large(n) => (game.cols() * n, game.rows() * n),
+        }
+    }
+
+    /// The biggest scale the field fits in the terminal with, along with `extra` lines below
+    /// it. If it doesn't fit at all, the size the terminal needs to have
+    pub fn pick(term: &Term, game: &Game, extra: usize) -> Result<Scale, (usize, usize)> {
+        Scale::pick_up_to(term, game, extra, Scale::Full)
+    }
+
+    /// Same as `pick`, but starting from a large print scale and going down one step at a time
+    /// when the field doesn't fit like that, all the way to half if it has to
+    pub fn pick_up_to(term: &Term, game: &Game, extra: usize, largest: Scale) -> Result<Scale, (usize, usize)> {
+        let (term_rows, term_cols) = term.size();
+
+        // The cursor ends up on the line below the last one, so that one is needed too
+        let needed = |scale: Scale| {
+            let (cols, lines) = scale.field_size(game);
+            (cols, lines + extra + 1)
+        };
+
+        let fits = |(cols, rows): (usize, usize)| cols <= term_cols as usize && rows <= term_rows as usize;
+
+        let large = match largest {
+            Scale::Large(n) => (2..=n).rev().map(Scale::Large).collect(),
+            _ => Vec::new(),
+        };
+
+        large
+            .into_iter()
+            .chain([Scale::Full, Scale::Half])
+            .find(|scale| fits(needed(*scale)))
+            .ok_or_else(|| needed(Scale::Half))
+    }
+}
+
+/// There is no event for a resize, so this keeps checking the size of the terminal and calls
+/// `resized` whenever it changed, for as long as that returns `true`. Meant for a thread of its
+/// own
+pub fn watch_size(mut resized: impl FnMut() -> bool) {
+    let term = Term::stdout();
+    let mut size = term.size();
+
+    loop {
+        thread::sleep(Duration::from_millis(100));
+
+        if term.size() != size {
+            size = term.size();
+
+            if !resized() {
+                return;
+            }
+        }
+    }
+}
+
+/// A whole screen, built in memory and sent to the terminal with a single write.
+///
+/// The buffer is reused from frame to frame, so once it has grown to the size of a frame
+/// drawing doesn't allocate anymore. The screen is never cleared either: every line clears
+/// whatever is left of the previous frame to its right, and the last one everything below it.
+/// Without that the terminal can show a half-drawn frame and the field flickers.
+///
+/// Lines are written with `write!`/`writeln!`.
+#[derive(Default)]
+pub struct Frame {
+    buf: String,
+    /// How many frames were drawn. Animations are driven by this, not by the ticks of the
+    /// game, so they keep going when the game is paused
+    frames: u64,
+    /// The age of every cell of the field, when the trail is drawn
+    trail: Option<Vec<u8>>,
+    /// How the field is drawn as pixels, on terminals that can show them
+    graphics: Option<Protocol>,
+    /// Whether this frame has the field as pixels, and whether the last one sent did. A kitty
+    /// picture stays on the screen until it's taken down
+    pictured: bool,
+    on_screen: bool,
+    /// The field as pixels, and what the protocol needs while encoding it. Both are kept from
+    /// one frame to the next
+    canvas: Option<Canvas>,
+    scratch: Vec<u8>,
+    /// The last frame sent, what a transition starts from
+    shown: String,
+    /// The transition the next frame comes in with, and the frame it starts from
+    transition: Option<(Transition, String)>,
+    /// The cell to highlight on the field being drawn, with the hint mutator
+    hint: Option<Cell>,
+}
+
+impl Frame {
+    /// Start a new frame
+    pub fn begin(&mut self) {
+        self.frames += 1;
+        self.pictured = false;
+        self.buf.clear();
+        self.buf.push_str(HOME);
+    }
+
+    /// A frame with nothing but a request for a bigger terminal, shown instead of a frame that
+    /// doesn't fit
+    pub fn too_small(&mut self, (cols, rows): (usize, usize)) {
+        self.begin();
+        write!(self.buf, "Please enlarge your terminal to at least {}×{}", cols, rows).unwrap();
+    }
+
+    /// Bring the next frame in with a transition from the one on the screen now. Pictures
+    /// can't be cut in lines, a frame that has one or replaces one just comes in at once
+    pub fn transition(&mut self, transition: Transition) {
+        if !self.shown.is_empty() {
+            self.transition = Some((transition, self.shown.clone()));
+        }
+    }
+
+    /// Draw the field as pixels with the protocol, rather than with characters. With sixel the
+    /// pixels can't be scaled, so a field at half scale is still drawn with characters
+    pub fn use_graphics(&mut self, protocol: Protocol) {
+        self.graphics = Some(protocol);
+    }
+
+    /// Draw a fading trail behind the snake, in the cells it left during the last few frames.
+    /// Only at full scale, there's no room for it in a quadrant block
+    pub fn show_trail(&mut self) {
+        self.trail = Some(Vec::new());
+    }
+
+    /// Age the trail by one frame, starting over if the field changed size
+    fn age_trail(&mut self, game: &Game) {
+        let Some(ages) = self.trail.as_mut() else {
+            return;
+        };
+
+        if ages.len() != game.rows() * game.cols() {
+            ages.clear();
+            ages.resize(game.rows() * game.cols(), 0);
+        }
+
+        for row in 0..game.rows() {
+            for (col, tile) in game.field.row(row).iter().enumerate() {
+                let age = &mut ages[row * game.cols() + col];
+
+                *age = match (tile, *age) {
+                    (Snake(_), _) => TRAIL_SNAKE,
+                    (_, TRAIL_SNAKE) => 1,
+                    (_, 0) => 0,
+                    (_, age) if age as usize >= TRAIL_GLYPHS.len() => 0,
+                    (_, age) => age + 1,
+                };
+            }
+        }
+    }
+
+    /// The trail glyph of an empty cell, if the snake left it not long ago
+    fn trail_glyph(&self, index: usize) -> Option<char> {
+        let age = *self.trail.as_ref()?.get(index)?;
+
+        match age {
+            0 | TRAIL_SNAKE => None,
+            age => TRAIL_GLYPHS.get(age as usize - 1).copied(),
+        }
+    }
+
+    /// Add an escape sequence that doesn't draw anything, e.g. to change the title
+    pub fn raw(&mut self, s: &str) {
+        self.buf.push_str(s);
+    }
+
+    /// Draw on its own line how far the snake is on its way from the length it started with to
+    /// the length it has to reach
+    pub fn goal(&mut self, length: usize, start: usize, target: usize) {
+        let done = length.clamp(start, target) - start;
+        let way = (target - start).max(1);
+
+        let filled = done * GOAL_WIDTH / way;
+        let color = match done * 4 / way {
+            0 | 1 => GOAL_COLORS[0],
+            2 => GOAL_COLORS[1],
+            3 => GOAL_COLORS[2],
+            _ => GOAL_COLORS[3],
+        };
+
+        write!(
+            self.buf,
+            "length [{}{:#<filled$}{}{:.<rest$}] {}/{}",
+            color,
+            "",
+            DEFAULT_COLOR,
+            "",
+            length,
+            target,
+            filled = filled,
+            rest = GOAL_WIDTH - filled,
+        )
+        .unwrap();
+        self.newline();
+    }
+
+    /// Draw the field at the given scale. The hint is only drawn on characters at full scale
+    /// or bigger
+    pub fn field(&mut self, game: &Game, scale: Scale) {
+        self.hint = match game.ruleset.has(Mutator::Hint) {
+            true => game.safe_turn(game.dir).and_then(|turn| game.next_cell(turn)),
+            false => None,
+        };
+
+        match (self.graphics, scale) {
+            (Some(Protocol::Sixel), Scale::Half) | (None, _) => {},
+            (Some(protocol), _) => return self.field_pixels(game, scale, protocol),
+        }
+
+        match scale {
+            Scale::Full => self.field_full(game),
+            Scale::Half => self.field_half(game),
+            Scale::Large(n) => self.field_large(game, n),
+        }
+    }
+
+    /// The field as a picture taking the cells it would take with characters. The lines are
+    /// cleared first, then the cursor goes back up to draw the picture from the top, and down
+    /// below it again for what comes next
+    fn field_pixels(&mut self, game: &Game, scale: Scale, protocol: Protocol) {
+        let (cols, lines) = scale.field_size(game);
+
+        for _ in 0..lines {
+            self.newline();
+        }
+
+        // A new canvas is only needed for a field of another size
+        let canvas = match &mut self.canvas {
+            Some(canvas) if canvas.fits(game, false) => canvas,
+            canvas => canvas.insert(Canvas::new(game, false)),
+        };
+        canvas.draw(game);
+
+        write!(self.buf, "\x1b[{}A\x1b7", lines).unwrap();
+        protocol.encode(canvas, cols, lines, &mut self.scratch, &mut self.buf);
+        write!(self.buf, "\x1b8\x1b[{}B", lines).unwrap();
+
+        self.pictured = true;
+    }
+
+    fn food_color(&self, game: &Game) -> &'static str {
+        if game.ruleset.has(Mutator::Decay) {
+            return DECAY_COLORS[game.decay_value() as usize - 1];
+        }
+
+        FOOD_COLORS[(self.frames / PULSE_FRAMES % 2) as usize]
+    }
+
+    /// Draw a character in the color of the food
+    fn push_food(&mut self, game: &Game, ch: char) {
+        self.buf.push_str(self.food_color(game));
+        self.buf.push(ch);
+        self.buf.push_str(DEFAULT_COLOR);
+    }
+
+    /// The number of columns two games of the same size take next to each other
+    pub fn pair_width(game: &Game) -> usize {
+        game.cols() * 2 + PAIR_GAP.chars().count()
+    }
+
+    /// Draw two games of the same size next to each other at full scale. The segments of
+    /// each snake that aren't where the other snake is are highlighted, that's where they went
+    /// different ways
+    pub fn pair(&mut self, left: &Game, right: &Game) {
+        self.hint = None;
+
+        for row in 0..left.rows() {
+            self.row_full(left, row, Some(right));
+            self.buf.push_str(PAIR_GAP);
+            self.row_full(right, row, Some(left));
+            self.newline();
+        }
+    }
+
+    /// One line per row
+    fn field_full(&mut self, game: &Game) {
+        self.age_trail(game);
+
+        for row in 0..game.rows() {
+            self.row_full(game, row, None);
+            self.newline();
+        }
+    }
+
+    /// A row of the field, with the segments of the snake that aren't in the other game if
+    /// there's one to compare with highlighted
+    fn row_full(&mut self, game: &Game, row: usize, other: Option<&Game>) {
+        for col in 0..game.cols() {
+            self.cell_full(game, Cell { row, col }, other);
+        }
+    }
+
+    /// Every cell drawn the same as at full scale, `n` times over to the right and `n` times
+    /// down
+    fn field_large(&mut self, game: &Game, n: usize) {
+        self.age_trail(game);
+
+        for row in 0..game.rows() {
+            let line = self.buf.len();
+
+            for col in 0..game.cols() {
+                let start = self.buf.len();
+                self.cell_full(game, Cell { row, col }, None);
+
+                let end = self.buf.len();
+                for _ in 1..n {
+                    self.buf.extend_from_within(start..end);
+                }
+            }
+
+            self.newline();
+
+            // Repeated in place, the buffer is reused from frame to frame
+            let end = self.buf.len();
+            for _ in 1..n {
+                self.buf.extend_from_within(line..end);
+            }
+        }
+    }
+
+    /// A cell of the field, as one character
+    fn cell_full(&mut self, game: &Game, cell: Cell, other: Option<&Game>) {
+        if self.hint == Some(cell) {
+            self.buf.push_str(HINT_BACKGROUND);
+            self.cell_plain(game, cell, other);
+            self.buf.push_str(DEFAULT_BACKGROUND);
+        } else {
+            self.cell_plain(game, cell, other);
+        }
+    }
+
+    fn cell_plain(&mut self, game: &Game, cell: Cell, other: Option<&Game>) {
+        match (game.field.tile(cell), game.field.entity(cell)) {
+            (Snake(_), _) if other.is_some_and(|other| !matches!(other.field.tile(cell), Snake(_))) => {
+                self.buf.push_str(APART_COLOR);
+                self.buf.push('@');
+                self.buf.push_str(DEFAULT_COLOR);
+            },
+            (Snake(_), _) => self.buf.push('@'),
+            (Wall, _) => self.buf.push('#'),
+            (Empty, Some(Entity::Food)) => self.push_food(game, '$'),
+            // Only the pellet to eat next is in the color of the food
+            (Empty, Some(Entity::Pellet(n))) => {
+                let digit = char::from_digit(n as u32 % 10, 10).unwrap();
+
+                if game.next_food() == Some(cell) {
+                    self.push_food(game, digit);
+                } else {
+                    self.buf.push(digit);
+                }
+            },
+            (Empty, None) => match self.trail_glyph(cell.row * game.cols() + cell.col) {
+                Some(glyph) => {
+                    self.buf.push_str(DIM);
+                    self.buf.push(glyph);
+                    self.buf.push_str(NORMAL);
+                },
+                None => self.buf.push('.'),
+            },
+        }
+    }
+
+    /// One line per two rows, one character per two columns. A quarter of a character is filled
+    /// where there is something, and the characters with food in them are colored so that the
+    /// food can still be told from the snake
+    fn field_half(&mut self, game: &Game) {
+        for top in (0..game.rows()).step_by(2) {
+            for left in (0..game.cols()).step_by(2) {
+                let mut quarters = 0;
+                let mut food = false;
+
+                for (bit, row, col) in [(1, top, left), (2, top, left + 1), (4, top + 1, left), (8, top + 1, left + 1)] {
+                    let cell = Cell { row, col };
+
+                    if !game.field.contains(cell) {
+                        continue;
+                    }
+
+                    if game.field.entity(cell).is_some() {
+                        food = true;
+                    }
+
+                    if !game.field.is_free(cell) {
+                        quarters |= bit;
+                    }
+                }
+
+                if food {
+                    self.push_food(game, QUADRANTS[quarters]);
+                } else {
+                    self.buf.push(QUADRANTS[quarters]);
+                }
+            }
+
+            self.newline();
+        }
+    }
+
+    fn newline(&mut self) {
+        self.buf.push_str(LINE_END);
+    }
+
+    /// Send the frame to the terminal. If a transition was started, its frames are sent first,
+    /// which takes a moment
+    pub fn present(&mut self, out: &mut impl Write) -> io::Result<()> {
+        // End the last line like all the others, but also get rid of anything below it
+        if self.buf.ends_with(LINE_END) {
+            self.buf.truncate(self.buf.len() - LINE_END.len());
+        }
+
+        if let Some((transition, old)) = self.transition.take() {
+            if !self.on_screen && !self.pictured {
+                self.play(transition, &old, out)?;
+            }
+        }
+
+        self.shown.clear();
+        self.shown.push_str(&self.buf);
+
+        self.buf.push_str(CLEAR_BELOW);
+        self.buf.push('\n');
+
+        if self.on_screen && !self.pictured {
+            if let Some(protocol) = self.graphics {
+                protocol.clear(&mut self.buf);
+            }
+        }
+        self.on_screen = self.pictured;
+
+        out.write_all(self.buf.as_bytes())?;
+        out.flush()
+    }
+
+    /// The frames of a transition from the old frame to this one, put together line by line
+    fn play(&self, transition: Transition, old: &str, out: &mut impl Write) -> io::Result<()> {
+        let lines = |frame: &str| -> Vec<String> { frame.trim_start_matches(HOME).split(LINE_END).map(quiet).collect() };
+        let (old, new) = (lines(old), lines(&self.buf));
+
+        let height = old.len().max(new.len());
+        // The shorter frame ends in empty lines
+        fn line(lines: &[String], i: usize) -> &str {
+            lines.get(i).map_or("", |line| line.as_str())
+        }
+
+        let mut mixed = String::new();
+
+        for step in 1..TRANSITION_FRAMES {
+            let done = height * step / TRANSITION_FRAMES;
+
+            mixed.clear();
+            mixed.push_str(HOME);
+
+            for i in 0..height {
+                if i > 0 {
+                    mixed.push_str(LINE_END);
+                }
+
+                mixed.push_str(match transition {
+                    Transition::Wipe if i < done => line(&new, i),
+                    Transition::Wipe => line(&old, i),
+                    Transition::Slide if i + done < height => line(&old, i + done),
+                    Transition::Slide => line(&new, i + done - height),
+                });
+            }
+
+            mixed.push_str(CLEAR_BELOW);
+            mixed.push('\n');
+
+            out.write_all(mixed.as_bytes())?;
+            out.flush()?;
+
+            thread::sleep(TRANSITION_FRAME);
+        }
+
+        Ok(())
+    }
+}
+
+/// A line without what only has to reach the terminal once, with the frame itself: the bell,
+/// and the title
+fn quiet(line: &str) -> String {
+    let mut quiet = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(at) = rest.find(['\x1b', '\x07']) {
+        quiet.push_str(&rest[..at]);
+        rest = &rest[at..];
+
+        rest = match rest.strip_prefix("\x1b]") {
+            Some(title) => title.split_once('\x07').map_or("", |(_, after)| after),
+            None if rest.starts_with('\x07') => &rest[1..],
+            None => {
+                quiet.push('\x1b');
+                &rest[1..]
+            },
+        };
+    }
+
+    quiet.push_str(rest);
+    quiet
+}
+
+impl fmt::Write for Frame {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut lines = s.split('\n');
+
+        if let Some(first) = lines.next() {
+            self.buf.push_str(first);
+        }
+
+        for line in lines {
+            self.newline();
+            self.buf.push_str(line);
+        }
+
+        Ok(())
+    }
+}
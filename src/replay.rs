@@ -0,0 +1,444 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::board::Cell;
+use crate::challenge::Challenge;
+use crate::codec;
+use crate::game::{Direction, Direction::*, Event, Game, Polarity::*, Snapshot};
+use crate::scores::data_dir;
+
+const REPLAYS_DIR: &str = "replays";
+
+/// How often a snapshot of the whole game is stored in the replay. Seeking to any tick then
+/// takes at most this many ticks of simulation
+pub const KEYFRAME_EVERY: u64 = 100;
+
+/// The longest replay that is read, a bit more than a day of play. Replays come from other
+/// players, a broken one can't be allowed to make the game allocate all the memory there is
+const MAX_TICKS: usize = 1_000_000;
+
+/// A recorded run: the challenge it was played with and the direction the snake went on every
+/// tick. Since the game is deterministic that's all it takes to play it again.
+///
+/// The inputs are all it takes to play the replay, but long replays would need a lot of
+/// simulation to jump to the end. So the replay also stores what happened on which tick and a
+/// snapshot of the game every `KEYFRAME_EVERY` ticks.
+///
+/// Replays are saved with `codec`, the inputs being written as a string with one letter per
+/// tick (`U`, `D`, `L` or `R`) to keep the files small. The older text format is still read,
+/// see `from_text`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub challenge: Challenge,
+    /// `inputs[i]` is the direction used on tick `i + 1`
+    #[serde(serialize_with = "inputs_to_string", deserialize_with = "inputs_from_string")]
+    pub inputs: Vec<Direction>,
+    /// The tick every event happened on
+    pub events: Vec<(u64, Event)>,
+    pub keyframes: Vec<Snapshot>,
+    /// `hashes[i]` is (the low half of) the state hash after tick `i + 1`, so a replay that
+    /// plays differently with another version of the game can be told at the tick it happens.
+    /// Older replays don't have them
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "hashes_to_string",
+        deserialize_with = "hashes_from_string"
+    )]
+    pub hashes: Vec<u32>,
+}
+
+/// What comes out of playing a replay again, see `Replay::verify`
+pub struct Verdict {
+    /// The game at the end of the replay
+    pub game: Game,
+    /// The first tick that goes differently than when the replay was recorded, if the replay
+    /// has the hashes to tell
+    pub divergence: Option<u64>,
+    /// Whether the events and keyframes in the replay are the ones its inputs make
+    pub consistent: bool,
+    /// The inputs left after the game over. The game doesn't record any
+    pub extra_inputs: usize,
+}
+
+impl Verdict {
+    /// What's wrong with the replay, if anything
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(tick) = self.divergence {
+            problems.push(format!("it plays differently than when it was recorded from tick {}", tick));
+        }
+        if !self.consistent {
+            problems.push("its events or keyframes don't match its inputs".to_string());
+        }
+        if self.extra_inputs > 0 {
+            problems.push(format!("it has {} inputs after the game over", self.extra_inputs));
+        }
+
+        problems
+    }
+}
+
+fn dir_to_char(dir: Direction) -> char {
+    match dir {
+        Ver(Neg) => 'U',
+        Ver(Pos) => 'D',
+        Hor(Neg) => 'L',
+        Hor(Pos) => 'R',
+    }
+}
+
+fn dir_from_str(s: &str) -> Option<Direction> {
+    match s {
+        "U" => Some(Ver(Neg)),
+        "D" => Some(Ver(Pos)),
+        "L" => Some(Hor(Neg)),
+        "R" => Some(Hor(Pos)),
+        _ => None,
+    }
+}
+
+fn inputs_to_string<S: Serializer>(inputs: &[Direction], serializer: S) -> Result<S::Ok, S::Error> {
+    let inputs: String = inputs.iter().map(|dir| dir_to_char(*dir)).collect();
+    serializer.serialize_str(&inputs)
+}
+
+fn inputs_from_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Direction>, D::Error> {
+    let inputs = String::deserialize(deserializer)?;
+
+    inputs
+        .chars()
+        .map(|ch| dir_from_str(ch.encode_utf8(&mut [0; 4])))
+        .collect::<Option<Vec<Direction>>>()
+        .ok_or_else(|| serde::de::Error::custom("inputs can only be U, D, L or R"))
+}
+
+fn hashes_to_string<S: Serializer>(hashes: &[u32], serializer: S) -> Result<S::Ok, S::Error> {
+    let hashes: String = hashes.iter().map(|hash| format!("{:08x}", hash)).collect();
+    serializer.serialize_str(&hashes)
+}
+
+fn hashes_from_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u32>, D::Error> {
+    let hashes = String::deserialize(deserializer)?;
+
+    if !hashes.is_ascii() || hashes.len() % 8 != 0 {
+        return Err(serde::de::Error::custom("hashes are 8 hex digits each"));
+    }
+
+    (0..hashes.len())
+        .step_by(8)
+        .map(|i| u32::from_str_radix(&hashes[i..i + 8], 16))
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|_| serde::de::Error::custom("hashes are 8 hex digits each"))
+}
+
+fn event_from_str(s: &str) -> Option<Event> {
+    match s {
+        "ate" => Some(Event::Ate),
+        "near-miss" => Some(Event::NearMiss),
+        "died" => Some(Event::Died),
+        _ => None,
+    }
+}
+
+fn cells_from_str(s: &str) -> Option<Vec<Cell>> {
+    if s == "-" {
+        return Some(Vec::new());
+    }
+
+    s.split(';')
+        .map(|cell| {
+            let (row, col) = cell.split_once(',')?;
+
+            Some(Cell {
+                row: row.parse().ok()?,
+                col: col.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+fn snapshot_from_str(s: &str) -> Option<Snapshot> {
+    let mut parts = s.split(' ');
+
+    let snapshot = Snapshot {
+        ticks: parts.next()?.parse().ok()?,
+        rng_pos: parts.next()?.parse().ok()?,
+        dir: dir_from_str(parts.next()?)?,
+        score: parts.next()?.parse().ok()?,
+        food: cells_from_str(parts.next()?)?,
+        pellets: Vec::new(),
+        body: cells_from_str(parts.next()?)?,
+        stalled: 0,
+        food_since: 0,
+    };
+
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(snapshot)
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+impl Replay {
+    pub fn new(challenge: Challenge) -> Replay {
+        Replay {
+            challenge,
+            inputs: Vec::new(),
+            events: Vec::new(),
+            keyframes: Vec::new(),
+            hashes: Vec::new(),
+        }
+    }
+
+    /// Record a tick that was just played: the direction the snake went, what happened and,
+    /// every now and then, the whole state of the game
+    pub fn record(&mut self, game: &Game, events: &[Event]) {
+        self.inputs.push(game.dir);
+        self.hashes.push(game.state_hash() as u32);
+
+        for event in events {
+            self.events.push((game.ticks, *event));
+        }
+
+        if !game.over && game.ticks.is_multiple_of(KEYFRAME_EVERY) {
+            self.keyframes.push(game.snapshot());
+        }
+    }
+
+    /// Play the inputs again to fill in the events and keyframes
+    pub fn rebuild(&self) -> Replay {
+        self.play().0
+    }
+
+    /// Play the inputs again, up to the game over if there's one. Returns the replay that
+    /// comes out of it, with the hashes of this one, and the game as it ends
+    fn play(&self) -> (Replay, Game) {
+        let mut game = Game::new(&self.challenge);
+        let mut replay = Replay::new(self.challenge.clone());
+
+        for dir in self.inputs.iter() {
+            let events = game.tick(*dir);
+            replay.record(&game, &events);
+
+            if game.over {
+                break;
+            }
+        }
+
+        // Hashes are only worth something if they come from when the replay was recorded
+        replay.hashes = self.hashes.clone();
+        (replay, game)
+    }
+
+    /// Play the replay again from scratch and see whether it holds up, for a score somebody
+    /// claims to have made with it
+    pub fn verify(&self) -> Verdict {
+        let (rebuilt, game) = self.play();
+
+        Verdict {
+            divergence: self.divergence(),
+            // A replay written by the game has exactly what comes out of its inputs
+            consistent: rebuilt.events == self.events && rebuilt.keyframes == self.keyframes,
+            extra_inputs: self.inputs.len() - rebuilt.inputs.len(),
+            game,
+        }
+    }
+
+    /// The first tick the game goes differently on than when the replay was recorded, if it
+    /// does. That's what happens to a replay recorded with a version of the game that had
+    /// other rules
+    pub fn divergence(&self) -> Option<u64> {
+        let mut game = Game::new(&self.challenge);
+
+        for (dir, hash) in self.inputs.iter().zip(self.hashes.iter()) {
+            game.tick(*dir);
+
+            if game.state_hash() as u32 != *hash {
+                return Some(game.ticks);
+            }
+        }
+
+        None
+    }
+
+    /// Read a replay in the text format older versions of the game wrote:
+    ///
+    /// ```text
+    /// snek-replay 2
+    /// challenge snek1:classic/30x15/wrap:1234:1a2b3c4d
+    /// ticks 250
+    /// turn 12 U
+    /// turn 20 L
+    /// event 31 ate
+    /// keyframe 100 2472 L 3 4,17 7,13;6,13;6,12;6,11;6,10
+    /// ```
+    ///
+    /// The snake starts going right and `turn N D` means that from tick N on it goes in
+    /// direction D. Ticks are counted from 1. `keyframe` is `TICK RNG DIR SCORE FOOD BODY`, with
+    /// the cells written as `row,col` and the body going from the tail to the head. Version 1
+    /// files don't have events and keyframes, they are rebuilt when loading.
+    pub fn from_text(text: &str) -> io::Result<Replay> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        let version = match lines.next() {
+            Some((_, "snek-replay 1")) => 1,
+            Some((_, "snek-replay 2")) => 2,
+            _ => return Err(invalid(1, "not a replay file")),
+        };
+
+        let challenge = match lines.next() {
+            Some((n, line)) => line
+                .strip_prefix("challenge ")
+                .and_then(Challenge::parse)
+                .ok_or_else(|| invalid(n, "expected a valid challenge"))?,
+            None => return Err(invalid(2, "missing challenge")),
+        };
+
+        let ticks: usize = match lines.next() {
+            Some((n, line)) => line
+                .strip_prefix("ticks ")
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid(n, "expected the number of ticks"))?,
+            None => return Err(invalid(3, "missing the number of ticks")),
+        };
+
+        if ticks > MAX_TICKS {
+            return Err(invalid(3, "too many ticks"));
+        }
+
+        let mut inputs = Vec::with_capacity(ticks);
+        let mut events = Vec::new();
+        let mut keyframes = Vec::new();
+        let mut dir = Hor(Pos);
+
+        for (n, line) in lines {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut parts = line.split(' ');
+
+            match parts.next() {
+                Some("turn") => {},
+                Some("event") if version >= 2 => {
+                    let event = parts
+                        .next()
+                        .and_then(|t| t.parse().ok())
+                        .zip(parts.next().and_then(event_from_str))
+                        .ok_or_else(|| invalid(n, "expected a tick and an event"))?;
+
+                    events.push(event);
+                    continue;
+                },
+                Some("keyframe") if version >= 2 => {
+                    let snapshot = line
+                        .strip_prefix("keyframe ")
+                        .and_then(snapshot_from_str)
+                        .ok_or_else(|| invalid(n, "expected a keyframe"))?;
+
+                    keyframes.push(snapshot);
+                    continue;
+                },
+                _ => return Err(invalid(n, "expected a turn")),
+            }
+
+            let tick: usize = parts
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid(n, "expected a tick number"))?;
+
+            if tick <= inputs.len() || tick > ticks {
+                return Err(invalid(n, "tick out of order"));
+            }
+
+            // Keep going in the previous direction until the turn
+            inputs.resize(tick - 1, dir);
+
+            dir = parts
+                .next()
+                .and_then(dir_from_str)
+                .ok_or_else(|| invalid(n, "expected a direction"))?;
+            inputs.push(dir);
+        }
+
+        inputs.resize(ticks, dir);
+
+        let replay = Replay {
+            challenge,
+            inputs,
+            events,
+            keyframes,
+            hashes: Vec::new(),
+        };
+
+        if version == 1 {
+            return Ok(replay.rebuild());
+        }
+
+        Ok(replay)
+    }
+
+    /// Read a replay in either format. Anything that doesn't make sense, down to events after
+    /// the end of the run, is an `InvalidData` error
+    pub fn parse(text: &str) -> io::Result<Replay> {
+        let replay: Replay = if text.starts_with("snek-replay ") {
+            Replay::from_text(text)?
+        } else {
+            codec::decode("replay", text)?
+        };
+
+        let broken = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        let end = replay.inputs.len() as u64;
+
+        if replay.inputs.len() > MAX_TICKS {
+            return Err(broken("the replay is too long"));
+        }
+
+        if !replay.events.windows(2).all(|pair| pair[0].0 <= pair[1].0) {
+            return Err(broken("the events are out of order"));
+        }
+
+        if replay.events.iter().any(|(tick, _)| *tick == 0 || *tick > end) {
+            return Err(broken("event outside of the replay"));
+        }
+
+        if replay.keyframes.iter().any(|keyframe| keyframe.ticks > end) {
+            return Err(broken("keyframe after the end of the replay"));
+        }
+
+        if !replay.hashes.is_empty() && replay.hashes.len() != replay.inputs.len() {
+            return Err(broken("the hashes don't go with the inputs"));
+        }
+
+        Ok(replay)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        Replay::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, codec::encode("replay", self))
+    }
+
+    /// Save the replay in the replays directory under a name made of the given timestamp and
+    /// score. Returns the path of the file
+    pub fn save_new(&self, time: u64, score: u32) -> io::Result<PathBuf> {
+        let dir = data_dir().join(REPLAYS_DIR);
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join(format!("{}-{}.snkrep", time, score));
+        self.save(&path)?;
+
+        Ok(path)
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::scores::base_dir;
+
+/// The rounds played in hot-seat, for all the profiles. It's kept next to the profiles rather
+/// than in one of them, a round belongs to both of its players
+const RIVALRIES_FILE: &str = "rivalries.tsv";
+
+/// How a player did in a round
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Turn {
+    pub player: String,
+    pub score: u32,
+    pub length: usize,
+}
+
+/// One round of hot-seat: two players, the same challenge
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Round {
+    /// Seconds since the unix epoch
+    pub time: u64,
+    /// The fingerprint of the ruleset the round was played with
+    pub ruleset: String,
+    pub turns: [Turn; 2],
+}
+
+/// What a player has to show against another one
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Record {
+    pub wins: u32,
+    pub best: u32,
+    /// The length of all the snakes of the player put together, for the average
+    pub length: usize,
+    /// The most rounds won in a row
+    pub streak: u32,
+}
+
+/// How two players did against each other, over all their rounds
+pub struct Rivalry {
+    pub players: [String; 2],
+    pub rounds: u32,
+    pub draws: u32,
+    pub records: [Record; 2],
+}
+
+impl Turn {
+    /// Whether this turn beat the other one: the better score wins, or the longer snake if the
+    /// scores are the same
+    fn beats(&self, other: &Turn) -> bool {
+        (self.score, self.length) > (other.score, other.length)
+    }
+}
+
+impl Round {
+    pub fn new(ruleset: String, turns: [Turn; 2]) -> Round {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Round { time, ruleset, turns }
+    }
+
+    /// Who won the round, `None` for a draw
+    pub fn winner(&self) -> Option<&str> {
+        let [a, b] = &self.turns;
+
+        match (a.beats(b), b.beats(a)) {
+            (true, _) => Some(&a.player),
+            (_, true) => Some(&b.player),
+            _ => None,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let [a, b] = &self.turns;
+
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.time, self.ruleset, a.player, a.score, a.length, b.player, b.score, b.length,
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Round> {
+        let mut parts = line.split('\t');
+
+        let time = parts.next()?.parse().ok()?;
+        let ruleset = parts.next()?.to_string();
+
+        let mut turn = || -> Option<Turn> {
+            Some(Turn {
+                player: parts.next()?.to_string(),
+                score: parts.next()?.parse().ok()?,
+                length: parts.next()?.parse().ok()?,
+            })
+        };
+
+        let turns = [turn()?, turn()?];
+
+        Some(Round { time, ruleset, turns })
+    }
+}
+
+impl Record {
+    pub fn average_length(&self, rounds: u32) -> f64 {
+        if rounds == 0 {
+            return 0.0;
+        }
+
+        self.length as f64 / rounds as f64
+    }
+}
+
+/// Every round played in hot-seat. A missing file just means nobody played one yet
+pub fn load() -> io::Result<Vec<Round>> {
+    let content = match fs::read_to_string(base_dir().join(RIVALRIES_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content.lines().filter_map(Round::from_line).collect())
+}
+
+/// Append a round to the file
+pub fn save(round: &Round) -> io::Result<()> {
+    let dir = base_dir();
+    fs::create_dir_all(&dir)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(RIVALRIES_FILE))?;
+
+    writeln!(file, "{}", round.to_line())
+}
+
+/// How the two players did against each other, whoever went first in each round. A player
+/// never played against themselves, that's no rivalry at all
+pub fn between(rounds: &[Round], a: &str, b: &str) -> Rivalry {
+    let mut rivalry = Rivalry {
+        players: [a.to_string(), b.to_string()],
+        rounds: 0,
+        draws: 0,
+        records: Default::default(),
+    };
+
+    // The rounds won in a row so far, by each player
+    let mut running = [0, 0];
+
+    for round in rounds {
+        let find = |player: &str| round.turns.iter().position(|turn| turn.player == player);
+
+        let (Some(i), Some(j)) = (find(a), find(b)) else {
+            continue;
+        };
+
+        if i == j {
+            continue;
+        }
+
+        let (first, second) = (&round.turns[i], &round.turns[j]);
+
+        rivalry.rounds += 1;
+
+        for (i, turn) in [first, second].into_iter().enumerate() {
+            let record = &mut rivalry.records[i];
+            record.best = record.best.max(turn.score);
+            record.length += turn.length;
+        }
+
+        let winner = match (first.beats(second), second.beats(first)) {
+            (true, _) => Some(0),
+            (_, true) => Some(1),
+            _ => None,
+        };
+
+        match winner {
+            Some(i) => {
+                rivalry.records[i].wins += 1;
+                running[i] += 1;
+                running[1 - i] = 0;
+                rivalry.records[i].streak = rivalry.records[i].streak.max(running[i]);
+            },
+            None => {
+                rivalry.draws += 1;
+                running = [0, 0];
+            },
+        }
+    }
+
+    rivalry
+}
+
+/// Every pair of players that played against each other, in the order they first did
+pub fn pairs(rounds: &[Round]) -> Vec<[String; 2]> {
+    let mut pairs: Vec<[String; 2]> = Vec::new();
+
+    for round in rounds {
+        let mut pair = [round.turns[0].player.clone(), round.turns[1].player.clone()];
+        pair.sort();
+
+        if !pairs.contains(&pair) {
+            pairs.push(pair);
+        }
+    }
+
+    pairs
+}
+
+impl Rivalry {
+    /// The rivalry screen: the score between the two, and how each of them did
+    pub fn lines(&self) -> Vec<String> {
+        let [a, b] = &self.players;
+        let [ra, rb] = &self.records;
+
+        let mut head = format!("{} vs {}: {}-{}", a, b, ra.wins, rb.wins);
+        if self.draws > 0 {
+            head.push_str(&format!(", {} drawn", self.draws));
+        }
+        head.push_str(&match self.rounds {
+            1 => " in 1 round".to_string(),
+            n => format!(" in {} rounds", n),
+        });
+
+        let width = a.len().max(b.len()).max(5);
+
+        vec![
+            head,
+            format!("  {:<16} {:>w$}  {:>w$}", "", a, b, w = width),
+            format!("  {:<16} {:>w$}  {:>w$}", "wins", ra.wins, rb.wins, w = width),
+            format!(
+                "  {:<16} {:>w$.1}  {:>w$.1}",
+                "average length",
+                ra.average_length(self.rounds),
+                rb.average_length(self.rounds),
+                w = width,
+            ),
+            format!("  {:<16} {:>w$}  {:>w$}", "longest streak", ra.streak, rb.streak, w = width),
+            format!("  {:<16} {:>w$}  {:>w$}", "best score", ra.best, rb.best, w = width),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(a: (&str, u32, usize), b: (&str, u32, usize)) -> Round {
+        let turn = |(player, score, length): (&str, u32, usize)| Turn {
+            player: player.to_string(),
+            score,
+            length,
+        };
+
+        Round {
+            time: 0,
+            ruleset: "classic/30x15/wrap".to_string(),
+            turns: [turn(a), turn(b)],
+        }
+    }
+
+    #[test]
+    fn the_longer_snake_wins_a_tie() {
+        assert_eq!(round(("alice", 3, 5), ("bob", 3, 6)).winner(), Some("bob"));
+        assert_eq!(round(("alice", 4, 5), ("bob", 3, 6)).winner(), Some("alice"));
+        assert_eq!(round(("alice", 3, 5), ("bob", 3, 5)).winner(), None);
+    }
+
+    #[test]
+    fn a_round_is_kept_as_a_line() {
+        let round = round(("alice", 3, 5), ("bob", 7, 9));
+
+        assert_eq!(Round::from_line(&round.to_line()), Some(round));
+        assert_eq!(Round::from_line("12\tclassic/30x15/wrap\talice\t3"), None);
+    }
+
+    #[test]
+    fn a_rivalry_counts_from_both_sides() {
+        let rounds = [
+            round(("alice", 5, 7), ("bob", 2, 4)),
+            round(("bob", 1, 3), ("alice", 3, 5)),
+            round(("alice", 2, 4), ("carol", 9, 11)),
+            round(("bob", 6, 8), ("alice", 6, 8)),
+            round(("alice", 0, 2), ("bob", 4, 6)),
+        ];
+
+        let rivalry = between(&rounds, "alice", "bob");
+        let [alice, bob] = &rivalry.records;
+
+        assert_eq!((rivalry.rounds, rivalry.draws), (4, 1));
+        assert_eq!((alice.wins, alice.best, alice.streak), (2, 6, 2));
+        assert_eq!((bob.wins, bob.best, bob.streak), (1, 6, 1));
+        assert_eq!(alice.average_length(rivalry.rounds), 5.5);
+        assert_eq!(bob.average_length(rivalry.rounds), 5.25);
+
+        // Whoever is asked about first
+        assert_eq!(between(&rounds, "bob", "alice").records[0], *bob);
+    }
+
+    #[test]
+    fn nobody_is_their_own_rival() {
+        let rounds = [round(("alice", 5, 7), ("bob", 2, 4))];
+
+        assert_eq!(between(&rounds, "alice", "alice").rounds, 0);
+        assert_eq!(Record::default().average_length(0), 0.0);
+    }
+
+    #[test]
+    fn every_pair_comes_once() {
+        let rounds = [
+            round(("bob", 1, 3), ("alice", 3, 5)),
+            round(("alice", 5, 7), ("bob", 2, 4)),
+            round(("carol", 2, 4), ("alice", 9, 11)),
+        ];
+
+        assert_eq!(
+            pairs(&rounds),
+            vec![["alice".to_string(), "bob".to_string()], ["alice".to_string(), "carol".to_string()]]
+        );
+    }
+}
@@ -0,0 +1,24 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+/// What the random numbers are for. Every part of the game that needs randomness gets its own
+/// stream, all of them from the seed of the challenge, so that one part drawing more or fewer
+/// numbers doesn't change what the others get.
+///
+/// The food being placed differently would change the whole run: it's what challenges and
+/// replays depend on. So something random added later, like a bot breaking ties or an effect
+/// that's only drawn, gets a variant with the next number, and the food still comes in the same
+/// places. A number is never reused for something else.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stream {
+    /// Where the food goes. This is the stream every game always used, so the food of old
+    /// replays and challenges stays where it was
+    Food = 0,
+}
+
+/// The generator for one stream of a game
+pub fn stream(seed: u64, stream: Stream) -> ChaCha12Rng {
+    let mut rng = ChaCha12Rng::seed_from_u64(seed);
+    rng.set_stream(stream as u64);
+    rng
+}
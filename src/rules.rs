@@ -0,0 +1,139 @@
+use std::fmt::Write;
+
+use crate::boost::{BOOSTED_TICK, TICK};
+use crate::game::{DECAY_TICKS, FOOD_VALUE, HEAVY_CELLS};
+use crate::render::Frame;
+use crate::ruleset::{Mode, Mutator, Ruleset};
+
+/// What the mode is about, in a few words
+fn mode(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Classic => "eat the food, don't run into anything",
+        Mode::Zen => "nothing kills the snake, running into something only stops it until it turns",
+        Mode::Puzzle => "the snake moves a cell per key, eat all the food in as few moves as possible",
+        Mode::Sequence => "eat the numbered pellets in order, one out of order costs a point",
+    }
+}
+
+/// The rules of the game being played, a line each, the way a player would want them
+/// explained. Everything comes from the ruleset, so a new variant can't be played with the
+/// rules of another one on the screen
+pub fn describe(ruleset: &Ruleset) -> Vec<String> {
+    let mut lines = vec![format!("Mode: {}, {}", ruleset.mode.name(), mode(ruleset.mode))];
+
+    let edges = if ruleset.has(Mutator::Wrap) {
+        "the snake goes off one side and comes back on the other"
+    } else {
+        "the edges are walls"
+    };
+    lines.push(format!("Field: {}x{}, {}", ruleset.cols, ruleset.rows, edges));
+
+    if ruleset.has(Mutator::Bounce) {
+        lines.push("Walls: the snake turns clockwise at them, or the other way if that's blocked".to_string());
+    }
+
+    lines.push(match ruleset.mode {
+        Mode::Sequence => "Growth: a cell per pellet eaten in order".to_string(),
+        _ => "Growth: a cell per food".to_string(),
+    });
+
+    lines.push(if ruleset.has(Mutator::Decay) {
+        format!(
+            "Points: {} for fresh food, a point less every {} ticks it waits, down to 1",
+            FOOD_VALUE, DECAY_TICKS
+        )
+    } else {
+        "Points: 1 per food".to_string()
+    });
+
+    lines.push(match ruleset.mode {
+        Mode::Puzzle => "Speed: a tick per key, nothing moves in between".to_string(),
+        _ => format!(
+            "Speed: a tick every {} ms, every {} ms while boosting, for as long as the stamina lasts",
+            TICK.as_millis(),
+            BOOSTED_TICK.as_millis()
+        ),
+    });
+
+    if ruleset.has(Mutator::Heavy) {
+        lines.push(format!(
+            "Weight: {} ms slower a tick for every cell, up to {} ms, and food is worth a point more for every {} cells",
+            ruleset.weight.slowdown.as_millis(),
+            ruleset.weight.slowest.as_millis(),
+            HEAVY_CELLS
+        ));
+    }
+
+    if ruleset.has(Mutator::Assist) {
+        lines.push("Assist: the snake turns by itself when going on would kill it".to_string());
+    } else if ruleset.has(Mutator::Hint) {
+        lines.push("Hint: the way out is highlighted when going on would kill the snake".to_string());
+    }
+
+    if ruleset.grace > 0 {
+        lines.push(format!("Grace: {} ticks against the body before it kills", ruleset.grace));
+    }
+
+    let ranked = if ruleset.ranked() {
+        format!("kept for {}", ruleset)
+    } else if ruleset.assisted() {
+        "none with help".to_string()
+    } else {
+        format!("none in {} mode", ruleset.mode.name())
+    };
+    lines.push(format!("High scores: {}", ranked));
+
+    lines
+}
+
+/// Draw the rules instead of the field
+pub fn draw(frame: &mut Frame, ruleset: &Ruleset, back: &str) {
+    writeln!(frame, "Rules").unwrap();
+    writeln!(frame).unwrap();
+
+    for line in describe(ruleset) {
+        writeln!(frame, "  {}", line).unwrap();
+    }
+
+    writeln!(frame).unwrap();
+    writeln!(frame, "{} to go back", back).unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_rules_screen_follows_the_ruleset() {
+        let rules = describe(&Ruleset::default());
+        assert!(rules.iter().any(|line| line.starts_with("Field: 30x15, the snake goes off one side")));
+        assert!(rules.iter().any(|line| line == "Points: 1 per food"));
+        assert!(!rules.iter().any(|line| line.starts_with("Grace") || line.starts_with("Walls")));
+
+        let ruleset = Ruleset {
+            mode: Mode::Zen,
+            mutators: vec![Mutator::Bounce, Mutator::Decay],
+            grace: 2,
+            ..Ruleset::default()
+        };
+        let rules = describe(&ruleset);
+        assert!(rules.iter().any(|line| line.starts_with("Field: 30x15, the edges are walls")));
+        assert!(rules.iter().any(|line| line.starts_with("Walls: the snake turns clockwise")));
+        assert!(rules.iter().any(|line| line.starts_with(&format!("Points: {} for fresh food", FOOD_VALUE))));
+        assert!(rules.iter().any(|line| line == "Grace: 2 ticks against the body before it kills"));
+        assert!(rules.iter().any(|line| line == "High scores: none in zen mode"));
+    }
+
+    #[test]
+    fn help_and_weight_are_explained() {
+        let ruleset = Ruleset {
+            mutators: vec![Mutator::Assist, Mutator::Heavy],
+            ..Ruleset::default()
+        };
+        let rules = describe(&ruleset);
+
+        assert!(rules.iter().any(|line| line.starts_with("Assist: ")));
+        assert!(rules.iter().any(|line| line.starts_with("Weight: 4 ms slower a tick for every cell, up to 300 ms")));
+        assert!(rules.iter().any(|line| line == "High scores: none with help"));
+    }
+}
@@ -0,0 +1,399 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::boost::Weight;
+
+/// The game mode. The scoreboard keeps the mode around so that the scores of different modes
+/// don't end up mixed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Classic,
+    /// Nothing can kill the snake: running into a wall or into itself just stops it until it
+    /// turns. Made for kids and for relaxing, so it has no high scores
+    Zen,
+    /// Handcrafted levels where the snake only moves when a key is pressed. There's no new
+    /// food once the food of the level is eaten, the goal is to eat it all in as few moves as
+    /// possible
+    Puzzle,
+    /// The food comes in rounds of numbered pellets that have to be eaten in order. A pellet
+    /// eaten out of order costs a point instead of giving one
+    Sequence,
+}
+
+/// Small tweaks on top of a mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mutator {
+    /// The snake leaves the field on one side and comes back on the other
+    Wrap,
+    /// Food is worth more the sooner it's eaten, down to a single point once it's been lying
+    /// there for a while
+    Decay,
+    /// Walls and edges don't kill the snake, they turn it clockwise, or the other way if
+    /// that's blocked too. Only a snake boxed in on all sides dies against a wall
+    Bounce,
+    /// When going on would kill the snake on the next tick, the way out is highlighted. There
+    /// are no high scores with it
+    Hint,
+    /// When going on would kill the snake on the next tick, it takes the way out by itself. No
+    /// high scores either
+    Assist,
+    /// The longer the snake, the slower it goes, but the more its food is worth. How much
+    /// slower is the weight of the ruleset
+    Heavy,
+}
+
+/// The smallest number of rows or columns a field can have. The snake starts two cells long, in
+/// the middle of a row, and there has to be room left for the food
+pub const MIN_SIZE: usize = 3;
+/// The biggest number of rows or columns a field can have. Nobody has a terminal this big, the
+/// limit is there so a mangled file can't make the game allocate gigabytes
+pub const MAX_SIZE: usize = 1000;
+/// The most grace ticks a ruleset can give. Any more and running into the body would hardly
+/// matter anymore
+pub const MAX_GRACE: u32 = 10;
+
+/// Everything that changes how a game plays. Two scores are only comparable if they were
+/// made with the same ruleset.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "RawRuleset")]
+pub struct Ruleset {
+    pub mode: Mode,
+    pub rows: usize,
+    pub cols: usize,
+    pub mutators: Vec<Mutator>,
+    /// How many ticks the snake waits with its head against its own body before that kills it,
+    /// so a turn that came a little too late still saves it. Walls and edges are never
+    /// forgiven
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub grace: u32,
+    /// How much slower the snake gets as it grows, with the heavy mutator. Without it, this is
+    /// always the default
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub weight: Weight,
+}
+
+/// A ruleset as it comes out of a file, before its size is checked
+#[derive(Deserialize)]
+struct RawRuleset {
+    mode: Mode,
+    rows: usize,
+    cols: usize,
+    mutators: Vec<Mutator>,
+    #[serde(default)]
+    grace: u32,
+    #[serde(default)]
+    weight: Weight,
+}
+
+pub(crate) fn is_zero<T: Default + PartialEq>(n: &T) -> bool {
+    *n == T::default()
+}
+
+impl TryFrom<RawRuleset> for Ruleset {
+    type Error = String;
+
+    fn try_from(raw: RawRuleset) -> Result<Ruleset, String> {
+        if !valid_size(raw.cols) || !valid_size(raw.rows) {
+            return Err(format!("can't play on a {}x{} field", raw.cols, raw.rows));
+        }
+
+        if raw.grace > MAX_GRACE {
+            return Err(format!("at most {} grace ticks, not {}", MAX_GRACE, raw.grace));
+        }
+
+        if !raw.weight.valid() || (raw.weight != Weight::default() && !raw.mutators.contains(&Mutator::Heavy)) {
+            return Err("the snake can't be that heavy".to_string());
+        }
+
+        Ok(Ruleset {
+            mode: raw.mode,
+            rows: raw.rows,
+            cols: raw.cols,
+            mutators: raw.mutators,
+            grace: raw.grace,
+            weight: raw.weight,
+        })
+    }
+}
+
+impl Mode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Zen => "zen",
+            Mode::Puzzle => "puzzle",
+            Mode::Sequence => "sequence",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "classic" => Some(Mode::Classic),
+            "zen" => Some(Mode::Zen),
+            "puzzle" => Some(Mode::Puzzle),
+            "sequence" => Some(Mode::Sequence),
+            _ => None,
+        }
+    }
+
+    /// Whether the scores made in this mode go on the scoreboard
+    pub fn ranked(&self) -> bool {
+        match self {
+            Mode::Classic | Mode::Sequence => true,
+            // Puzzles keep their own records, per level
+            Mode::Zen | Mode::Puzzle => false,
+        }
+    }
+}
+
+impl Mutator {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mutator::Wrap => "wrap",
+            Mutator::Decay => "decay",
+            Mutator::Bounce => "bounce",
+            Mutator::Hint => "hint",
+            Mutator::Assist => "assist",
+            Mutator::Heavy => "heavy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Mutator> {
+        match name {
+            "wrap" => Some(Mutator::Wrap),
+            "decay" => Some(Mutator::Decay),
+            "bounce" => Some(Mutator::Bounce),
+            "hint" => Some(Mutator::Hint),
+            "assist" => Some(Mutator::Assist),
+            "heavy" => Some(Mutator::Heavy),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Ruleset {
+    fn default() -> Self {
+        Ruleset {
+            mode: Mode::Classic,
+            rows: 15,
+            cols: 30,
+            mutators: vec![Mutator::Wrap],
+            grace: 0,
+            weight: Weight::default(),
+        }
+    }
+}
+
+impl Ruleset {
+    pub fn has(&self, mutator: Mutator) -> bool {
+        self.mutators.contains(&mutator)
+    }
+
+    /// Whether the player gets help avoiding death
+    pub fn assisted(&self) -> bool {
+        self.has(Mutator::Hint) || self.has(Mutator::Assist)
+    }
+
+    /// Whether the scores made with this ruleset go on the scoreboard: the mode decides, but a
+    /// run with help never does
+    pub fn ranked(&self) -> bool {
+        self.mode.ranked() && !self.assisted()
+    }
+
+    /// A string that uniquely describes the ruleset, e.g. `classic/30x15/wrap`, or
+    /// `classic/30x15/wrap/grace3` when the snake gets grace ticks, and
+    /// `classic/30x15/heavy/weight8-500` when a heavy snake slows down by another curve than
+    /// the default one (8 ms a cell, up to 500 ms a tick).
+    ///
+    /// This is what gets stored next to every high score. Mutators are sorted so the order
+    /// they were enabled in doesn't matter.
+    pub fn fingerprint(&self) -> String {
+        let mut mutators: Vec<&str> = self.mutators.iter().map(|m| m.name()).collect();
+        mutators.sort();
+        mutators.dedup();
+
+        let mutators = if mutators.is_empty() {
+            "-".to_string()
+        } else {
+            mutators.join("+")
+        };
+
+        let mut fingerprint = format!("{}/{}x{}/{}", self.mode.name(), self.cols, self.rows, mutators);
+
+        // Rulesets without grace ticks keep the fingerprint they always had
+        if self.grace > 0 {
+            fingerprint.push_str(&format!("/grace{}", self.grace));
+        }
+
+        // The same goes for heavy snakes with the default weight
+        if self.has(Mutator::Heavy) && self.weight != Weight::default() {
+            fingerprint.push_str(&format!(
+                "/weight{}-{}",
+                self.weight.slowdown.as_millis(),
+                self.weight.slowest.as_millis()
+            ));
+        }
+
+        fingerprint
+    }
+
+    /// A short hash of the fingerprint, handy to tell at a glance whether two runs were played
+    /// with the same rules.
+    ///
+    /// This is FNV-1a. The std `DefaultHasher` is not guaranteed to stay the same between Rust
+    /// versions, and this hash ends up in files that are shared between players.
+    pub fn hash(&self) -> u32 {
+        let mut hash: u32 = 0x811c9dc5;
+
+        for byte in self.fingerprint().bytes() {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+
+        hash
+    }
+
+    /// The reverse of `fingerprint`. Returns `None` if the string is not a valid fingerprint
+    pub fn from_fingerprint(s: &str) -> Option<Ruleset> {
+        let mut parts = s.split('/');
+
+        let mode = Mode::from_name(parts.next()?)?;
+        let (cols, rows) = parse_size(parts.next()?)?;
+
+        let mut mutators = Vec::new();
+        match parts.next()? {
+            "-" => {},
+            list => {
+                for name in list.split('+') {
+                    mutators.push(Mutator::from_name(name)?);
+                }
+            }
+        }
+
+        let mut part = parts.next();
+
+        let grace = match part.and_then(|p| p.strip_prefix("grace")) {
+            Some(n) => {
+                part = parts.next();
+                n.parse().ok().filter(|n| (1..=MAX_GRACE).contains(n))?
+            },
+            None => 0,
+        };
+
+        let weight = match part {
+            Some(p) => {
+                part = parts.next();
+
+                let (slowdown, slowest) = p.strip_prefix("weight")?.split_once('-')?;
+                let weight = Weight {
+                    slowdown: Duration::from_millis(slowdown.parse().ok()?),
+                    slowest: Duration::from_millis(slowest.parse().ok()?),
+                };
+
+                // Only heavy snakes have a weight, and the default one isn't written out
+                if !mutators.contains(&Mutator::Heavy) || !weight.valid() || weight == Weight::default() {
+                    return None;
+                }
+
+                weight
+            },
+            None => Weight::default(),
+        };
+
+        if part.is_some() {
+            return None;
+        }
+
+        Some(Ruleset {
+            mode,
+            rows,
+            cols,
+            mutators,
+            grace,
+            weight,
+        })
+    }
+}
+
+impl fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.fingerprint())
+    }
+}
+
+fn valid_size(n: usize) -> bool {
+    (MIN_SIZE..=MAX_SIZE).contains(&n)
+}
+
+/// Parse a board size in the `COLSxROWS` form, e.g. `30x15`. Both have to be between
+/// `MIN_SIZE` and `MAX_SIZE`
+pub fn parse_size(s: &str) -> Option<(usize, usize)> {
+    let (cols, rows) = s.split_once('x')?;
+    let cols = cols.parse().ok()?;
+    let rows = rows.parse().ok()?;
+
+    if !valid_size(cols) || !valid_size(rows) {
+        return None;
+    }
+
+    Some((cols, rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heavy() -> Ruleset {
+        Ruleset {
+            mutators: vec![Mutator::Heavy],
+            ..Ruleset::default()
+        }
+    }
+
+    #[test]
+    fn the_default_weight_keeps_the_fingerprint() {
+        assert_eq!(heavy().fingerprint(), "classic/30x15/heavy");
+    }
+
+    #[test]
+    fn another_weight_is_another_ruleset() {
+        let light = Ruleset {
+            weight: Weight {
+                slowdown: Duration::ZERO,
+                ..Weight::default()
+            },
+            ..heavy()
+        };
+
+        assert_eq!(light.fingerprint(), "classic/30x15/heavy/weight0-300");
+        assert_ne!(light.hash(), heavy().hash());
+        assert_eq!(Ruleset::from_fingerprint(&light.fingerprint()), Some(light));
+    }
+
+    #[test]
+    fn only_a_heavy_snake_has_a_weight() {
+        assert_eq!(Ruleset::from_fingerprint("classic/30x15/wrap/weight0-300"), None);
+        assert_eq!(Ruleset::from_fingerprint("classic/30x15/heavy/weight4-300"), None);
+        assert_eq!(Ruleset::from_fingerprint("classic/30x15/heavy/weight500-300"), None);
+        assert!(Ruleset::from_fingerprint("classic/30x15/heavy/grace2/weight8-500").is_some());
+    }
+
+    #[test]
+    fn help_keeps_a_run_off_the_scoreboard() {
+        for mutator in [Mutator::Hint, Mutator::Assist] {
+            let ruleset = Ruleset {
+                mutators: vec![Mutator::Wrap, mutator],
+                ..Ruleset::default()
+            };
+
+            assert!(ruleset.assisted());
+            assert!(!ruleset.ranked());
+        }
+
+        assert!(Ruleset::default().ranked());
+    }
+}
@@ -0,0 +1,282 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+use console::Term;
+
+use crate::boost;
+use crate::game::{Direction, Direction::*, Game, Polarity::*};
+use crate::level::Level;
+use crate::render::{Frame, Scale};
+use crate::ruleset::{Mode, Mutator, MAX_GRACE};
+
+/// What has to hold once the inputs of a scenario are played
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Expect {
+    Score(u32),
+    Length(usize),
+    /// The snake is still alive, or it died
+    Alive(bool),
+}
+
+/// A situation set up by hand and what the snake does in it, so a tricky one (chasing its
+/// own tail, eating across the edge, a full field) can be played again exactly, by the tests
+/// or on the screen.
+///
+/// Scenarios are text files, a lot like levels:
+///
+/// ```text
+/// snek-scenario 1
+/// name Eating across the edge
+/// mode classic
+/// mutators wrap
+/// seed 7
+/// inputs 2> ^ .
+/// expect score 1
+/// expect length 3
+/// expect alive
+/// map
+/// ......
+/// ..o>..
+/// $.....
+/// ```
+///
+/// `name` is required, `mode` is `classic` without it and the `seed` (for the food that comes
+/// after the food on the map) is 0. `mutators` and `grace` are the same as in a ruleset. The
+/// `inputs` are played one per tick: `^`, `v`, `<` and `>` turn the snake, `.` lets it go
+/// straight on, and a number in front plays the input that many times. There can be several
+/// `inputs` lines, they're played one after the other. Then come the checks, `expect score
+/// N`, `expect length N`, and `expect alive` or `expect dead`. The map is the same as the map
+/// of a level.
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub name: String,
+    pub mode: Mode,
+    pub grace: u32,
+    pub seed: u64,
+    /// The field, the snake and the food, with the mutators
+    pub level: Level,
+    /// The direction pressed on every tick, `None` for going straight on
+    pub inputs: Vec<Option<Direction>>,
+    pub expect: Vec<Expect>,
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+/// One input of an `inputs` line, a number of times
+fn parse_input(token: &str) -> Option<(usize, Option<Direction>)> {
+    let split = token.find(|ch: char| !ch.is_ascii_digit())?;
+    let (count, input) = token.split_at(split);
+
+    let count = match count {
+        "" => 1,
+        count => count.parse().ok().filter(|n| *n > 0)?,
+    };
+
+    let input = match input {
+        "." => None,
+        "^" => Some(Ver(Neg)),
+        "v" => Some(Ver(Pos)),
+        "<" => Some(Hor(Neg)),
+        ">" => Some(Hor(Pos)),
+        _ => return None,
+    };
+
+    Some((count, input))
+}
+
+impl Scenario {
+    pub fn parse(text: &str) -> io::Result<Scenario> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        if lines.next().map(|(_, line)| line) != Some("snek-scenario 1") {
+            return Err(invalid(1, "not a scenario file"));
+        }
+
+        let mut name = None;
+        let mut mode = Mode::Classic;
+        let mut mutators = Vec::new();
+        let mut grace = 0;
+        let mut seed = 0;
+        let mut inputs = Vec::new();
+        let mut expect = Vec::new();
+
+        loop {
+            let (n, line) = lines.next().ok_or_else(|| invalid(1, "missing the map"))?;
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if line == "map" {
+                break;
+            }
+
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            let value = value.trim();
+
+            match key {
+                "name" if !value.is_empty() => name = Some(value.to_string()),
+                "mode" => mode = Mode::from_name(value).ok_or_else(|| invalid(n, "unknown mode"))?,
+                "mutators" if value == "-" => mutators.clear(),
+                "mutators" => {
+                    mutators = value
+                        .split('+')
+                        .map(Mutator::from_name)
+                        .collect::<Option<Vec<Mutator>>>()
+                        .ok_or_else(|| invalid(n, "unknown mutator"))?;
+                },
+                "grace" => {
+                    grace = value
+                        .parse()
+                        .ok()
+                        .filter(|grace| *grace <= MAX_GRACE)
+                        .ok_or_else(|| invalid(n, &format!("expected at most {} grace ticks", MAX_GRACE)))?;
+                },
+                "seed" => seed = value.parse().map_err(|_| invalid(n, "expected a number"))?,
+                "inputs" => {
+                    for token in value.split_whitespace() {
+                        let (count, input) = parse_input(token).ok_or_else(|| invalid(n, &format!("unknown input {:?}", token)))?;
+                        inputs.extend(std::iter::repeat_n(input, count));
+                    }
+                },
+                "expect" => {
+                    let (what, value) = value.split_once(' ').unwrap_or((value, ""));
+                    let number = || value.trim().parse().map_err(|_| invalid(n, "expected a number"));
+
+                    expect.push(match what {
+                        "score" => Expect::Score(number()?),
+                        "length" => Expect::Length(number()? as usize),
+                        "alive" => Expect::Alive(true),
+                        "dead" => Expect::Alive(false),
+                        _ => return Err(invalid(n, "expected score, length, alive or dead")),
+                    });
+                },
+                _ => return Err(invalid(n, "expected name, mode, mutators, grace, seed, inputs, expect or map")),
+            }
+        }
+
+        let name = name.ok_or_else(|| invalid(1, "the scenario has no name"))?;
+
+        let level = Level {
+            name: name.clone(),
+            mutators,
+            ..Level::parse_map(lines)?
+        };
+
+        Ok(Scenario {
+            name,
+            mode,
+            grace,
+            seed,
+            level,
+            inputs,
+            expect,
+        })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Scenario> {
+        Scenario::parse(&fs::read_to_string(path)?)
+    }
+
+    /// The game as it is before the first input
+    pub fn start(&self) -> Game {
+        let mut game = Game::from_level(&self.level, self.mode, self.seed);
+        game.ruleset.grace = self.grace;
+        game
+    }
+
+    /// Play an input. Going straight on is going the way the snake goes
+    pub fn step(game: &mut Game, input: Option<Direction>) {
+        game.tick(input.unwrap_or(game.dir));
+    }
+
+    /// Play all the inputs, without drawing anything. The inputs left once the snake is dead
+    /// don't do anything
+    pub fn run(&self) -> Game {
+        let mut game = self.start();
+
+        for input in self.inputs.iter() {
+            Scenario::step(&mut game, *input);
+        }
+
+        game
+    }
+
+    /// What the game doesn't do the way the scenario expects
+    pub fn check(&self, game: &Game) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        for expect in self.expect.iter() {
+            match *expect {
+                Expect::Score(score) if game.score != score => {
+                    problems.push(format!("expected a score of {}, got {}", score, game.score));
+                },
+                Expect::Length(length) if game.length != length => {
+                    problems.push(format!("expected a length of {}, got {}", length, game.length));
+                },
+                Expect::Alive(true) if game.over => problems.push(format!("expected the snake alive, it died on tick {}", game.ticks)),
+                Expect::Alive(false) if !game.over => problems.push("expected the snake dead, it's alive".to_string()),
+                _ => {},
+            }
+        }
+
+        problems
+    }
+
+    /// Play the inputs on the screen, a tick at the speed of the game, and leave the last
+    /// frame there
+    pub fn watch(&self, term: &Term) -> io::Result<Game> {
+        let mut frame = Frame::default();
+        let mut stdout = io::stdout();
+
+        let mut game = self.start();
+        let mut inputs = self.inputs.iter();
+
+        loop {
+            match Scale::pick(term, &game, 2) {
+                Ok(scale) => {
+                    frame.begin();
+                    frame.field(&game, scale);
+                    writeln!(frame, "{}  tick {}/{}", self.name, game.ticks, self.inputs.len()).unwrap();
+                    writeln!(frame, "score {}  length {}", game.score, game.length).unwrap();
+                },
+                Err(size) => frame.too_small(size),
+            }
+
+            frame.present(&mut stdout)?;
+
+            let Some(input) = inputs.next().filter(|_| !game.over) else {
+                return Ok(game);
+            };
+
+            thread::sleep(boost::TICK);
+            Scenario::step(&mut game, *input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scenarios_end_the_way_they_say() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let mut played = 0;
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let scenario = Scenario::load(&path).unwrap();
+
+            assert_eq!(scenario.check(&scenario.run()), Vec::<String>::new(), "{}", path.display());
+            played += 1;
+        }
+
+        assert!(played > 0);
+    }
+}
@@ -0,0 +1,236 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::profile;
+use crate::ruleset::{Mode, Mutator, Ruleset};
+
+const SCORES_FILE: &str = "scores.tsv";
+
+/// One line of the high score file
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub score: u32,
+    pub length: usize,
+    /// The fingerprint of the ruleset the score was made with
+    pub ruleset: String,
+    /// Seconds since the unix epoch
+    pub time: u64,
+}
+
+/// What to show on the high score screen. `None` (or an empty list) means "anything"
+#[derive(Default)]
+pub struct Filter {
+    pub mode: Option<Mode>,
+    pub size: Option<(usize, usize)>,
+    pub mutators: Vec<Mutator>,
+}
+
+/// The directory where the game keeps its files: `~/.snek`, or the current directory if
+/// there is no home. The files of the profiles are in subdirectories of it
+pub fn base_dir() -> PathBuf {
+    match env::var_os("HOME") {
+        Some(home) => PathBuf::from(home).join(".snek"),
+        None => PathBuf::from("."),
+    }
+}
+
+/// The directory of the profile in use, where the scores, replays and everything else are
+pub fn data_dir() -> PathBuf {
+    profile::dir(profile::current().as_deref())
+}
+
+impl Entry {
+    pub fn new(score: u32, length: usize, ruleset: &Ruleset) -> Entry {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Entry {
+            score,
+            length,
+            ruleset: ruleset.fingerprint(),
+            time,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}", self.score, self.length, self.ruleset, self.time)
+    }
+
+    fn from_line(line: &str) -> Option<Entry> {
+        let mut parts = line.split('\t');
+
+        let entry = Entry {
+            score: parts.next()?.parse().ok()?,
+            length: parts.next()?.parse().ok()?,
+            ruleset: parts.next()?.to_string(),
+            time: parts.next()?.parse().ok()?,
+        };
+
+        Some(entry)
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, entry: &Entry) -> bool {
+        // Scores from older versions may have a fingerprint we don't understand anymore.
+        // They are only shown if there is no filter at all.
+        let ruleset = match Ruleset::from_fingerprint(&entry.ruleset) {
+            Some(ruleset) => ruleset,
+            None => return self.mode.is_none() && self.size.is_none() && self.mutators.is_empty(),
+        };
+
+        if let Some(mode) = self.mode {
+            if ruleset.mode != mode {
+                return false;
+            }
+        }
+
+        if let Some((cols, rows)) = self.size {
+            if ruleset.cols != cols || ruleset.rows != rows {
+                return false;
+            }
+        }
+
+        self.mutators.iter().all(|m| ruleset.has(*m))
+    }
+}
+
+/// Load all the high scores of the profile in use
+pub fn load() -> io::Result<Vec<Entry>> {
+    load_from(&data_dir())
+}
+
+/// Load all the high scores kept in a directory. A missing file just means there are no
+/// scores yet
+pub fn load_from(dir: &Path) -> io::Result<Vec<Entry>> {
+    let content = match fs::read_to_string(dir.join(SCORES_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    Ok(content.lines().filter_map(Entry::from_line).collect())
+}
+
+/// Append a new entry to the high score file
+pub fn save(entry: &Entry) -> io::Result<()> {
+    let dir = data_dir();
+    fs::create_dir_all(&dir)?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(SCORES_FILE))?;
+
+    writeln!(file, "{}", entry.to_line())
+}
+
+/// The best scores made with exactly this ruleset, best first
+pub fn top(entries: &[Entry], ruleset: &Ruleset, n: usize) -> Vec<Entry> {
+    let fingerprint = ruleset.fingerprint();
+
+    let mut top: Vec<Entry> = entries
+        .iter()
+        .filter(|e| e.ruleset == fingerprint)
+        .cloned()
+        .collect();
+
+    top.sort_by(|a, b| b.score.cmp(&a.score).then(a.time.cmp(&b.time)));
+    top.truncate(n);
+    top
+}
+
+/// Group the entries matching the filter by their ruleset. Each group is sorted best first
+/// and the groups are sorted by their fingerprint so the output is stable
+pub fn grouped(entries: &[Entry], filter: &Filter) -> Vec<(String, Vec<Entry>)> {
+    let mut groups: Vec<(String, Vec<Entry>)> = Vec::new();
+
+    for entry in entries.iter().filter(|e| filter.matches(e)) {
+        match groups.iter_mut().find(|(ruleset, _)| *ruleset == entry.ruleset) {
+            Some((_, group)) => group.push(entry.clone()),
+            None => groups.push((entry.ruleset.clone(), vec![entry.clone()])),
+        }
+    }
+
+    for (_, group) in groups.iter_mut() {
+        group.sort_by(|a, b| b.score.cmp(&a.score).then(a.time.cmp(&b.time)));
+    }
+
+    groups.sort_by(|a, b| a.0.cmp(&b.0));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(score: u32, ruleset: &str, time: u64) -> Entry {
+        Entry {
+            score,
+            length: 2,
+            ruleset: ruleset.to_string(),
+            time,
+        }
+    }
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            entry(5, "classic/30x15/wrap", 1),
+            entry(9, "zen/30x15/wrap", 2),
+            entry(7, "classic/30x15/wrap", 3),
+            entry(7, "classic/20x10/decay+wrap", 4),
+            entry(3, "somethingelse", 5),
+        ]
+    }
+
+    #[test]
+    fn an_entry_is_kept_as_a_line() {
+        let line = entry(7, "classic/20x10/decay+wrap", 4).to_line();
+        let back = Entry::from_line(&line).unwrap();
+
+        assert_eq!((back.score, back.length, back.ruleset.as_str(), back.time), (7, 2, "classic/20x10/decay+wrap", 4));
+        assert!(Entry::from_line("7\ttwo\tclassic/30x15/wrap\t4").is_none());
+    }
+
+    #[test]
+    fn the_top_is_of_the_same_ruleset_only() {
+        let top = top(&entries(), &Ruleset::default(), 10);
+
+        assert_eq!(top.iter().map(|e| (e.score, e.time)).collect::<Vec<_>>(), [(7, 3), (5, 1)]);
+    }
+
+    #[test]
+    fn scores_are_grouped_by_ruleset() {
+        let groups = grouped(&entries(), &Filter::default());
+        let names: Vec<&str> = groups.iter().map(|(ruleset, _)| ruleset.as_str()).collect();
+
+        assert_eq!(names, ["classic/20x10/decay+wrap", "classic/30x15/wrap", "somethingelse", "zen/30x15/wrap"]);
+        assert_eq!(groups[1].1.iter().map(|e| e.score).collect::<Vec<_>>(), [7, 5]);
+    }
+
+    #[test]
+    fn a_filter_leaves_out_the_other_rulesets() {
+        let shown = |filter: Filter| -> Vec<u64> {
+            let mut times: Vec<u64> = entries().iter().filter(|e| filter.matches(e)).map(|e| e.time).collect();
+            times.sort();
+            times
+        };
+
+        assert_eq!(shown(Filter::default()), [1, 2, 3, 4, 5]);
+        assert_eq!(shown(Filter { mode: Some(Mode::Classic), ..Filter::default() }), [1, 3, 4]);
+        assert_eq!(shown(Filter { size: Some((20, 10)), ..Filter::default() }), [4]);
+        assert_eq!(shown(Filter { mutators: vec![Mutator::Decay], ..Filter::default() }), [4]);
+
+        let none = Filter {
+            mode: Some(Mode::Zen),
+            mutators: vec![Mutator::Decay],
+            ..Filter::default()
+        };
+        assert!(shown(none).is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use crate::challenge;
+use crate::game::{Event, Game};
+use crate::ruleset::Mode;
+
+/// How the last game of a session ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub score: u32,
+    pub length: usize,
+    /// Whether the game was played to the game over, rather than left
+    pub over: bool,
+}
+
+/// What was played since the game was started, kept in memory only. It's printed once the
+/// terminal is back to normal, on the way out
+pub struct Session {
+    started: Instant,
+    pub games: u32,
+    pub best: Option<u32>,
+    pub foods: u32,
+    /// The ticks played in every mode, in the order the modes were first played
+    pub ticks: Vec<(Mode, u64)>,
+    pub last: Option<Outcome>,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session {
+            started: Instant::now(),
+            games: 0,
+            best: None,
+            foods: 0,
+            ticks: Vec::new(),
+            last: None,
+        }
+    }
+}
+
+impl Session {
+    /// Count a tick of a game, with what happened in it
+    pub fn tick(&mut self, game: &Game, events: &[Event]) {
+        let mode = game.ruleset.mode;
+
+        match self.ticks.iter_mut().find(|(played, _)| *played == mode) {
+            Some((_, ticks)) => *ticks += 1,
+            None => self.ticks.push((mode, 1)),
+        }
+
+        self.foods += events.iter().filter(|event| matches!(event, Event::Ate)).count() as u32;
+    }
+
+    /// Count a game once it's over, or left for another one. A game that never got a tick
+    /// wasn't played
+    pub fn finish(&mut self, game: &Game) {
+        if game.ticks == 0 {
+            return;
+        }
+
+        self.games += 1;
+        self.best = Some(self.best.map_or(game.score, |best| best.max(game.score)));
+        self.last = Some(Outcome {
+            score: game.score,
+            length: game.length,
+            over: game.over,
+        });
+    }
+
+    /// Whether any game got a tick, there's nothing to sum up otherwise
+    pub fn played(&self) -> bool {
+        !self.ticks.is_empty()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// A line or two about the session
+    pub fn summary(&self) -> Vec<String> {
+        let games = if self.games == 1 { "1 game".to_string() } else { format!("{} games", self.games) };
+
+        let mut line = format!("Played {}: {}", challenge::format_duration(self.elapsed()), games);
+        if let Some(best) = self.best {
+            line.push_str(&format!(", best score {}", best));
+        }
+        line.push_str(&format!(", {} food eaten", self.foods));
+
+        let mut lines = vec![line];
+
+        if !self.ticks.is_empty() {
+            let modes: Vec<String> = self.ticks.iter().map(|(mode, ticks)| format!("{} {}", mode.name(), ticks)).collect();
+            lines.push(format!("Ticks: {}", modes.join(", ")));
+        }
+
+        lines
+    }
+
+    pub fn print(&self) {
+        for line in self.summary() {
+            println!("{}", line);
+        }
+    }
+}
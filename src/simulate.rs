@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+use rayon::prelude::*;
+
+use crate::bot;
+use crate::challenge::Challenge;
+use crate::game::Game;
+use crate::ruleset::Ruleset;
+
+/// How a batch of headless games is played
+pub struct Options {
+    pub games: u64,
+    /// 0 lets rayon pick one thread per CPU
+    pub threads: usize,
+    pub seed: u64,
+    /// A bot that never dies would keep a game going forever, so every game is cut at this
+    /// many ticks
+    pub max_ticks: u64,
+}
+
+/// The aggregated results of a batch of games
+#[derive(Default, Clone, Copy)]
+pub struct Stats {
+    pub games: u64,
+    pub deaths: u64,
+    pub ticks: u64,
+    pub total_score: u64,
+    pub best_score: u32,
+    pub worst_score: u32,
+    pub total_length: u64,
+}
+
+impl Stats {
+    fn single(game: &Game) -> Stats {
+        Stats {
+            games: 1,
+            deaths: game.over as u64,
+            ticks: game.ticks,
+            total_score: game.score as u64,
+            best_score: game.score,
+            worst_score: game.score,
+            total_length: game.length as u64,
+        }
+    }
+
+    fn merge(self, other: Stats) -> Stats {
+        if self.games == 0 {
+            return other;
+        }
+
+        if other.games == 0 {
+            return self;
+        }
+
+        Stats {
+            games: self.games + other.games,
+            deaths: self.deaths + other.deaths,
+            ticks: self.ticks + other.ticks,
+            total_score: self.total_score + other.total_score,
+            best_score: self.best_score.max(other.best_score),
+            worst_score: self.worst_score.min(other.worst_score),
+            total_length: self.total_length + other.total_length,
+        }
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        self.total_score as f64 / self.games.max(1) as f64
+    }
+
+    pub fn mean_length(&self) -> f64 {
+        self.total_length as f64 / self.games.max(1) as f64
+    }
+
+    pub fn mean_ticks(&self) -> f64 {
+        self.ticks as f64 / self.games.max(1) as f64
+    }
+}
+
+/// The seed of the `index`-th game of a batch.
+///
+/// Every game gets its own stream of random numbers which only depends on the seed of the
+/// batch and the index of the game, never on the thread it ran on. This is splitmix64, it
+/// makes sure neighbouring indices don't end up with similar seeds.
+pub fn game_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn play_one(ruleset: &Ruleset, seed: u64, max_ticks: u64) -> Game {
+    let challenge = Challenge {
+        ruleset: ruleset.clone(),
+        seed,
+    };
+
+    let mut game = Game::new(&challenge);
+
+    while !game.over && game.ticks < max_ticks {
+        let dir = bot::greedy(&game);
+        game.tick(dir);
+    }
+
+    game
+}
+
+/// Play all the games of the batch on a thread pool. Returns the stats and how long it took
+pub fn run(ruleset: &Ruleset, options: &Options) -> (Stats, Duration) {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads)
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+
+    let stats = pool.install(|| {
+        (0..options.games)
+            .into_par_iter()
+            .map(|i| {
+                let game = play_one(ruleset, game_seed(options.seed, i), options.max_ticks);
+                Stats::single(&game)
+            })
+            .reduce(Stats::default, Stats::merge)
+    });
+
+    (stats, started.elapsed())
+}
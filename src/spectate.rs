@@ -0,0 +1,336 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use console::Key::*;
+use console::Term;
+
+use crate::codec;
+use crate::error::{self, Error};
+use crate::game::{Direction, Game};
+use crate::render::{self, Frame, Scale};
+use crate::scores::base_dir;
+use crate::subtitles;
+use crate::title;
+
+const SOCKET_FILE: &str = "spectate.sock";
+
+/// A spectator that doesn't take a state for this long is dropped, so a stuck terminal on the
+/// other side can't slow the game down
+const WRITE_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// Where a running game can be watched from. It's the same for all the profiles, whoever is
+/// playing can be watched
+pub fn socket_path() -> PathBuf {
+    base_dir().join(SOCKET_FILE)
+}
+
+/// A running game, sent to everyone watching it.
+///
+/// Spectators connect to a Unix socket in the data directory. One that just came gets the
+/// whole game, encoded with `codec` on one line. After that, every tick only sends the
+/// direction it was played with, and the spectator plays it on its own copy: the game comes
+/// out the same from the same directions. The console can change the game between ticks, the
+/// spectators get the whole of it again then.
+///
+/// Every line is what it is (`game` or `tick`), the state hash of the game once it's read,
+/// and the data, with spaces in between. So the spectator can tell when its copy isn't the
+/// game that was sent.
+pub struct Broadcast {
+    path: PathBuf,
+    listener: UnixListener,
+    spectators: Vec<UnixStream>,
+    /// The game as the spectators have it, as long as there are some
+    copy: Option<Game>,
+    line: String,
+}
+
+impl Broadcast {
+    /// Start listening for spectators. Fails if another game is already being broadcast
+    pub fn start(path: &Path) -> io::Result<Broadcast> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        // A socket file nobody listens on is left over from a game that didn't end well
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(ErrorKind::AddrInUse, "another game is already being broadcast"));
+            }
+
+            fs::remove_file(path)?;
+        }
+
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+
+        Ok(Broadcast {
+            path: path.to_path_buf(),
+            listener,
+            spectators: Vec::new(),
+            copy: None,
+            line: String::new(),
+        })
+    }
+
+    /// Send the tick just played with the direction to all the spectators, and the whole game
+    /// to the ones who just came
+    pub fn send(&mut self, game: &Game, dir: Direction) {
+        let mut joined = Vec::new();
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(false).is_ok() && stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_ok() {
+                joined.push(stream);
+            }
+        }
+
+        if self.spectators.is_empty() && joined.is_empty() {
+            self.copy = None;
+            return;
+        }
+
+        // The spectators play the tick the same way, unless the game was changed in between
+        let same = self.copy.as_mut().is_some_and(|copy| {
+            copy.tick(dir);
+            copy.state_hash() == game.state_hash()
+        });
+
+        if same {
+            self.line.clear();
+            writeln!(self.line, "tick {:016x} {}", game.state_hash(), codec::encode("input", &dir)).unwrap();
+            Broadcast::write(&mut self.spectators, &self.line);
+        } else {
+            joined.append(&mut self.spectators);
+            self.copy = Some(game.clone());
+        }
+
+        if !joined.is_empty() {
+            self.line.clear();
+            writeln!(self.line, "game {:016x} {}", game.state_hash(), codec::encode("game", game)).unwrap();
+            Broadcast::write(&mut joined, &self.line);
+        }
+
+        self.spectators.append(&mut joined);
+    }
+
+    /// Whoever couldn't take the whole line is gone, or too slow to keep up
+    fn write(spectators: &mut Vec<UnixStream>, line: &str) {
+        spectators.retain_mut(|stream| stream.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+impl Drop for Broadcast {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The caption typed while spectating
+#[derive(Default)]
+struct Typing {
+    /// The tick of the last state that came in
+    tick: u64,
+    /// The tick the caption being typed goes with, and what's typed so far
+    caption: Option<(u64, String)>,
+    /// The game is over, the next key quits
+    ended: bool,
+}
+
+/// Watch the game running in another terminal. Escape stops watching.
+///
+/// With a captions file, enter starts typing a caption for the tick it was pressed on, and
+/// enter again adds it to the file. That file goes next to the replay of the game, the captions
+/// are then shown while it's watched
+pub fn run(path: &Path, subs: Option<&Path>) {
+    let stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            eprintln!("There is no game to watch, start one in another terminal first");
+            exit(1);
+        },
+        Err(e) => error::report(Error::Spectate(e)),
+    };
+
+    let term = Term::stdout();
+    if let Err(e) = title::push(&term, "Snek! (spectating)") {
+        error::report(Error::Terminal(e));
+    }
+
+    let typing = Arc::new(Mutex::new(Typing::default()));
+    let typing_thread = Arc::clone(&typing);
+
+    thread::spawn(move || {
+        let term = Term::stdout();
+
+        match show(BufReader::new(stream), &term, &typing_thread) {
+            // Quitting is left to the keys: the terminal is raw until the read going on there is over
+            Ok(()) => {
+                typing_thread.lock().unwrap().ended = true;
+
+                let mut frame = Frame::default();
+                frame.begin();
+                let _ = writeln!(frame, "The game has ended");
+                let _ = writeln!(frame);
+                let _ = writeln!(frame, "Press any key to quit");
+                if let Err(e) = frame.present(&mut io::stdout()) {
+                    error::hand_over(Error::Terminal(e));
+                }
+            },
+            Err(e) => error::hand_over(e),
+        }
+    });
+
+    loop {
+        let key = error::read_key(&term);
+        let mut typing = typing.lock().unwrap();
+
+        if typing.ended {
+            let _ = title::pop(&term);
+            println!("The game has ended");
+            exit(0);
+        }
+
+        match (key, typing.caption.take(), subs) {
+            // Escape while typing only drops the caption
+            (Escape, None, _) => {
+                let _ = title::pop(&term);
+                exit(0);
+            },
+            (Enter, None, Some(_)) => typing.caption = Some((typing.tick, String::new())),
+            (Enter, Some((tick, text)), Some(subs)) => {
+                if !text.trim().is_empty() {
+                    if let Err(e) = subtitles::append(subs, tick, text.trim()) {
+                        error::fail(&term, Error::Subtitles(subs.to_path_buf(), e));
+                    }
+                }
+            },
+            (Char(ch), Some((tick, mut text)), _) => {
+                text.push(ch);
+                typing.caption = Some((tick, text));
+            },
+            (Backspace, Some((tick, mut text)), _) => {
+                text.pop();
+                typing.caption = Some((tick, text));
+            },
+            (Escape, Some(_), _) => {},
+            (_, caption, _) => typing.caption = caption,
+        }
+    }
+}
+
+/// The game once the line sent by the broadcast is read: the whole game, or the copy played
+/// by a tick. Makes sure it's the same game that was sent
+fn decode(line: &str, copy: Option<Game>) -> io::Result<Game> {
+    let broken = || io::Error::new(ErrorKind::InvalidData, "not a game state");
+
+    let mut parts = line.splitn(3, ' ');
+    let (what, hash, text) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(what), Some(hash), Some(text)) => (what, hash, text),
+        _ => return Err(broken()),
+    };
+    let hash = u64::from_str_radix(hash, 16).map_err(|_| broken())?;
+
+    let game = match (what, copy) {
+        ("game", _) => codec::decode("game", text)?,
+        ("tick", Some(mut game)) => {
+            game.tick(codec::decode("input", text)?);
+            game
+        },
+        ("tick", None) => return Err(io::Error::new(ErrorKind::InvalidData, "a tick came before the game")),
+        _ => return Err(broken()),
+    };
+
+    if game.state_hash() != hash {
+        return Err(io::Error::new(ErrorKind::InvalidData, format!("out of sync with the game at tick {}", game.ticks)));
+    }
+
+    Ok(game)
+}
+
+/// Draw every state coming from the game, until the game ends
+fn show(stream: impl BufRead, term: &Term, typing: &Mutex<Typing>) -> error::Result<()> {
+    let mut frame = render::Frame::default();
+    let mut stdout = io::stdout();
+
+    let mut copy = None;
+
+    for line in stream.lines() {
+        let line = line.map_err(Error::Spectate)?;
+        let game = decode(&line, copy.take()).map_err(Error::Spectate)?;
+
+        let caption = {
+            let mut typing = typing.lock().unwrap();
+            typing.tick = game.ticks;
+            typing.caption.clone()
+        };
+
+        match Scale::pick(term, &game, 2) {
+            Ok(scale) => {
+                frame.begin();
+                frame.field(&game, scale);
+
+                let state = if game.over { "  GAME OVER!" } else { "" };
+                writeln!(frame, "score {}  len {}{}", game.score, game.length, state).unwrap();
+
+                // The caption only shows up as fast as the states come in, ten times a second
+                // is fast enough for typing
+                if let Some((tick, text)) = caption {
+                    writeln!(frame, "caption at tick {}: {}_", tick, text).unwrap();
+                }
+            },
+            Err(size) => frame.too_small(size),
+        }
+
+        frame.present(&mut stdout).map_err(Error::Terminal)?;
+        copy = Some(game);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+    use crate::game::{tests::small, Direction::*, Polarity::*};
+    use crate::ruleset::Mutator;
+    use crate::tuning::{Command, Console};
+
+    #[test]
+    fn a_spectator_plays_the_ticks_it_gets() {
+        let path = std::env::temp_dir().join(format!("snake-video-spectate-{}.sock", std::process::id()));
+        let mut broadcast = Broadcast::start(&path).unwrap();
+        let mut game = small(Mutator::Wrap, &[(1, 1), (2, 1)]);
+
+        let mut lines = BufReader::new(UnixStream::connect(&path).unwrap()).lines();
+        let mut tick = |game: &mut Game| {
+            game.tick(Ver(Pos));
+            broadcast.send(game, Ver(Pos));
+        };
+
+        tick(&mut game);
+        tick(&mut game);
+
+        // Food from the console isn't a tick, the whole game comes again
+        Console::default().run(Command::Food(Cell { row: 4, col: 4 }), &mut game).unwrap();
+        tick(&mut game);
+
+        let mut copy = None;
+        for what in ["game", "tick", "game"] {
+            let line = lines.next().unwrap().unwrap();
+            assert!(line.starts_with(what), "{}", line);
+
+            copy = Some(decode(&line, copy.take()).unwrap());
+        }
+        assert_eq!(copy.unwrap().state_hash(), game.state_hash());
+
+        let line = format!("tick {:016x} {}", game.state_hash(), codec::encode("input", &Hor(Pos)));
+        assert!(decode(&line, None).is_err());
+        assert!(decode(&line, Some(game.clone())).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// How long a caption without an end stays up, unless the next one comes first
+const DEFAULT_TICKS: u64 = 30;
+
+/// A line of text shown during the ticks from `from` to `to`, both included
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Caption {
+    pub from: u64,
+    pub to: u64,
+    pub text: String,
+}
+
+/// Captions for a replay: what to say about it, shown below the field while it plays. They're
+/// kept next to the replay in a text file of their own, so they can be written by hand:
+///
+/// ```text
+/// snek-subs 1
+/// 120 The snake goes for the far food first
+/// 300-340 Close one!
+/// ```
+///
+/// A caption is shown from its tick on, until the tick after the dash or for three seconds,
+/// or until the next caption comes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Subtitles {
+    pub captions: Vec<Caption>,
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+/// Where the captions of a replay are kept: `run.snkrep` has them in `run.subs`
+pub fn sidecar(replay: &Path) -> PathBuf {
+    replay.with_extension("subs")
+}
+
+impl Subtitles {
+    pub fn parse(text: &str) -> io::Result<Subtitles> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        if lines.next().map(|(_, line)| line) != Some("snek-subs 1") {
+            return Err(invalid(1, "not a subtitles file"));
+        }
+
+        // (from, to if given, text)
+        let mut timed: Vec<(u64, Option<u64>, String)> = Vec::new();
+
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let (ticks, text) = line.split_once(' ').ok_or_else(|| invalid(n, "expected a tick and a text"))?;
+
+            let (from, to) = match ticks.split_once('-') {
+                Some((from, to)) => (from, Some(to)),
+                None => (ticks, None),
+            };
+
+            let from: u64 = from.parse().map_err(|_| invalid(n, "expected a tick"))?;
+            let to: Option<u64> = match to {
+                Some(to) => Some(to.parse().ok().filter(|to| *to >= from).ok_or_else(|| invalid(n, "expected a tick after the first one"))?),
+                None => None,
+            };
+
+            timed.push((from, to, text.trim().to_string()));
+        }
+
+        timed.sort_by_key(|(from, _, _)| *from);
+
+        let starts: Vec<u64> = timed.iter().map(|(from, _, _)| *from).collect();
+        let captions = timed
+            .into_iter()
+            .enumerate()
+            .map(|(i, (from, to, text))| {
+                let next = starts.get(i + 1).map(|next| next.saturating_sub(1).max(from));
+
+                Caption {
+                    from,
+                    to: to.unwrap_or_else(|| next.unwrap_or(u64::MAX).min(from.saturating_add(DEFAULT_TICKS - 1))),
+                    text,
+                }
+            })
+            .collect();
+
+        Ok(Subtitles { captions })
+    }
+
+    /// The captions of a replay, if it has any
+    pub fn load(path: &Path) -> io::Result<Subtitles> {
+        match fs::read_to_string(path) {
+            Ok(text) => Subtitles::parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Subtitles::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// What to show at the tick. When captions overlap, the one that came last wins
+    pub fn at(&self, tick: u64) -> Option<&str> {
+        self.captions
+            .iter()
+            .rev()
+            .find(|caption| (caption.from..=caption.to).contains(&tick))
+            .map(|caption| caption.text.as_str())
+    }
+}
+
+/// Add a caption at the end of a subtitles file, starting it if it's not there yet
+pub fn append(path: &Path, tick: u64, text: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "snek-subs 1")?;
+    }
+
+    // A caption is one line
+    writeln!(file, "{} {}", tick, text.replace(['\n', '\r'], " "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &str) -> Subtitles {
+        Subtitles::parse(&format!("snek-subs 1\n{}", lines)).unwrap()
+    }
+
+    fn caption(from: u64, to: u64, text: &str) -> Caption {
+        Caption {
+            from,
+            to,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn a_caption_without_an_end_stays_up_for_a_while() {
+        assert_eq!(parse("120 Far food first").captions, vec![caption(120, 149, "Far food first")]);
+    }
+
+    #[test]
+    fn a_caption_without_an_end_goes_when_the_next_one_comes() {
+        let subtitles = parse("130 Second\n120 First");
+
+        assert_eq!(subtitles.captions, vec![caption(120, 129, "First"), caption(130, 159, "Second")]);
+        assert_eq!(subtitles.at(129), Some("First"));
+        assert_eq!(subtitles.at(130), Some("Second"));
+        assert_eq!(subtitles.at(160), None);
+    }
+
+    #[test]
+    fn the_last_of_overlapping_captions_wins() {
+        let subtitles = parse("300-340 Close one!\n320 Again");
+
+        assert_eq!(subtitles.at(310), Some("Close one!"));
+        assert_eq!(subtitles.at(325), Some("Again"));
+        assert_eq!(subtitles.at(345), Some("Again"));
+        assert_eq!(subtitles.at(350), None);
+    }
+
+    #[test]
+    fn a_caption_at_the_last_tick_ends_there() {
+        let last = u64::MAX - 1;
+
+        assert_eq!(parse(&format!("{} The end", last)).captions, vec![caption(last, u64::MAX, "The end")]);
+    }
+
+    #[test]
+    fn an_end_before_the_start_is_refused() {
+        assert!(Subtitles::parse("snek-subs 1\n50-40 Backwards").is_err());
+        assert!(Subtitles::parse("50 Not a subtitles file").is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use std::fmt::Write;
+use std::io;
+use std::time::{Duration, Instant};
+
+use console::Term;
+
+use crate::render::Frame;
+
+/// Don't touch the title more often than this. Some terminals (and window managers) get
+/// noticeably slow when the title changes on every frame
+const THROTTLE: Duration = Duration::from_millis(500);
+
+/// Keeps the terminal title in sync with the game
+#[derive(Default)]
+pub struct Title {
+    shown: String,
+    // Where the next title is put together. Keeping it around means no allocation per frame
+    next: String,
+    last_update: Option<Instant>,
+}
+
+fn set(term: &Term, title: &str) -> io::Result<()> {
+    term.write_str(&format!("\x1b]0;{}\x07", title))
+}
+
+/// Remember the current title of the terminal and set our own one.
+///
+/// There is no reliable way to read the title of a terminal, so we ask the terminal to push
+/// it on its title stack instead. xterm and most of its descendants support it, the others
+/// just ignore the sequence.
+pub fn push(term: &Term, title: &str) -> io::Result<()> {
+    term.write_str("\x1b[22;0t")?;
+    set(term, title)?;
+    term.flush()
+}
+
+/// Bring back the title saved by `push`
+pub fn pop(term: &Term) -> io::Result<()> {
+    term.write_str("\x1b[23;0t")?;
+    term.flush()
+}
+
+impl Title {
+    /// Show the live score and length in the title. The new title is added to the frame only
+    /// if it differs from the current one and the last update was long enough ago
+    pub fn update(&mut self, frame: &mut Frame, score: u32, length: usize) {
+        if let Some(last_update) = self.last_update {
+            if last_update.elapsed() < THROTTLE {
+                return;
+            }
+        }
+
+        self.next.clear();
+        write!(self.next, "Snek! — {} pts, len {}", score, length).unwrap();
+
+        if self.next != self.shown {
+            frame.raw("\x1b]0;");
+            frame.raw(&self.next);
+            frame.raw("\x07");
+
+            std::mem::swap(&mut self.shown, &mut self.next);
+            self.last_update = Some(Instant::now());
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use console::Key;
+
+use crate::challenge::Challenge;
+use crate::config::{key_name, Action};
+use crate::game::{Direction::*, Game, Polarity::*};
+use crate::replay::Replay;
+
+/// Something that happened at some time during a game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Entry {
+    /// The game moved one step forward
+    Tick,
+    /// A key was pressed, with what it did then and the name of the key
+    Key(Option<Action>, String),
+}
+
+/// Every key the player pressed and when, next to when the ticks of the game happened.
+///
+/// A replay only keeps the direction the snake went on every tick, which is all the game needs
+/// to play it again. A trace keeps the human side of it: when a key came in and how long
+/// before the next tick. That's what it takes to look into "the snake didn't turn when I
+/// pressed the key", and to play the same human timing through a newer engine.
+///
+/// Traces are text, one entry per line, the time being in microseconds since the start:
+///
+/// ```text
+/// snek-trace 1
+/// challenge snek1:classic/30x15/wrap:1234:1a2b3c4d
+/// 100213 tick
+/// 153097 key up Up
+/// 200398 tick
+/// ```
+pub struct Trace {
+    pub challenge: Challenge,
+    pub entries: Vec<(Duration, Entry)>,
+}
+
+fn invalid(line: usize, msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+}
+
+/// Writes a trace while the game is played
+pub struct Recorder {
+    started: Instant,
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, challenge: &Challenge) -> io::Result<Recorder> {
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(out, "snek-trace 1")?;
+        writeln!(out, "challenge {}", challenge.code())?;
+
+        Ok(Recorder {
+            started: Instant::now(),
+            out,
+        })
+    }
+
+    fn micros(&self) -> u128 {
+        self.started.elapsed().as_micros()
+    }
+
+    pub fn key(&mut self, key: &Key, action: Option<Action>) -> io::Result<()> {
+        let action = action.map(|action| action.name()).unwrap_or("-");
+        writeln!(self.out, "{} key {} {}", self.micros(), action, key_name(key))
+    }
+
+    pub fn tick(&mut self) -> io::Result<()> {
+        writeln!(self.out, "{} tick", self.micros())
+    }
+
+    /// The trace is buffered, so this has to be called before the game quits
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> io::Result<Trace> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+
+        if lines.next().map(|(_, line)| line) != Some("snek-trace 1") {
+            return Err(invalid(1, "not an input trace"));
+        }
+
+        let challenge = lines
+            .next()
+            .and_then(|(_, line)| Challenge::parse(line.strip_prefix("challenge ")?))
+            .ok_or_else(|| invalid(2, "expected the challenge the game was played with"))?;
+
+        let mut entries: Vec<(Duration, Entry)> = Vec::new();
+
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let mut parts = line.splitn(4, ' ');
+
+            let at = parts
+                .next()
+                .and_then(|micros| micros.parse().ok())
+                .map(Duration::from_micros)
+                .ok_or_else(|| invalid(n, "expected a time"))?;
+
+            if entries.last().is_some_and(|(last, _)| *last > at) {
+                return Err(invalid(n, "the entries have to be in order"));
+            }
+
+            let entry = match (parts.next(), parts.next(), parts.next()) {
+                (Some("tick"), None, None) => Entry::Tick,
+                (Some("key"), Some(action), Some(key)) => {
+                    let action = match action {
+                        "-" => None,
+                        name => Some(Action::from_name(name).ok_or_else(|| invalid(n, "unknown action"))?),
+                    };
+
+                    Entry::Key(action, key.to_string())
+                },
+                _ => return Err(invalid(n, "expected a tick or a key")),
+            };
+
+            entries.push((at, entry));
+        }
+
+        Ok(Trace { challenge, entries })
+    }
+
+    pub fn load(path: &Path) -> io::Result<Trace> {
+        Trace::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Play the game again with the keys pressed at the same times, relative to the ticks.
+    /// With the engine the trace was recorded with, that's the same game as the replay saved
+    /// at the time
+    pub fn replay(&self) -> Replay {
+        let mut game = Game::new(&self.challenge);
+        let mut replay = Replay::new(self.challenge.clone());
+
+        // The same as the game does with the keys from the terminal
+        let mut dir_current = game.dir;
+        let mut dir_next = game.dir;
+
+        for (_, entry) in self.entries.iter() {
+            let action = match entry {
+                Entry::Tick => {
+                    dir_current = dir_next;
+
+                    let events = game.tick(dir_current);
+                    replay.record(&game, &events);
+
+                    if game.over {
+                        break;
+                    }
+
+                    continue;
+                },
+                Entry::Key(action, _) => action,
+            };
+
+            let dir = match action {
+                Some(Action::Left) => Hor(Neg),
+                Some(Action::Right) => Hor(Pos),
+                Some(Action::Up) => Ver(Neg),
+                Some(Action::Down) => Ver(Pos),
+                _ => continue,
+            };
+
+            if dir_current.can_turn_to(dir) {
+                dir_next = dir;
+            }
+        }
+
+        replay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ruleset::Ruleset;
+
+    fn challenge() -> Challenge {
+        Challenge {
+            ruleset: Ruleset::default(),
+            seed: 1234,
+        }
+    }
+
+    fn trace(entries: &str) -> io::Result<Trace> {
+        Trace::parse(&format!("snek-trace 1\nchallenge {}\n{}", challenge().code(), entries))
+    }
+
+    #[test]
+    fn a_trace_has_the_keys_and_the_ticks_in_order() {
+        let trace = trace("100 tick\n150 key up Up\n151 key - x\n\n200 tick\n").unwrap();
+
+        assert_eq!(trace.challenge.code(), challenge().code());
+        assert_eq!(
+            trace.entries,
+            [
+                (Duration::from_micros(100), Entry::Tick),
+                (Duration::from_micros(150), Entry::Key(Some(Action::Up), "Up".to_string())),
+                (Duration::from_micros(151), Entry::Key(None, "x".to_string())),
+                (Duration::from_micros(200), Entry::Tick),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_broken_trace_says_where() {
+        let error = |trace: io::Result<Trace>| trace.err().map(|e| e.to_string()).unwrap_or_default();
+
+        assert_eq!(error(Trace::parse("snek-replay 1")), "line 1: not an input trace");
+        assert_eq!(
+            error(Trace::parse("snek-trace 1\nchallenge snek1")),
+            "line 2: expected the challenge the game was played with"
+        );
+        assert_eq!(error(trace("200 tick\n100 tick")), "line 4: the entries have to be in order");
+        assert_eq!(error(trace("100 key jump j")), "line 3: unknown action");
+        assert_eq!(error(trace("soon tick")), "line 3: expected a time");
+        assert_eq!(error(trace("100 tock")), "line 3: expected a tick or a key");
+    }
+
+    #[test]
+    fn a_key_turns_the_snake_on_the_next_tick() {
+        let trace = trace("100 tick\n150 key up Up\n160 key left Left\n200 tick\n300 tick").unwrap();
+
+        // Left can't come right after up, the snake hasn't gone up yet
+        assert_eq!(trace.replay().inputs, [Hor(Pos), Ver(Neg), Ver(Neg)]);
+    }
+
+    #[test]
+    fn a_recorded_trace_loads() {
+        let path = std::env::temp_dir().join(format!("snake-video-trace-{}.txt", std::process::id()));
+
+        let mut recorder = Recorder::create(&path, &challenge()).unwrap();
+        recorder.tick().unwrap();
+        recorder.key(&Key::ArrowDown, Some(Action::Down)).unwrap();
+        recorder.tick().unwrap();
+        recorder.flush().unwrap();
+
+        let trace = Trace::load(&path).unwrap();
+        let entries: Vec<&Entry> = trace.entries.iter().map(|(_, entry)| entry).collect();
+        assert_eq!(entries, [&Entry::Tick, &Entry::Key(Some(Action::Down), key_name(&Key::ArrowDown)), &Entry::Tick]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+use console::Key;
+
+use crate::board::{Cell, Entity, Tile};
+use crate::game::Game;
+use crate::render::Frame;
+
+/// The fastest and the slowest a tick can be set to, in milliseconds
+const FASTEST: u64 = 10;
+const SLOWEST: u64 = 2000;
+
+const HELP: &str = "set speed MS, spawn food ROW COL, kill or seed N";
+
+/// A command of the console
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    /// Every tick takes this long from now on, boosting or not
+    Speed(Duration),
+    /// Put food on the field there
+    Food(Cell),
+    /// The snake dies right away
+    Kill,
+    /// The food to come is placed with another seed
+    Seed(u64),
+}
+
+/// A console to tune the game while it's played, for trying out new rules without playing a
+/// whole game to get where they matter. The game waits while it's open.
+///
+/// Commands are typed in and run with enter:
+///
+/// ```text
+/// set speed 50      a tick every 50 ms
+/// spawn food 3 7    food on row 3, column 7, counted from 0
+/// kill              game over
+/// seed 42           the food to come is placed with seed 42
+/// ```
+///
+/// A game tuned from the console doesn't play the way its challenge says anymore, so none of
+/// it is kept after the game over: no high score, no replay
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    line: String,
+    /// What the last command did, or what was wrong with it
+    reply: String,
+    /// The tick set with `set speed`
+    pub speed: Option<Duration>,
+    /// A command changed the game
+    pub used: bool,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| word.parse::<u64>().map_err(|_| format!("{:?} isn't a number", word));
+
+        match words[..] {
+            ["set", "speed", ms] => match number(ms)? {
+                ms @ FASTEST..=SLOWEST => Ok(Command::Speed(Duration::from_millis(ms))),
+                _ => Err(format!("the speed is {} to {} ms a tick", FASTEST, SLOWEST)),
+            },
+            ["spawn", "food", row, col] => Ok(Command::Food(Cell {
+                row: number(row)? as usize,
+                col: number(col)? as usize,
+            })),
+            ["kill"] => Ok(Command::Kill),
+            ["seed", seed] => Ok(Command::Seed(number(seed)?)),
+            _ => Err(format!("unknown command, try {}", HELP)),
+        }
+    }
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.line.clear();
+    }
+
+    /// A key typed into the console, enter runs the line
+    pub fn key(&mut self, key: &Key, game: &mut Game) {
+        match key {
+            Key::Char(ch) => self.line.push(*ch),
+            Key::Backspace => {
+                self.line.pop();
+            },
+            Key::Enter => {
+                let line = std::mem::take(&mut self.line);
+
+                self.reply = match Command::parse(&line).and_then(|command| self.run(command, game)) {
+                    Ok(reply) => reply,
+                    Err(e) => e,
+                };
+            },
+            _ => {},
+        }
+    }
+
+    /// Apply a command to the game, saying what it did
+    pub fn run(&mut self, command: Command, game: &mut Game) -> Result<String, String> {
+        match command {
+            Command::Speed(tick) => self.speed = Some(tick),
+            Command::Food(cell) => {
+                if cell.row >= game.rows() || cell.col >= game.cols() {
+                    return Err(format!("the field is {} rows by {} columns", game.rows(), game.cols()));
+                }
+
+                if game.field.tile(cell) != Tile::Empty || game.field.entity(cell).is_some() {
+                    return Err("there's something there already".to_string());
+                }
+
+                game.field.put_entity(cell, Entity::Food);
+            },
+            Command::Kill => game.over = true,
+            Command::Seed(seed) => game.reseed(seed),
+        }
+
+        self.used = true;
+
+        Ok(match command {
+            Command::Speed(tick) => format!("a tick every {} ms", tick.as_millis()),
+            Command::Food(cell) => format!("food on row {}, column {}", cell.row, cell.col),
+            Command::Kill => "killed".to_string(),
+            Command::Seed(seed) => format!("the food comes with seed {} now", seed),
+        })
+    }
+
+    /// The line being typed and what the last command did, on one line
+    pub fn draw(&self, frame: &mut Frame) {
+        write!(frame, "> {}_", self.line).unwrap();
+
+        if !self.reply.is_empty() {
+            write!(frame, "  ({})", self.reply).unwrap();
+        }
+
+        writeln!(frame).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::small;
+    use crate::ruleset::Mutator;
+
+    #[test]
+    fn commands_are_parsed_or_refused() {
+        assert_eq!(Command::parse("spawn food 3 4"), Ok(Command::Food(Cell { row: 3, col: 4 })));
+        assert!(Command::parse("set speed 5").is_err());
+        assert!(Command::parse("jump").is_err());
+    }
+
+    #[test]
+    fn the_console_tunes_the_running_game() {
+        let mut game = small(Mutator::Wrap, &[(1, 1), (2, 1)]);
+        let mut console = Console::default();
+
+        assert!(console.run(Command::Food(Cell { row: 9, col: 0 }), &mut game).is_err());
+        assert!(console.run(Command::Food(Cell { row: 2, col: 1 }), &mut game).is_err());
+        assert!(!console.used);
+
+        console.run(Command::Food(Cell { row: 3, col: 4 }), &mut game).unwrap();
+        assert_eq!(game.field.entity(Cell { row: 3, col: 4 }), Some(Entity::Food));
+
+        console.run(Command::Kill, &mut game).unwrap();
+        assert!(game.over && console.used);
+    }
+}
@@ -0,0 +1,223 @@
+//! Golden replays: recorded runs kept in `tests/golden`, played again from scratch and checked
+//! against what they ended with when they were recorded. A change to the engine that plays any of
+//! them differently fails here, even if the game still looks fine.
+//!
+//! The expected results are in `tests/golden/expected.tsv`, one line per replay:
+//!
+//! ```text
+//! file    score   length  ticks   hash
+//! ```
+//!
+//! When the engine is changed on purpose, run the tests with `SNEK_BLESS=1` to write the new
+//! results (and review the diff of the file before committing it).
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use snake_video::codec;
+use snake_video::game::Game;
+use snake_video::replay::Replay;
+use snake_video::rng::{self, Stream};
+
+const EXPECTED: &str = "expected.tsv";
+
+struct Outcome {
+    score: u32,
+    length: usize,
+    ticks: u64,
+    hash: u64,
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+/// All the replays in the golden directory, sorted by name
+fn replays() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(golden_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".snkrep"))
+        .collect();
+
+    names.sort();
+    names
+}
+
+/// Play the replay from scratch, checking the events and keyframes stored in it along the way
+fn resimulate(name: &str, replay: &Replay) -> Outcome {
+    let mut game = Game::new(&replay.challenge);
+    let mut events = Vec::new();
+    let mut keyframes = replay.keyframes.iter().peekable();
+
+    for dir in replay.inputs.iter() {
+        for event in game.tick(*dir) {
+            events.push((game.ticks, event));
+        }
+
+        if let Some(keyframe) = keyframes.next_if(|k| k.ticks == game.ticks) {
+            assert_eq!(*keyframe, game.snapshot(), "{}: keyframe at tick {}", name, game.ticks);
+        }
+
+        if game.over {
+            break;
+        }
+    }
+
+    assert!(keyframes.next().is_none(), "{}: keyframes past the end of the run", name);
+    assert_eq!(events, replay.events, "{}: events", name);
+
+    Outcome {
+        score: game.score,
+        length: game.length,
+        ticks: game.ticks,
+        hash: game.hash(),
+    }
+}
+
+fn parse_expected(line: &str) -> (String, Outcome) {
+    let parts: Vec<&str> = line.split('\t').collect();
+    assert_eq!(parts.len(), 5, "bad line in {}: {:?}", EXPECTED, line);
+
+    let outcome = Outcome {
+        score: parts[1].parse().unwrap(),
+        length: parts[2].parse().unwrap(),
+        ticks: parts[3].parse().unwrap(),
+        hash: u64::from_str_radix(parts[4], 16).unwrap(),
+    };
+
+    (parts[0].to_string(), outcome)
+}
+
+fn bless() {
+    let mut out = String::new();
+
+    for name in replays() {
+        let replay = Replay::load(&golden_dir().join(&name)).unwrap();
+        let outcome = resimulate(&name, &replay);
+
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{:016x}\n",
+            name, outcome.score, outcome.length, outcome.ticks, outcome.hash,
+        ));
+    }
+
+    fs::write(golden_dir().join(EXPECTED), out).unwrap();
+}
+
+#[test]
+fn golden_replays() {
+    if env::var_os("SNEK_BLESS").is_some() {
+        bless();
+    }
+
+    let expected = fs::read_to_string(golden_dir().join(EXPECTED)).unwrap();
+    let expected: Vec<(String, Outcome)> = expected.lines().map(parse_expected).collect();
+
+    let listed: Vec<&str> = expected.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(listed, replays(), "every golden replay needs a line in {}", EXPECTED);
+
+    for (name, want) in expected.iter() {
+        let replay = Replay::load(&golden_dir().join(name)).unwrap();
+        let got = resimulate(name, &replay);
+
+        assert_eq!(got.score, want.score, "{}: score", name);
+        assert_eq!(got.length, want.length, "{}: length", name);
+        assert_eq!(got.ticks, want.ticks, "{}: ticks", name);
+        assert_eq!(got.hash, want.hash, "{}: board hash {:016x}", name, got.hash);
+    }
+}
+
+/// The same run saved in the current format and in the old text one has to play the same
+#[test]
+fn legacy_replays_match() {
+    let pairs = [
+        ("small-greedy-11.snkrep", "legacy-v2-small-11.snkrep"),
+        ("walls-random-5.snkrep", "legacy-v1-walls-5.snkrep"),
+    ];
+
+    for (current, legacy) in pairs {
+        let current = Replay::load(&golden_dir().join(current)).unwrap();
+        let legacy = Replay::load(&golden_dir().join(legacy)).unwrap();
+
+        assert_eq!(current.challenge.code(), legacy.challenge.code());
+        assert_eq!(current.inputs, legacy.inputs);
+        assert_eq!(current.events, legacy.events);
+        assert_eq!(current.keyframes, legacy.keyframes);
+    }
+}
+
+/// The food stream is the one the game used before there were streams, which is what keeps the
+/// replays above playing the same
+#[test]
+fn the_food_stream_is_the_old_generator() {
+    let draw = |mut rng: ChaCha12Rng| -> Vec<u64> { (0..8).map(|_| rng.gen()).collect() };
+
+    for seed in [0, 7, 42, u64::MAX] {
+        let food = draw(rng::stream(seed, Stream::Food));
+
+        assert_eq!(food, draw(ChaCha12Rng::seed_from_u64(seed)));
+        assert_ne!(food, draw(rng::stream(seed.wrapping_add(1), Stream::Food)));
+    }
+}
+
+/// A replay recorded again has hashes that agree with the game, and the first tick that
+/// doesn't agree is the one reported
+#[test]
+fn hashes_find_the_divergence() {
+    let golden = Replay::load(&golden_dir().join("small-greedy-11.snkrep")).unwrap();
+
+    let mut replay = Replay::new(golden.challenge.clone());
+    let mut game = Game::new(&golden.challenge);
+
+    for dir in golden.inputs.iter() {
+        let events = game.tick(*dir);
+        replay.record(&game, &events);
+
+        if game.over {
+            break;
+        }
+    }
+
+    assert_eq!(replay.hashes.len(), replay.inputs.len());
+    assert_eq!(replay.divergence(), None);
+
+    // What spectators check every state they get against
+    let sent: Game = codec::decode("game", &codec::encode("game", &game)).unwrap();
+    assert_eq!(sent.state_hash(), game.state_hash());
+
+    let replay = Replay::parse(&codec::encode("replay", &replay)).unwrap();
+    assert_eq!(replay.divergence(), None);
+
+    let mut broken = replay.clone();
+    broken.hashes[20] ^= 1;
+    assert_eq!(broken.divergence(), Some(21));
+}
+
+/// The golden replays hold up when they're verified, and a replay with more inputs than its
+/// game doesn't
+#[test]
+fn verify_finds_tampered_replays() {
+    let expected = fs::read_to_string(golden_dir().join(EXPECTED)).unwrap();
+
+    for (name, want) in expected.lines().map(parse_expected) {
+        let replay = Replay::load(&golden_dir().join(&name)).unwrap();
+        let verdict = replay.verify();
+
+        assert_eq!(verdict.problems(), Vec::<String>::new(), "{}", name);
+        assert_eq!(verdict.game.score, want.score, "{}: score", name);
+        assert_eq!(verdict.game.length, want.length, "{}: length", name);
+    }
+
+    let mut replay = Replay::load(&golden_dir().join("small-greedy-11.snkrep")).unwrap();
+    replay.inputs.extend_from_within(..5);
+    replay.events.pop();
+
+    let verdict = replay.verify();
+    assert!(!verdict.consistent);
+    assert_eq!(verdict.extra_inputs, 5);
+}
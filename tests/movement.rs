@@ -0,0 +1,369 @@
+//! Random input sequences fed into `Game::tick`, checking that the snake always stays in one
+//! piece whatever the player does.
+
+use std::collections::BTreeSet;
+
+use proptest::prelude::*;
+
+use snake_video::board::{Cell, Entity, Tile};
+use snake_video::boost::Weight;
+use snake_video::challenge::Challenge;
+use snake_video::game::{Direction, Direction::*, Event, Game, Polarity::*, Snapshot, DECAY_TICKS, FOOD_VALUE};
+use snake_video::ruleset::{Mode, Mutator, Ruleset, MIN_SIZE};
+
+fn direction() -> impl Strategy<Value = Direction> {
+    prop_oneof![Just(Ver(Neg)), Just(Ver(Pos)), Just(Hor(Neg)), Just(Hor(Pos))]
+}
+
+fn challenge() -> impl Strategy<Value = Challenge> {
+    let mode = prop_oneof![Just(Mode::Classic), Just(Mode::Zen), Just(Mode::Sequence)];
+
+    let mutators = (any::<bool>(), any::<bool>(), any::<bool>()).prop_map(|(wrap, bounce, assist)| {
+        [(wrap, Mutator::Wrap), (bounce, Mutator::Bounce), (assist, Mutator::Assist)]
+            .into_iter()
+            .filter_map(|(on, mutator)| on.then_some(mutator))
+            .collect::<Vec<_>>()
+    });
+
+    (mode, MIN_SIZE..12usize, MIN_SIZE..16usize, mutators, 0..3u32, any::<u64>()).prop_map(|(mode, rows, cols, mutators, grace, seed)| {
+
+        Challenge {
+            ruleset: Ruleset {
+                mode,
+                rows,
+                cols,
+                mutators,
+                grace,
+                weight: Weight::default(),
+            },
+            seed,
+        }
+    })
+}
+
+/// Whether two cells are next to each other, possibly across the edge of a field that wraps
+fn adjacent(game: &Game, a: (usize, usize), b: (usize, usize)) -> bool {
+    let wrap = game.ruleset.has(Mutator::Wrap);
+
+    let close = |x: usize, y: usize, lim: usize| {
+        let d = x.abs_diff(y);
+        d == 1 || (wrap && d == lim - 1)
+    };
+
+    (a.0 == b.0 && close(a.1, b.1, game.cols())) || (a.1 == b.1 && close(a.0, b.0, game.rows()))
+}
+
+fn check_invariants(game: &Game) -> Result<(), TestCaseError> {
+    let body = game.body();
+    prop_assert_eq!(body.len(), game.length);
+
+    let cells: BTreeSet<_> = body.iter().collect();
+    prop_assert_eq!(cells.len(), body.len(), "the snake overlaps itself");
+
+    for cell in body.iter() {
+        prop_assert!(game.field.contains(*cell), "segment out of the field: {:?}", cell);
+    }
+
+    for pair in body.windows(2) {
+        prop_assert!(
+            adjacent(game, (pair[0].row, pair[0].col), (pair[1].row, pair[1].col)),
+            "the snake is torn apart between {:?} and {:?}",
+            pair[0],
+            pair[1],
+        );
+    }
+
+    // Nothing else on the field is snake
+    let snake_tiles = (0..game.rows())
+        .flat_map(|row| game.field.row(row).iter())
+        .filter(|tile| matches!(tile, Tile::Snake(_)))
+        .count();
+    prop_assert_eq!(snake_tiles, game.length);
+
+    // The hash kept up to date on every change is the one of the whole field
+    prop_assert_eq!(game.field.hash(), game.field.rehash());
+
+    let free = game.rows() * game.cols() - game.length;
+
+    if game.ruleset.mode == Mode::Sequence {
+        // A round of pellets with different numbers, and a new one as soon as the last pellet
+        // is gone
+        let pellets: BTreeSet<u8> = game
+            .field
+            .entities()
+            .filter_map(|(_, e)| match e {
+                Entity::Pellet(n) => Some(n),
+                Entity::Food => None,
+            })
+            .collect();
+
+        prop_assert_eq!(pellets.len(), game.field.entities().count());
+        prop_assert!(pellets.iter().all(|n| (1..=5).contains(n)), "bad pellets {:?}", pellets);
+        prop_assert!(!pellets.is_empty() || free == 0, "no pellets left");
+    } else {
+        // There's always exactly one food, unless the snake took all the room
+        prop_assert_eq!(game.length, game.score as usize + 2);
+
+        let food = game.field.entities().filter(|(_, e)| *e == Entity::Food).count();
+        prop_assert_eq!(food, free.min(1));
+    }
+
+    for (cell, _) in game.field.entities() {
+        prop_assert!(!cells.contains(&cell), "food under the snake at {:?}", cell);
+    }
+
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn snake_stays_whole(challenge in challenge(), inputs in prop::collection::vec(direction(), 0..300)) {
+        let mut game = Game::new(&challenge);
+        check_invariants(&game)?;
+
+        for dir in inputs {
+            let ticks = game.ticks;
+            let score = game.score;
+            let length = game.length;
+            let was_over = game.over;
+            let events = game.tick(dir);
+
+            if was_over {
+                // Nothing happens anymore once the game is over
+                prop_assert!(events.is_empty());
+                prop_assert_eq!(game.ticks, ticks);
+                continue;
+            }
+
+            prop_assert_eq!(game.ticks, ticks + 1);
+            let ate = events.contains(&Event::Ate);
+            let expected = if events.contains(&Event::WrongOrder) { score.saturating_sub(1) } else { score + ate as u32 };
+            prop_assert_eq!(game.score, expected);
+            prop_assert_eq!(game.length, length + ate as usize);
+            prop_assert_eq!(*game.body().last().unwrap(), game.head);
+
+            if game.ruleset.mode == Mode::Zen {
+                prop_assert!(!game.over, "died in zen mode");
+            }
+
+            check_invariants(&game)?;
+        }
+    }
+
+    #[test]
+    fn never_turns_around(challenge in challenge(), inputs in prop::collection::vec(direction(), 0..100)) {
+        let mut game = Game::new(&challenge);
+
+        for dir in inputs {
+            let before = game.dir;
+            game.tick(dir);
+
+            prop_assert!(game.dir == before || before.can_turn_to(game.dir));
+        }
+    }
+
+    #[test]
+    fn same_inputs_same_game(challenge in challenge(), inputs in prop::collection::vec(direction(), 0..200)) {
+        let mut a = Game::new(&challenge);
+        let mut b = Game::new(&challenge);
+
+        for dir in inputs {
+            prop_assert_eq!(a.tick(dir), b.tick(dir));
+        }
+
+        prop_assert_eq!(a.hash(), b.hash());
+    }
+}
+
+/// A snake of five on an 8x8 field without wrapping, its head right below its body and going
+/// left: going up runs into the body, going on to the left is fine
+fn hooked(grace: u32) -> Game {
+    let challenge = Challenge {
+        ruleset: Ruleset {
+            mode: Mode::Classic,
+            rows: 8,
+            cols: 8,
+            mutators: Vec::new(),
+            grace,
+            weight: Weight::default(),
+        },
+        seed: 1,
+    };
+
+    let cell = |row, col| Cell { row, col };
+    let snapshot = Snapshot {
+        ticks: 0,
+        rng_pos: 0,
+        dir: Hor(Neg),
+        score: 0,
+        food: vec![cell(6, 6)],
+        pellets: Vec::new(),
+        body: vec![cell(1, 1), cell(1, 2), cell(1, 3), cell(2, 3), cell(2, 2)],
+        stalled: 0,
+        food_since: 0,
+    };
+
+    Game::from_snapshot(&challenge, &snapshot).unwrap()
+}
+
+#[test]
+fn no_grace_runs_into_the_body() {
+    let mut game = hooked(0);
+
+    assert_eq!(game.tick(Ver(Neg)), vec![Event::Died]);
+    assert!(game.over);
+}
+
+#[test]
+fn grace_ticks_wait_before_the_body_kills() {
+    let mut game = hooked(2);
+    let body = game.body();
+
+    for stalled in 1..=2 {
+        assert!(game.tick(Ver(Neg)).is_empty());
+        assert!(!game.over);
+        assert_eq!(game.stalled, stalled);
+        assert_eq!(game.body(), body, "the snake moved while stalled");
+    }
+
+    assert_eq!(game.tick(Ver(Neg)), vec![Event::Died]);
+    assert!(game.over);
+    assert_eq!(game.ticks, 3);
+}
+
+#[test]
+fn turning_away_in_the_grace_ticks_saves_the_snake() {
+    let mut game = hooked(2);
+
+    game.tick(Ver(Neg));
+    assert_eq!(game.tick(Hor(Neg)), vec![Event::NearMiss]);
+    assert!(!game.over);
+    assert_eq!(game.stalled, 0);
+    assert_eq!(game.head, Cell { row: 2, col: 1 });
+}
+
+#[test]
+fn grace_ticks_dont_forgive_the_edge() {
+    let mut game = hooked(3);
+
+    game.tick(Hor(Neg));
+    game.tick(Hor(Neg));
+    assert_eq!(game.tick(Hor(Neg)), vec![Event::Died]);
+}
+
+#[test]
+fn grace_ticks_are_part_of_the_ruleset() {
+    let ruleset = hooked(3).ruleset;
+
+    assert_eq!(ruleset.fingerprint(), "classic/8x8/-/grace3");
+    assert_eq!(Ruleset::from_fingerprint(&ruleset.fingerprint()), Some(ruleset));
+    assert_eq!(hooked(0).ruleset.fingerprint(), "classic/8x8/-");
+    assert_eq!(Ruleset::from_fingerprint("classic/8x8/-/grace0"), None);
+}
+
+#[test]
+fn stalled_games_seek_the_same() {
+    let mut game = hooked(2);
+    game.tick(Ver(Neg));
+
+    let restored = Game::from_snapshot(&Challenge { ruleset: game.ruleset.clone(), seed: 1 }, &game.snapshot()).unwrap();
+    assert_eq!(restored.stalled, 1);
+    assert_eq!(restored.state_hash(), game.state_hash());
+}
+
+#[test]
+fn decaying_food_is_worth_less_the_longer_it_waits() {
+    let challenge = Challenge {
+        ruleset: Ruleset {
+            mode: Mode::Classic,
+            rows: 8,
+            cols: 8,
+            mutators: vec![Mutator::Decay],
+            grace: 0,
+            weight: Weight::default(),
+        },
+        seed: 1,
+    };
+
+    // Food right in front of the snake, put there `age` ticks before the next one
+    let eat = |age: u64| {
+        let snapshot = Snapshot {
+            ticks: 1000 + age - 1,
+            rng_pos: 0,
+            dir: Hor(Pos),
+            score: 0,
+            food: vec![Cell { row: 4, col: 5 }],
+            pellets: Vec::new(),
+            body: vec![Cell { row: 4, col: 3 }, Cell { row: 4, col: 4 }],
+            stalled: 0,
+            food_since: 1000,
+        };
+
+        let mut game = Game::from_snapshot(&challenge, &snapshot).unwrap();
+        assert_eq!(game.tick(Hor(Pos)), vec![Event::Ate]);
+        assert_eq!(game.food_since, game.ticks, "the new food is fresh");
+        game.score
+    };
+
+    assert_eq!(eat(1), FOOD_VALUE);
+    assert_eq!(eat(DECAY_TICKS - 1), FOOD_VALUE);
+    assert_eq!(eat(DECAY_TICKS), FOOD_VALUE - 1);
+    assert_eq!(eat(DECAY_TICKS * 3 + 5), FOOD_VALUE - 3);
+    assert_eq!(eat(DECAY_TICKS * 100), 1);
+}
+
+/// A game on a 5x5 field with the mutator, with the snake from the tail to the head, going down
+fn small(mutator: Mutator, body: &[(usize, usize)]) -> Game {
+    let challenge = Challenge {
+        ruleset: Ruleset {
+            mode: Mode::Classic,
+            rows: 5,
+            cols: 5,
+            mutators: vec![mutator],
+            grace: 0,
+            weight: Weight::default(),
+        },
+        seed: 1,
+    };
+
+    let snapshot = Snapshot {
+        ticks: 0,
+        rng_pos: 0,
+        dir: Ver(Pos),
+        score: 0,
+        food: vec![Cell { row: 0, col: 0 }],
+        pellets: Vec::new(),
+        body: body.iter().map(|(row, col)| Cell { row: *row, col: *col }).collect(),
+        stalled: 0,
+        food_since: 0,
+    };
+
+    Game::from_snapshot(&challenge, &snapshot).unwrap()
+}
+
+#[test]
+fn the_edge_turns_a_bouncing_snake_clockwise() {
+    let mut game = small(Mutator::Bounce, &[(2, 4), (3, 4), (4, 4)]);
+
+    game.tick(Ver(Pos));
+    assert!(!game.over);
+    assert_eq!(game.dir, Hor(Neg));
+    assert_eq!(game.head, Cell { row: 4, col: 3 });
+}
+
+#[test]
+fn a_bouncing_snake_turns_the_other_way_when_it_has_to() {
+    // Clockwise is the body, but there's room on the other side
+    let mut game = small(Mutator::Bounce, &[(4, 0), (4, 1), (3, 1), (3, 2), (4, 2)]);
+
+    game.tick(Ver(Pos));
+    assert!(!game.over);
+    assert_eq!(game.head, Cell { row: 4, col: 3 });
+}
+
+#[test]
+fn a_boxed_in_snake_still_dies_against_the_edge() {
+    let mut game = small(Mutator::Bounce, &[(4, 2), (4, 3), (3, 3), (3, 4), (4, 4)]);
+
+    assert_eq!(game.tick(Ver(Pos)), vec![Event::Died]);
+}